@@ -0,0 +1,36 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+
+use thiserror::Error;
+
+/// A malformed or unsupported label, ignored while parsing a container's labels.
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("Ignoring label '{label}': {reason}")]
+pub struct LabelParseError {
+    /// Key of the offending label
+    pub label: String,
+    pub reason: String,
+}
+
+/// Collects the [`LabelParseError`]s found while parsing the labels of a single container.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    errors: RefCell<Vec<LabelParseError>>,
+}
+
+impl Diagnostics {
+    pub(crate) fn report(&self, label: impl Into<String>, reason: impl Display) {
+        self.errors.borrow_mut().push(LabelParseError {
+            label: label.into(),
+            reason: reason.to_string(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.borrow().is_empty()
+    }
+
+    pub fn into_errors(self) -> Vec<LabelParseError> {
+        self.errors.into_inner()
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Structural changes between two
+/// [`DynamicConfiguration`](crate::dynamic_configuration::DynamicConfiguration)s, entries being
+/// compared name by name within each section.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigDiff {
+    pub added: Vec<DiffEntry>,
+    pub removed: Vec<DiffEntry>,
+    pub modified: Vec<ModifiedEntry>,
+}
+
+/// A named entry of a section, e.g. the `HTTP router` named `api`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffEntry {
+    pub kind: &'static str,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModifiedEntry {
+    pub entry: DiffEntry,
+    pub fields: Vec<FieldChange>,
+}
+
+/// Change of a field, e.g. `rule` or `loadBalancer.servers`, `None` when absent on one side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    pub path: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    /// Compares the `old` and `new` entries of a section.
+    pub(crate) fn section<V: Serialize>(
+        &mut self,
+        kind: &'static str,
+        old: &BTreeMap<String, V>,
+        new: &BTreeMap<String, V>,
+    ) {
+        let entry = |name: &String| DiffEntry {
+            kind,
+            name: name.clone(),
+        };
+
+        for (name, old_value) in old {
+            match new.get(name) {
+                None => self.removed.push(entry(name)),
+                Some(new_value) => {
+                    let mut fields = vec![];
+                    field_changes(
+                        "",
+                        Some(&to_value(old_value)),
+                        Some(&to_value(new_value)),
+                        &mut fields,
+                    );
+                    if !fields.is_empty() {
+                        self.modified.push(ModifiedEntry {
+                            entry: entry(name),
+                            fields,
+                        });
+                    }
+                }
+            }
+        }
+
+        self.added.extend(
+            new.keys()
+                .filter(|name| !old.contains_key(*name))
+                .map(entry),
+        );
+    }
+}
+
+fn to_value<V: Serialize>(value: &V) -> Value {
+    // Configuration entries only have string keys, which always serialize
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+/// Collects the leaves that differ between `old` and `new`, objects being compared key by key and
+/// anything else, including arrays, as a whole.
+fn field_changes(
+    path: &str,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<FieldChange>,
+) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                field_changes(&path, old.get(key), new.get(key), changes);
+            }
+        }
+        (old, new) if old != new => changes.push(FieldChange {
+            path: path.to_owned(),
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+impl Display for DiffEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} '{}'", self.kind, self.name)
+    }
+}
+
+impl Display for FieldChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let format = |value: &Option<Value>| match value {
+            Some(value) => value.to_string(),
+            None => "(none)".to_owned(),
+        };
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.path,
+            format(&self.old),
+            format(&self.new)
+        )
+    }
+}
+
+/// One line per change, e.g. ``HTTP router 'api' modified, rule: "Host(`a`)" -> "Host(`b`)"``.
+impl Display for ConfigDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut lines = vec![];
+        lines.extend(self.added.iter().map(|entry| format!("{} added", entry)));
+        lines.extend(
+            self.removed
+                .iter()
+                .map(|entry| format!("{} removed", entry)),
+        );
+        lines.extend(self.modified.iter().map(|modified| {
+            let fields: Vec<String> = modified.fields.iter().map(ToString::to_string).collect();
+            format!("{} modified, {}", modified.entry, fields.join(", "))
+        }));
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_section() {
+        let old: BTreeMap<String, Value> = [
+            ("kept".to_owned(), json!({ "rule": "Host(`a`)" })),
+            ("removed".to_owned(), json!({ "rule": "Host(`b`)" })),
+            (
+                "modified".to_owned(),
+                json!({ "rule": "Host(`c`)", "tls": { "certResolver": "le" } }),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let new: BTreeMap<String, Value> = [
+            ("kept".to_owned(), json!({ "rule": "Host(`a`)" })),
+            ("added".to_owned(), json!({ "rule": "Host(`d`)" })),
+            (
+                "modified".to_owned(),
+                json!({ "rule": "Host(`e`)", "tls": {}, "priority": 10 }),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut diff = ConfigDiff::default();
+        diff.section("HTTP router", &old, &new);
+
+        assert_eq!(
+            diff.to_string(),
+            r#"HTTP router 'added' added
+HTTP router 'removed' removed
+HTTP router 'modified' modified, priority: (none) -> 10, rule: "Host(`c`)" -> "Host(`e`)", tls.certResolver: "le" -> (none)"#
+        );
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_section_unchanged() {
+        let entries: BTreeMap<String, Value> =
+            [("kept".to_owned(), json!({ "rule": "Host(`a`)" }))]
+                .into_iter()
+                .collect();
+
+        let mut diff = ConfigDiff::default();
+        diff.section("HTTP router", &entries, &entries.clone());
+
+        assert!(diff.is_empty());
+    }
+}
@@ -1,32 +1,410 @@
-use crate::{extract_traefik_config, TraefikedContainer};
+use crate::diagnostics::Diagnostics;
+use crate::error::ContainerConversionError;
+use crate::template::{has_env_variables, resolve_env_labels};
+use crate::{
+    generated_router, is_traefik_enabled, LabelRegexes, TraefikedContainer, DEFAULT_LABEL_PREFIX,
+};
+use anyhow::anyhow;
+use bollard::models::ContainerSummary;
 use bollard::Docker;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 
-pub async fn get_traefik_labeled_containers() -> anyhow::Result<Vec<TraefikedContainer>> {
+/// Options controlling which Docker containers are picked up and how their labels are read.
+#[derive(Clone, Debug)]
+pub struct DockerOptions {
+    /// Expose containers without a `traefik.enable` label.
+    /// When `false`, only containers labeled `traefik.enable=true` are returned.
+    pub exposed_by_default: bool,
+    /// Rule template for containers without a rule label, `{name}` being replaced by the container name.
+    /// When `None`, containers without a rule label are ignored.
+    pub default_rule: Option<String>,
+    /// Prefix of the labels to read, e.g. `traefik-internal` for `traefik-internal.http.routers.*`.
+    pub label_prefix: String,
+    /// Value of the `{{ domain }}` placeholder in rule and middleware labels.
+    pub domain: Option<String>,
+    /// Only pick up containers having this label, e.g. `traefik.constraint-label=public`.
+    pub constraint: Option<LabelConstraint>,
+    /// Leave out containers having any of these labels, e.g. `traefik.instance=ops`.
+    pub exclude_labels: Vec<LabelConstraint>,
+    /// Name services after the `com.docker.compose.service` label rather than the container,
+    /// prefixed by the compose project when several projects have the same service.
+    pub use_compose_service_name: bool,
+    /// Fail instead of skipping containers or ignoring labels that cannot be parsed.
+    pub strict: bool,
+    /// Resolve `${VARIABLE}`s in label values from the container environment, which requires
+    /// inspecting the containers using them.
+    pub resolve_env: bool,
+    /// Keep containers without ports, their routers targeting services without servers.
+    pub allow_empty_services: bool,
+}
+
+impl Default for DockerOptions {
+    fn default() -> Self {
+        Self {
+            exposed_by_default: true,
+            default_rule: None,
+            label_prefix: DEFAULT_LABEL_PREFIX.to_owned(),
+            domain: None,
+            constraint: None,
+            exclude_labels: vec![],
+            use_compose_service_name: false,
+            strict: false,
+            resolve_env: false,
+            allow_empty_services: false,
+        }
+    }
+}
+
+/// Container selection constraint requiring a label to have a given value.
+///
+/// Parsed from either `Label("<key>", "<value>")` or `<key>=<value>`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelConstraint {
+    pub key: String,
+    pub value: String,
+}
+
+/// Whether a container's labels satisfy the configured constraint, if any.
+fn satisfies_constraint(
+    labels: &HashMap<String, String>,
+    constraint: Option<&LabelConstraint>,
+) -> bool {
+    constraint.is_none_or(|constraint| labels.get(&constraint.key) == Some(&constraint.value))
+}
+
+/// The first of `exclude_labels` a container's labels match, if any.
+fn excluding_label<'a>(
+    labels: &HashMap<String, String>,
+    exclude_labels: &'a [LabelConstraint],
+) -> Option<&'a LabelConstraint> {
+    exclude_labels
+        .iter()
+        .find(|exclude_label| labels.get(&exclude_label.key) == Some(&exclude_label.value))
+}
+
+impl FromStr for LabelConstraint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (key, value) = match s
+            .strip_prefix("Label(")
+            .and_then(|arguments| arguments.strip_suffix(')'))
+        {
+            Some(arguments) => {
+                let (key, value) = arguments
+                    .split_once(',')
+                    .ok_or(anyhow!("Label constraint '{}' needs a key and a value", s))?;
+                (unquote(key), unquote(value))
+            }
+            None => s
+                .split_once('=')
+                .ok_or(anyhow!(
+                    "Constraint '{}' should be either Label(\"<key>\", \"<value>\") or <key>=<value>",
+                    s
+                ))
+                .map(|(key, value)| (Some(key.trim()), Some(value.trim())))?,
+        };
+
+        match (key, value) {
+            (Some(key), Some(value)) if !key.is_empty() => Ok(LabelConstraint {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            }),
+            _ => Err(anyhow!("Invalid label constraint '{}'", s)),
+        }
+    }
+}
+
+/// Content of a `"`- or `` ` ``-quoted constraint argument.
+fn unquote(argument: &str) -> Option<&str> {
+    let argument = argument.trim();
+    ['"', '`'].into_iter().find_map(|quote| {
+        argument
+            .strip_prefix(quote)
+            .and_then(|argument| argument.strip_suffix(quote))
+    })
+}
+
+/// Lists the running containers exposed through Traefik labels.
+///
+/// Label errors are logged along with the container name. In strict mode, any label error
+/// or container conversion failure fails the whole listing instead.
+pub async fn get_traefik_labeled_containers(
+    options: &DockerOptions,
+) -> anyhow::Result<Vec<TraefikedContainer>> {
     let docker = Docker::connect_with_local_defaults()?;
+    let regexes = LabelRegexes::new(&options.label_prefix);
 
-    let containers = docker
+    let summaries: Vec<ContainerSummary> = docker
         .list_containers::<String>(None)
         .await?
-        .iter()
-        .filter(|c| c.labels.as_ref().and_then(extract_traefik_config).is_some())
-        .cloned()
-        .filter_map(|c| c.try_into().ok())
+        .into_iter()
+        .filter(|c| {
+            let no_labels = HashMap::new();
+            let labels = c.labels.as_ref().unwrap_or(&no_labels);
+
+            if let Some(exclude_label) = excluding_label(labels, &options.exclude_labels) {
+                tracing::debug!(
+                    "Container {:?} excluded by label {}={}",
+                    c.names.as_deref().unwrap_or_default(),
+                    exclude_label.key,
+                    exclude_label.value
+                );
+                return false;
+            }
+
+            // Labels are only parsed once, by the conversion, so this only discards the containers
+            // that cannot be exposed without running the label regexes
+            satisfies_constraint(labels, options.constraint.as_ref())
+                && is_traefik_enabled(labels, &regexes, options.exposed_by_default)
+                && (labels
+                    .keys()
+                    .any(|label_key| regexes.is_own_label(label_key))
+                    || generated_router(c, options, &regexes).is_some())
+        })
         .collect();
 
+    let mut containers = Vec::with_capacity(summaries.len());
+    let mut failures = Vec::new();
+
+    for mut c in summaries {
+        let names = c.names.clone().unwrap_or_default();
+        let diagnostics = Diagnostics::default();
+
+        if options.resolve_env {
+            if let Err(e) = resolve_container_env(&docker, &mut c).await {
+                tracing::warn!("Skipping container {:?}: {}", names, e);
+                failures.push(format!("Container {:?}: {}", names, e));
+                continue;
+            }
+        }
+
+        match TraefikedContainer::from_summary(c, options, &regexes, &diagnostics) {
+            // e.g. only a `traefik.enable` label, which is not an error
+            Err(
+                ContainerConversionError::NoTraefikLabels
+                | ContainerConversionError::TraefikDisabled,
+            ) => {}
+            Ok(container) => {
+                for error in diagnostics.into_errors() {
+                    tracing::warn!("Container '{}': {}", container.name, error);
+                    failures.push(format!("Container '{}': {}", container.name, error));
+                }
+                containers.push(container);
+            }
+            Err(e) => {
+                tracing::warn!("Skipping container {:?}: {}", names, e);
+                failures.push(format!("Container {:?}: {}", names, e));
+            }
+        }
+    }
+
+    if options.strict && !failures.is_empty() {
+        return Err(anyhow!(
+            "Refusing to serve a partial configuration in strict mode:\n{}",
+            failures.join("\n")
+        ));
+    }
+
+    qualify_compose_collisions(&mut containers);
+
+    // Docker lists containers in no particular order, which would decide collisions
+    containers.sort_by(|a, b| a.name.cmp(&b.name));
+
     Ok(containers)
 }
 
+/// Resolves the `${VARIABLE}`s of the container labels from its environment, only inspecting
+/// the containers whose labels use them.
+async fn resolve_container_env(docker: &Docker, c: &mut ContainerSummary) -> anyhow::Result<()> {
+    let Some(labels) = c.labels.take() else {
+        return Ok(());
+    };
+
+    if !has_env_variables(&labels) {
+        c.labels = Some(labels);
+        return Ok(());
+    }
+
+    let id = c.id.as_deref().ok_or(anyhow!("No container id found"))?;
+    let env = docker
+        .inspect_container(id, None)
+        .await?
+        .config
+        .and_then(|config| config.env)
+        .unwrap_or_default();
+
+    c.labels = Some(resolve_env_labels(labels, &env)?);
+    Ok(())
+}
+
+/// Prefixes the service names shared by containers of several compose projects
+/// with their project name.
+fn qualify_compose_collisions(containers: &mut [TraefikedContainer]) {
+    let mut projects: HashMap<String, HashSet<Option<String>>> = HashMap::new();
+    for container in containers.iter() {
+        projects
+            .entry(container.service_name.clone())
+            .or_default()
+            .insert(container.compose_project.clone());
+    }
+
+    for container in containers.iter_mut() {
+        if projects[&container.service_name].len() > 1 {
+            let service_name = container.service_name.clone();
+            if container.qualify_with_compose_project() {
+                tracing::info!(
+                    "Service '{}' of container '{}' renamed '{}' as other compose projects declare it",
+                    service_name,
+                    container.name,
+                    container.service_name
+                );
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assertables::*;
     use rstest::*;
 
     use super::*;
+    use crate::labels;
+
+    #[rstest]
+    #[case(r#"Label("traefik.constraint-label", "public")"#)]
+    #[case("Label(`traefik.constraint-label`, `public`)")]
+    #[case("traefik.constraint-label=public")]
+    #[case(" traefik.constraint-label = public ")]
+    fn test_label_constraint_from_str(#[case] constraint: &str) -> anyhow::Result<()> {
+        assert_eq!(
+            constraint.parse::<LabelConstraint>()?,
+            LabelConstraint {
+                key: "traefik.constraint-label".to_owned(),
+                value: "public".to_owned(),
+            }
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("traefik.constraint-label")]
+    #[case("=public")]
+    #[case(r#"Label("traefik.constraint-label")"#)]
+    #[case("Label(traefik.constraint-label, public)")]
+    fn test_label_constraint_from_str_invalid(#[case] constraint: &str) {
+        assert!(constraint.parse::<LabelConstraint>().is_err());
+    }
+
+    #[rstest]
+    #[case(&[("traefik.constraint-label", "public")])]
+    #[case(&[("traefik.constraint-label", "internal")])]
+    #[case(&[])]
+    fn test_satisfies_constraint_without_constraint(#[case] pairs: &[(&str, &str)]) {
+        assert!(satisfies_constraint(&labels(pairs), None));
+    }
+
+    #[rstest]
+    #[case(&[("traefik.constraint-label", "public")], true)]
+    #[case(&[("traefik.constraint-label", "internal")], false)]
+    #[case(&[("traefik.constraint-label", "Public")], false)]
+    #[case(&[], false)]
+    fn test_satisfies_constraint(#[case] pairs: &[(&str, &str)], #[case] expected: bool) {
+        let constraint = LabelConstraint {
+            key: "traefik.constraint-label".to_owned(),
+            value: "public".to_owned(),
+        };
+
+        assert_eq!(
+            satisfies_constraint(&labels(pairs), Some(&constraint)),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(&[("traefik.instance", "ops")], Some("ops"))]
+    #[case(&[("traefik.instance", "backup")], Some("backup"))]
+    #[case(&[("traefik.instance", "public")], None)]
+    #[case(&[], None)]
+    fn test_excluding_label(#[case] pairs: &[(&str, &str)], #[case] expected: Option<&str>) {
+        let exclude_labels = [
+            LabelConstraint {
+                key: "traefik.instance".to_owned(),
+                value: "ops".to_owned(),
+            },
+            LabelConstraint {
+                key: "traefik.instance".to_owned(),
+                value: "backup".to_owned(),
+            },
+        ];
+
+        assert_eq!(
+            excluding_label(&labels(pairs), &exclude_labels)
+                .map(|exclude_label| exclude_label.value.as_str()),
+            expected
+        );
+    }
+
+    fn compose_container(name: &str, compose_project: Option<&str>) -> TraefikedContainer {
+        TraefikedContainer::from_summary(
+            ContainerSummary {
+                names: Some(vec![format!("/{}", name)]),
+                ports: Some(vec![]),
+                labels: Some(labels(&[
+                    ("traefik.http.routers.web.rule", "Host(`web.local`)"),
+                    ("com.docker.compose.service", "web"),
+                    (
+                        "com.docker.compose.project",
+                        compose_project.unwrap_or_default(),
+                    ),
+                ])),
+                ..Default::default()
+            },
+            &DockerOptions {
+                use_compose_service_name: true,
+                ..Default::default()
+            },
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_qualify_compose_collisions() {
+        let mut containers = vec![
+            compose_container("shop-web-1", Some("shop")),
+            compose_container("blog-web-1", Some("blog")),
+            compose_container("web", None),
+        ];
+
+        qualify_compose_collisions(&mut containers);
+
+        let service_names: Vec<&str> = containers.iter().map(|c| c.service_name.as_str()).collect();
+        assert_eq!(service_names, vec!["shop-web", "blog-web", "web"]);
+    }
+
+    #[test]
+    fn test_qualify_compose_collisions_same_project() {
+        // Replicas of a single compose service are not collisions
+        let mut containers = vec![
+            compose_container("shop-web-1", Some("shop")),
+            compose_container("shop-web-2", Some("shop")),
+        ];
+
+        qualify_compose_collisions(&mut containers);
+
+        assert!(containers.iter().all(|c| c.service_name == "web"));
+    }
 
     #[rstest]
     #[tokio::test]
     async fn test_get_traefik_labeled_containers() -> anyhow::Result<()> {
-        let containers = get_traefik_labeled_containers().await?;
+        let containers = get_traefik_labeled_containers(&DockerOptions::default()).await?;
 
         println!("{:?}", containers);
 
@@ -35,6 +413,7 @@ mod tests {
         assert_contains!(container_names, &String::from("nginx1"));
         assert_contains!(container_names, &String::from("nginx2"));
         assert_not_contains!(container_names, &String::from("nginx3"));
+        assert_not_contains!(container_names, &String::from("nginx4"));
 
         Ok(())
     }
@@ -1,259 +1,7335 @@
-use anyhow::anyhow;
-use std::collections::BTreeMap;
+use anyhow::{anyhow, Context};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
 
-use axum::http::{header, HeaderMap};
+use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
-use serde::Serialize;
-use url::Url;
+use axum::Json;
+use itertools::Itertools;
+use regex::Regex;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::json;
+use url::{Position, Url};
 
-use crate::{TraefikedContainer, TraefikedContainerConfig};
+use crate::diff::ConfigDiff;
+use crate::error::{
+    MergeConflict, MergeConflicts, SerializationError, Severity, ValidationError, ValidationErrors,
+    ValidationIssue,
+};
+use crate::middleware::MiddlewareConfiguration;
+use crate::servers_transport::ServersTransportConfiguration;
+use crate::tcp::{TraefikedContainerTcpConfig, TraefikedTcpRouterTlsConfig};
+use crate::tls::{TlsCertificate, TlsOptions};
+use crate::udp::TraefikedContainerUdpConfig;
+use crate::weighted::TraefikedWeightedService;
+use crate::{
+    is_provider_qualified, ComposeService, TraefikedContainer, TraefikedContainerConfig,
+    TraefikedContainerSinglePortConfig, TraefikedHealthCheckConfig, TraefikedLoadBalancerConfig,
+    TraefikedObservabilityConfig, TraefikedRouterTlsConfig, TraefikedStickyConfig,
+};
 
 type HttpRouterName = String;
 
 type HttpServiceName = String;
 
+type MiddlewareName = String;
+
+type ServersTransportName = String;
+
 type RuleValue = String;
 
-#[derive(Clone, Debug, Serialize)]
+type TcpRouterName = String;
+
+/// Rewrites the rule of a router generated from a container, see
+/// [`DynamicConfigurationBuilder::with_rule_transform`].
+type RuleTransform = Arc<dyn Fn(&str, &TraefikedContainer) -> String + Send + Sync>;
+
+type TcpServiceName = String;
+
+type UdpRouterName = String;
+
+type UdpServiceName = String;
+
+type TlsOptionsName = String;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DynamicConfiguration {
+    #[serde(default)]
     http: HttpConfiguration,
+    #[serde(default, skip_serializing_if = "TcpConfiguration::is_empty")]
+    tcp: TcpConfiguration,
+    #[serde(default, skip_serializing_if = "UdpConfiguration::is_empty")]
+    udp: UdpConfiguration,
+    #[serde(default, skip_serializing_if = "TlsConfiguration::is_empty")]
+    tls: TlsConfiguration,
 }
 
-#[derive(Clone, Debug, Serialize)]
+impl HttpConfiguration {
+    fn is_empty(&self) -> bool {
+        self.routers.is_empty()
+            && self.services.is_empty()
+            && self.middlewares.is_empty()
+            && self.servers_transports.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 struct HttpConfiguration {
+    #[serde(default)]
     routers: BTreeMap<HttpRouterName, HttpRouterConfiguration>,
+    #[serde(default)]
     services: BTreeMap<HttpServiceName, HttpServiceConfiguration>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    middlewares: BTreeMap<MiddlewareName, MiddlewareConfiguration>,
+    #[serde(
+        rename = "serversTransports",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    servers_transports: BTreeMap<ServersTransportName, ServersTransportConfiguration>,
 }
 
-#[derive(Clone, Debug, Serialize)]
-struct HttpRouterConfiguration {
+/// HTTP router, built from the same options as container router labels.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HttpRouterConfiguration {
+    #[serde(rename = "entryPoints", default, skip_serializing_if = "Vec::is_empty")]
+    entry_points: Vec<String>,
     rule: RuleValue,
+    #[serde(rename = "ruleSyntax", skip_serializing_if = "Option::is_none")]
+    rule_syntax: Option<String>,
     service: HttpServiceName,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    middlewares: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tls: Option<HttpRouterTlsConfiguration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    observability: Option<ObservabilityConfiguration>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ObservabilityConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    access_logs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tracing: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HttpRouterTlsConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert_resolver: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    domains: Vec<TlsDomain>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TlsDomain {
+    main: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sans: Vec<String>,
 }
 
-#[derive(Clone, Debug, Serialize)]
-struct HttpServiceConfiguration {
+/// HTTP service, either a load balancer or weighted services.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HttpServiceConfiguration {
     #[serde(flatten)]
     service_type: HttpServiceType,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[allow(clippy::large_enum_variant)]
 enum HttpServiceType {
     LoadBalancer(LoadBalancerHttpServiceConfiguration),
+    Weighted(WeightedHttpServiceConfiguration),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct WeightedHttpServiceConfiguration {
+    services: Vec<WeightedServiceReference>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct WeightedServiceReference {
+    name: HttpServiceName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weight: Option<u32>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct LoadBalancerHttpServiceConfiguration {
     servers: Vec<ServiceUrl>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pass_host_header: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    health_check: Option<HealthCheckConfiguration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sticky: Option<StickyConfiguration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    servers_transport: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_forwarding: Option<ResponseForwardingConfiguration>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResponseForwardingConfiguration {
+    flush_interval: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct StickyConfiguration {
+    cookie: StickyCookieConfiguration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StickyCookieConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secure: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    same_site: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct HealthCheckConfiguration {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scheme: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
 struct ServiceUrl {
     url: Url,
+    /// Share of the requests of the load balancer, relative to the other servers.
+    #[serde(default)]
+    weight: Option<u32>,
+    /// Whether a URL without path keeps the trailing slash `Url` gives it, e.g.
+    /// `http://192.168.1.100:7878/` rather than `http://192.168.1.100:7878`.
+    #[serde(skip, default = "default_trailing_slash")]
+    trailing_slash: bool,
+}
+
+fn default_trailing_slash() -> bool {
+    true
 }
 
 impl ServiceUrl {
     pub fn new(url: Url) -> Self {
-        Self { url }
+        Self {
+            url,
+            weight: None,
+            trailing_slash: default_trailing_slash(),
+        }
+    }
+
+    fn weighted(url: Url, weight: Option<u32>) -> Self {
+        Self {
+            weight,
+            ..Self::new(url)
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self.url.as_str().strip_suffix('/') {
+            Some(trimmed) if !self.trailing_slash && self.url.path() == "/" => trimmed,
+            _ => self.url.as_str(),
+        }
+    }
+}
+
+impl Serialize for ServiceUrl {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ServiceUrl", 2)?;
+        state.serialize_field("url", self.as_str())?;
+        match self.weight {
+            Some(weight) => state.serialize_field("weight", &weight)?,
+            None => state.skip_field("weight")?,
+        }
+        state.end()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct TlsConfiguration {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    certificates: Vec<TlsCertificate>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    options: BTreeMap<TlsOptionsName, TlsOptions>,
+}
+
+impl TlsConfiguration {
+    fn is_empty(&self) -> bool {
+        self.certificates.is_empty() && self.options.is_empty()
+    }
+
+    fn certificates_by_file(&self) -> BTreeMap<String, &TlsCertificate> {
+        self.certificates
+            .iter()
+            .map(|certificate| (certificate.cert_file.clone(), certificate))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct TcpConfiguration {
+    #[serde(default)]
+    routers: BTreeMap<TcpRouterName, TcpRouterConfiguration>,
+    #[serde(default)]
+    services: BTreeMap<TcpServiceName, TcpServiceConfiguration>,
+}
+
+impl TcpConfiguration {
+    fn is_empty(&self) -> bool {
+        self.routers.is_empty() && self.services.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TcpRouterConfiguration {
+    #[serde(rename = "entryPoints", default, skip_serializing_if = "Vec::is_empty")]
+    entry_points: Vec<String>,
+    rule: RuleValue,
+    service: TcpServiceName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tls: Option<TcpRouterTlsConfiguration>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcpRouterTlsConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    passthrough: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert_resolver: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TcpServiceConfiguration {
+    #[serde(flatten)]
+    service_type: TcpServiceType,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum TcpServiceType {
+    LoadBalancer(LoadBalancerTcpServiceConfiguration),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct LoadBalancerTcpServiceConfiguration {
+    servers: Vec<ServiceAddress>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct UdpConfiguration {
+    #[serde(default)]
+    routers: BTreeMap<UdpRouterName, UdpRouterConfiguration>,
+    #[serde(default)]
+    services: BTreeMap<UdpServiceName, UdpServiceConfiguration>,
+}
+
+impl UdpConfiguration {
+    fn is_empty(&self) -> bool {
+        self.routers.is_empty() && self.services.is_empty()
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct UdpRouterConfiguration {
+    #[serde(rename = "entryPoints", default, skip_serializing_if = "Vec::is_empty")]
+    entry_points: Vec<String>,
+    service: UdpServiceName,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct UdpServiceConfiguration {
+    #[serde(flatten)]
+    service_type: UdpServiceType,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum UdpServiceType {
+    LoadBalancer(LoadBalancerUdpServiceConfiguration),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct LoadBalancerUdpServiceConfiguration {
+    servers: Vec<ServiceAddress>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ServiceAddress {
+    address: String,
+}
+
+#[derive(Clone)]
 pub struct DynamicConfigurationBuilder {
     routers: BTreeMap<HttpRouterName, HttpRouterConfiguration>,
     services: BTreeMap<HttpServiceName, HttpServiceConfiguration>,
+    middlewares: BTreeMap<MiddlewareName, MiddlewareConfiguration>,
+    servers_transports: BTreeMap<ServersTransportName, ServersTransportConfiguration>,
+    tcp: TcpConfiguration,
+    udp: UdpConfiguration,
+    tls: TlsConfiguration,
     base_url: Url,
+    /// Base URL of the servers using https, required unless https is only detected.
+    https_base_url: Option<Url>,
+    /// Other base URLs the containers are reachable through, each adding a server to HTTP services.
+    additional_base_urls: Vec<Url>,
+    /// Weight of the servers reached through each base URL.
+    server_weights: BTreeMap<Url, u32>,
+    use_container_ip: bool,
+    detect_https: bool,
+    https_ports: BTreeSet<u16>,
+    allow_empty_services: bool,
+    default_entrypoints: Vec<String>,
+    /// Middlewares appended to the routers generated from containers.
+    default_middlewares: Vec<String>,
+    /// TLS configuration of the routers generated from containers without one.
+    default_tls: Option<TraefikedRouterTlsConfig>,
+    /// Certificate resolver of the routers generated from containers with TLS but no resolver.
+    default_cert_resolver: Option<String>,
+    /// Servers transport of the load balancers generated from containers without one.
+    default_servers_transport: Option<String>,
+    strict: bool,
+    /// Whether routers generated without a priority get the one Traefik would give them.
+    compute_priorities: bool,
+    /// First container each container service name was derived from, to detect collisions and
+    /// replicas.
+    containers: BTreeMap<HttpServiceName, TraefikedContainer>,
+    /// Service name of the first container of each compose service, given to its replicas.
+    compose_services: BTreeMap<ComposeService, HttpServiceName>,
+    collision_policy: CollisionPolicy,
+    /// Name of the container each router and service was added by, keyed by kind and name.
+    owners: BTreeMap<(&'static str, String), String>,
+    /// Suffixed name given to a colliding router or service, keyed by kind, name and container.
+    renames: BTreeMap<(&'static str, String, String), String>,
+    server_path_segment: ServerPathSegment,
+    /// Prefix of the names of the routers and services generated from containers.
+    name_prefix: String,
+    trailing_slash: bool,
+    /// Hand-written configuration merged with the one generated from containers.
+    extra_configuration: Option<DynamicConfiguration>,
+    extra_configuration_policy: ExtraConfigurationPolicy,
+    container_error_policy: ContainerErrorPolicy,
+    /// Containers left out under [`ContainerErrorPolicy::SkipAndReport`].
+    skipped_containers: Vec<SkippedContainer>,
+    rule_transform: Option<RuleTransform>,
+    /// Patterns of the names of the containers left out, e.g. meant for another Traefik.
+    excluded_names: Vec<NamePattern>,
+    /// Changes made by the container being inserted, recorded under
+    /// [`ContainerErrorPolicy::SkipAndReport`] to be undone if it cannot be inserted.
+    changes: Option<Vec<Change>>,
 }
 
-impl DynamicConfigurationBuilder {
-    pub fn new(base_url: Url) -> DynamicConfigurationBuilder {
-        DynamicConfigurationBuilder {
-            base_url,
-            routers: BTreeMap::default(),
-            services: BTreeMap::default(),
+/// Entry of a [`DynamicConfigurationBuilder`] changed by a container, with its previous value.
+/// Containers and compose services are only ever added.
+#[derive(Clone)]
+enum Change {
+    Container(HttpServiceName),
+    ComposeService(ComposeService),
+    Owner((&'static str, String), Option<String>),
+    Rename((&'static str, String, String), Option<String>),
+    Router(HttpRouterName, Option<HttpRouterConfiguration>),
+    Service(HttpServiceName, Option<HttpServiceConfiguration>),
+    TcpRouter(TcpRouterName, Option<TcpRouterConfiguration>),
+    TcpService(TcpServiceName, Option<TcpServiceConfiguration>),
+    UdpRouter(UdpRouterName, Option<UdpRouterConfiguration>),
+    UdpService(UdpServiceName, Option<UdpServiceConfiguration>),
+}
+
+/// Sets the entry of `key` back to `previous`, removing it when it did not exist.
+fn restore<K: Ord, V>(map: &mut BTreeMap<K, V>, key: K, previous: Option<V>) {
+    match previous {
+        Some(value) => {
+            map.insert(key, value);
+        }
+        None => {
+            map.remove(&key);
         }
     }
+}
 
-    pub fn add_container(
-        mut self,
-        container: &TraefikedContainer,
-    ) -> anyhow::Result<DynamicConfigurationBuilder> {
-        match &container.config {
-            TraefikedContainerConfig::SinglePort(config) => {
-                let service_name = &container.name;
+/// What happens when a container cannot be added, e.g. a single port container without public
+/// port.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ContainerErrorPolicy {
+    /// Fail, the configuration not being built.
+    #[default]
+    FailFast,
+    /// Leave the container out, keeping the routers and services of the others, and report it
+    /// through [`DynamicConfigurationBuilder::skipped_containers`].
+    SkipAndReport,
+}
 
-                let mut url = self.base_url.clone();
-                let public_port = container.public_ports.first().cloned().ok_or(anyhow!(
-                    "No public port specified for container '{}'",
-                    service_name
-                ))?;
+impl FromStr for ContainerErrorPolicy {
+    type Err = anyhow::Error;
 
-                url.set_port(Some(public_port))
-                    .map_err(|_| anyhow!("Cannot append container public port to base_url."))?;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fail-fast" => Ok(ContainerErrorPolicy::FailFast),
+            "skip-and-report" => Ok(ContainerErrorPolicy::SkipAndReport),
+            _ => Err(anyhow!(
+                "Unknown container error policy '{}', expected fail-fast or skip-and-report",
+                s
+            )),
+        }
+    }
+}
 
-                self.services.insert(
-                    service_name.clone(),
-                    HttpServiceConfiguration {
-                        service_type: HttpServiceType::LoadBalancer(
-                            LoadBalancerHttpServiceConfiguration {
-                                servers: vec![ServiceUrl::new(url)],
-                            },
-                        ),
-                    },
-                );
+/// e.g. ``to-my-service: Host(`my-service.my-domain.com`) -> my-service [http://192.168.1.100:7878/]``
+fn summary_line(name: &str, rule: &str, service: &str, servers: &[String]) -> String {
+    let rule = if rule.chars().count() > SUMMARY_RULE_MAX_LENGTH {
+        let truncated: String = rule.chars().take(SUMMARY_RULE_MAX_LENGTH - 3).collect();
+        format!("{}...", truncated)
+    } else {
+        rule.to_owned()
+    };
 
-                self.routers.insert(
-                    config.router_name.clone(),
-                    HttpRouterConfiguration {
-                        service: service_name.clone(),
-                        rule: config.rule.clone(),
-                    },
-                );
-            }
-            TraefikedContainerConfig::MultiplePorts(config) => {
-                for c in config {
-                    let service_name = &c.service_name;
-
-                    let mut url = self.base_url.clone();
-                    url.set_port(Some(c.target_port))
-                        .map_err(|_| anyhow!("Cannot append container public port to base_url."))?;
-
-                    self.services.insert(
-                        service_name.clone(),
-                        HttpServiceConfiguration {
-                            service_type: HttpServiceType::LoadBalancer(
-                                LoadBalancerHttpServiceConfiguration {
-                                    servers: vec![ServiceUrl::new(url)],
-                                },
-                            ),
-                        },
-                    );
+    if servers.is_empty() {
+        format!("{}: {} -> {}", name, rule, service)
+    } else {
+        format!("{}: {} -> {} [{}]", name, rule, service, servers.join(", "))
+    }
+}
 
-                    self.routers.insert(
-                        c.config.router_name.clone(),
-                        HttpRouterConfiguration {
-                            service: service_name.clone(),
-                            rule: c.config.rule.clone(),
-                        },
-                    );
-                }
-            }
+/// Number of entries of each section of a [`DynamicConfiguration`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConfigStats {
+    pub routers: usize,
+    pub services: usize,
+    pub middlewares: usize,
+    pub servers_transports: usize,
+    pub tcp_routers: usize,
+    pub tcp_services: usize,
+    pub udp_routers: usize,
+    pub udp_services: usize,
+    pub tls_certificates: usize,
+    pub tls_options: usize,
+}
+
+/// e.g. `3 routers, 3 services, 1 middlewares`, sections without entries being left out.
+impl Display for ConfigStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let counts = [
+            (self.routers, "routers"),
+            (self.services, "services"),
+            (self.middlewares, "middlewares"),
+            (self.servers_transports, "servers transports"),
+            (self.tcp_routers, "TCP routers"),
+            (self.tcp_services, "TCP services"),
+            (self.udp_routers, "UDP routers"),
+            (self.udp_services, "UDP services"),
+            (self.tls_certificates, "TLS certificates"),
+            (self.tls_options, "TLS options"),
+        ];
+        let counts = counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, section)| format!("{} {}", count, section))
+            .join(", ");
+
+        if counts.is_empty() {
+            write!(f, "no entries")
+        } else {
+            write!(f, "{}", counts)
         }
+    }
+}
 
-        Ok(self)
+/// Glob matching container names, `*` matching any characters and `?` a single one, e.g. `ops-*`.
+#[derive(Clone, Debug)]
+pub struct NamePattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl NamePattern {
+    pub fn matches(&self, name: &str) -> bool {
+        self.regex.is_match(name)
     }
+}
 
-    pub fn build(self) -> DynamicConfiguration {
-        DynamicConfiguration {
-            http: HttpConfiguration {
-                routers: self.routers,
-                services: self.services,
-            },
+impl FromStr for NamePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pattern = s.trim();
+        if pattern.is_empty() {
+            return Err(anyhow!("Empty container name pattern"));
         }
+
+        let regex = pattern
+            .split('*')
+            .map(|part| part.split('?').map(regex::escape).join("."))
+            .join(".*");
+        Ok(NamePattern {
+            pattern: pattern.to_owned(),
+            regex: Regex::new(&format!("^{}$", regex))?,
+        })
     }
 }
 
-impl IntoResponse for DynamicConfiguration {
-    fn into_response(self) -> Response {
-        let mut headers = HeaderMap::new();
-        headers.insert(header::CONTENT_TYPE, "text/yaml".parse().unwrap());
+impl Display for NamePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
 
-        let payload = serde_yaml::to_string(&self).expect("Should serialize to YAML");
+/// A container left out of the configuration and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkippedContainer {
+    pub name: String,
+    pub reason: String,
+}
 
-        (headers, payload).into_response()
+impl Display for SkippedContainer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Container '{}' skipped: {}", self.name, self.reason)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::TraefikedContainerSinglePortConfig;
+/// `container` named after the first container of its compose service when a replica of it, its
+/// services being named after each container unless named after compose services.
+fn compose_replica(
+    compose_services: &BTreeMap<ComposeService, HttpServiceName>,
+    containers: &BTreeMap<HttpServiceName, TraefikedContainer>,
+    container: &TraefikedContainer,
+) -> Option<TraefikedContainer> {
+    let service_name = compose_services.get(container.compose_service.as_ref()?)?;
+    if *service_name == container.service_name {
+        return None;
+    }
 
-    #[test]
-    fn test_yaml_serialize() -> anyhow::Result<()> {
-        let dynamic_configuration = DynamicConfiguration {
-            http: HttpConfiguration {
-                routers: [(
-                    "to-my-service".to_owned(),
-                    HttpRouterConfiguration {
-                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
-                        service: "my-service".to_owned(),
-                    },
-                )]
-                .iter()
-                .cloned()
-                .collect(),
-                services: [(
-                    "my-service".to_owned(),
-                    HttpServiceConfiguration {
-                        service_type: HttpServiceType::LoadBalancer(
-                            LoadBalancerHttpServiceConfiguration {
-                                servers: vec![
-                                    ServiceUrl {
-                                        url: "http://192.168.1.100:7878".try_into()?,
-                                    },
-                                    ServiceUrl {
-                                        url: "http://my-service.local:7878".try_into()?,
-                                    },
-                                ],
-                            },
-                        ),
-                    },
-                )]
-                .iter()
-                .cloned()
-                .collect(),
-            },
-        };
+    let mut replica = container.clone();
+    if let TraefikedContainerConfig::SinglePort(config) = &mut replica.config {
+        if config.router_name == replica.service_name {
+            config.router_name = service_name.clone();
+        }
+    }
+    replica.service_name = service_name.clone();
 
-        let expected = r#"http:
-  routers:
-    to-my-service:
-      rule: Host(`my-service.my-domain.com`)
-      service: my-service
-  services:
-    my-service:
-      loadBalancer:
-        servers:
-        - url: http://192.168.1.100:7878/
-        - url: http://my-service.local:7878/
-"#;
+    are_replicas(containers.get(service_name)?, &replica).then_some(replica)
+}
 
-        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+/// Whether `first` and `second` are replicas of the same service, e.g. started by
+/// `docker compose up --scale`: different containers with the same routers and services, only
+/// their ports and networks differing.
+fn are_replicas(first: &TraefikedContainer, second: &TraefikedContainer) -> bool {
+    first.name != second.name
+        && first.service_name == second.service_name
+        && first.config == second.config
+        && first.tcp == second.tcp
+        && first.udp == second.udp
+        && first.weighted_services == second.weighted_services
+        && first.middlewares == second.middlewares
+        && first.servers_transports == second.servers_transports
+}
 
-        assert_eq!(configuration_yaml, expected);
-        Ok(())
+/// Owner of the routers and services added by [`DynamicConfigurationBuilder::add_router`] and
+/// [`DynamicConfigurationBuilder::add_service`], which no container name can be.
+const BUILDER_OWNER: &str = "";
+
+/// e.g. `container 'my-service'`
+fn describe_owner(owner: &str) -> String {
+    if owner == BUILDER_OWNER {
+        "the builder".to_owned()
+    } else {
+        format!("container '{}'", owner)
     }
+}
 
-    #[test]
-    fn test_builder() -> anyhow::Result<()> {
-        let base_url = Url::parse("http://192.168.1.100")?;
-        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
-            .add_container(&TraefikedContainer {
-                name: "my-service".to_owned(),
-                config: TraefikedContainerConfig::SinglePort(TraefikedContainerSinglePortConfig {
-                    router_name: "to-my-service".to_owned(),
-                    rule: "Host(`my-service.my-domain.com`)".to_owned(),
-                }),
-                public_ports: vec![7878],
-            })
-            .build();
+/// e.g. `containers 'a' and 'b'`
+fn describe_owners(first: &str, second: &str) -> String {
+    if first == BUILDER_OWNER || second == BUILDER_OWNER {
+        format!("{} and {}", describe_owner(first), describe_owner(second))
+    } else {
+        format!("containers '{}' and '{}'", first, second)
+    }
+}
 
-        let expected = r#"http:
-  routers:
-    to-my-service:
-      rule: Host(`my-service.my-domain.com`)
-      service: my-service
-  services:
-    my-service:
-      loadBalancer:
-        servers:
-        - url: http://192.168.1.100:7878/
-"#;
+/// Parameters of the 64-bit FNV-1a hash of [`DynamicConfiguration::fingerprint`].
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
 
-        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+/// Rules longer than this many characters are truncated in [`DynamicConfiguration::summary`].
+const SUMMARY_RULE_MAX_LENGTH: usize = 80;
 
-        assert_eq!(configuration_yaml, expected);
+/// Ports whose services are assumed to speak TLS when HTTPS detection is enabled.
+const HTTPS_PORTS: &[u16] = &[443, 8443];
+
+/// What to do when two containers declare a router or service with the same name.
+///
+/// Containers are added in name order, so the winning container does not depend on Docker.
+/// Whatever the policy, collisions are reported with the policy and the names of both containers.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CollisionPolicy {
+    /// Fail the build, naming both containers.
+    ///
+    /// The default, as silently keeping either declaration would route requests to a container
+    /// nobody asked for.
+    #[default]
+    Error,
+    /// Keep the router or service of the first container, logging a warning.
+    FirstWins,
+    /// Replace the router or service by the one of the last container, logging a warning.
+    LastWins,
+    /// Rename the router or service of the last container to `<name>-2`, `<name>-3`, etc.,
+    /// its routers targeting the renamed service, logging a warning.
+    AutoSuffix,
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "error" => Ok(CollisionPolicy::Error),
+            "first-wins" => Ok(CollisionPolicy::FirstWins),
+            "last-wins" => Ok(CollisionPolicy::LastWins),
+            "auto-suffix" => Ok(CollisionPolicy::AutoSuffix),
+            _ => Err(anyhow!(
+                "Unknown collision policy '{}', expected error, first-wins, last-wins or auto-suffix",
+                s
+            )),
+        }
+    }
+}
+
+/// The name [`CollisionPolicy::from_str`] parses, e.g. `first-wins`.
+impl Display for CollisionPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CollisionPolicy::Error => "error",
+            CollisionPolicy::FirstWins => "first-wins",
+            CollisionPolicy::LastWins => "last-wins",
+            CollisionPolicy::AutoSuffix => "auto-suffix",
+        })
+    }
+}
+
+/// Path segment appended to the path of the base URL in server URLs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ServerPathSegment {
+    /// Servers are reached on the path of the base URL, e.g. `http://gateway.internal:7878/apps`.
+    #[default]
+    None,
+    /// e.g. `http://gateway.internal:7878/apps/my-container`
+    ContainerName,
+    /// e.g. `http://gateway.internal:7878/apps/my-service`, before any collision renaming.
+    ServiceName,
+}
+
+impl FromStr for ServerPathSegment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(ServerPathSegment::None),
+            "container-name" => Ok(ServerPathSegment::ContainerName),
+            "service-name" => Ok(ServerPathSegment::ServiceName),
+            _ => Err(anyhow!(
+                "Unknown server path segment '{}', expected none, container-name or service-name",
+                s
+            )),
+        }
+    }
+}
+
+/// What is answered when the configuration is empty, e.g. no container has Traefik labels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EmptyConfigurationPolicy {
+    /// Serve the empty configuration, Traefik removing every route of this provider.
+    #[default]
+    Serve,
+    /// Answer `204 No Content`, Traefik keeping the last configuration it received.
+    NoContent,
+}
+
+impl FromStr for EmptyConfigurationPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "serve" => Ok(EmptyConfigurationPolicy::Serve),
+            "no-content" => Ok(EmptyConfigurationPolicy::NoContent),
+            _ => Err(anyhow!(
+                "Unknown empty configuration policy '{}', expected serve or no-content",
+                s
+            )),
+        }
+    }
+}
+
+/// Which entry is kept when the extra configuration declares a router, service, middleware,
+/// servers transport or TLS options with the same name as a generated one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExtraConfigurationPolicy {
+    /// Keep the generated entry, logging a warning.
+    #[default]
+    PreferGenerated,
+    /// Replace the generated entry by the extra one, logging a warning.
+    PreferExtra,
+    /// Fail the build, naming the conflicting entries.
+    Error,
+}
+
+impl FromStr for ExtraConfigurationPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "prefer-generated" => Ok(ExtraConfigurationPolicy::PreferGenerated),
+            "prefer-extra" => Ok(ExtraConfigurationPolicy::PreferExtra),
+            "error" => Ok(ExtraConfigurationPolicy::Error),
+            _ => Err(anyhow!(
+                "Unknown extra configuration policy '{}', expected prefer-generated, prefer-extra or error",
+                s
+            )),
+        }
+    }
+}
+
+/// How [`DynamicConfiguration::merge`] resolves names declared by both configurations.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MergePolicy {
+    /// Keep the entry of the configuration merged into.
+    #[default]
+    PreferSelf,
+    /// Replace the entry by the one of the merged configuration.
+    PreferOther,
+    /// Fail, listing every conflicting name.
+    Error,
+}
+
+impl From<ExtraConfigurationPolicy> for MergePolicy {
+    fn from(policy: ExtraConfigurationPolicy) -> Self {
+        match policy {
+            ExtraConfigurationPolicy::PreferGenerated => MergePolicy::PreferSelf,
+            ExtraConfigurationPolicy::PreferExtra => MergePolicy::PreferOther,
+            ExtraConfigurationPolicy::Error => MergePolicy::Error,
+        }
+    }
+}
+
+/// Inserts the `other` entries of a section into `entries`, replacing them when `prefer_other`,
+/// and returns the names declared by both.
+fn merge_section<V>(
+    kind: &'static str,
+    entries: &mut BTreeMap<String, V>,
+    other: BTreeMap<String, V>,
+    prefer_other: bool,
+) -> Vec<MergeConflict> {
+    let mut conflicts = vec![];
+    for (name, value) in other {
+        match entries.entry(name) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+            Entry::Occupied(mut entry) => {
+                conflicts.push(MergeConflict {
+                    kind,
+                    name: entry.key().clone(),
+                });
+                if prefer_other {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+impl HttpRouterConfiguration {
+    /// Router of `config` targeting `service`, the load balancer options of `config` being ignored.
+    pub fn new(config: &TraefikedContainerSinglePortConfig, service: &HttpServiceName) -> Self {
+        Self {
+            entry_points: config.entrypoints.clone(),
+            rule: config.rule.clone(),
+            rule_syntax: config.rule_syntax.clone(),
+            service: service.clone(),
+            middlewares: config.middlewares.clone().unwrap_or_default(),
+            priority: config.priority,
+            tls: config.tls.as_ref().map(HttpRouterTlsConfiguration::from),
+            observability: config
+                .observability
+                .as_ref()
+                .map(ObservabilityConfiguration::from),
+        }
+    }
+}
+
+impl HttpRouterConfiguration {
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// Name of the targeted service, possibly provider-qualified like `auth@file`.
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Entrypoints the router listens on, all of them when empty.
+    pub fn entry_points(&self) -> &[String] {
+        &self.entry_points
+    }
+
+    pub fn middlewares(&self) -> &[String] {
+        &self.middlewares
+    }
+
+    pub fn priority(&self) -> Option<i64> {
+        self.priority
+    }
+}
+
+impl From<&TraefikedObservabilityConfig> for ObservabilityConfiguration {
+    fn from(observability: &TraefikedObservabilityConfig) -> Self {
+        Self {
+            access_logs: observability.access_logs,
+            metrics: observability.metrics,
+            tracing: observability.tracing,
+        }
+    }
+}
+
+impl From<&TraefikedRouterTlsConfig> for HttpRouterTlsConfiguration {
+    fn from(tls: &TraefikedRouterTlsConfig) -> Self {
+        Self {
+            cert_resolver: tls.cert_resolver.clone(),
+            options: tls.options.clone(),
+            domains: tls
+                .domains
+                .iter()
+                .map(|domain| TlsDomain {
+                    main: domain.main.clone(),
+                    sans: domain.sans.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TcpRouterConfiguration {
+    fn new(config: &TraefikedContainerTcpConfig, service: &TcpServiceName) -> Self {
+        Self {
+            entry_points: config.entrypoints.clone(),
+            rule: config.rule.clone(),
+            service: service.clone(),
+            tls: config.tls.as_ref().map(TcpRouterTlsConfiguration::from),
+        }
+    }
+}
+
+impl From<&TraefikedTcpRouterTlsConfig> for TcpRouterTlsConfiguration {
+    fn from(tls: &TraefikedTcpRouterTlsConfig) -> Self {
+        Self {
+            passthrough: tls.passthrough,
+            cert_resolver: tls.cert_resolver.clone(),
+        }
+    }
+}
+
+impl TcpServiceConfiguration {
+    fn load_balancer(address: Option<String>) -> Self {
+        Self {
+            service_type: TcpServiceType::LoadBalancer(LoadBalancerTcpServiceConfiguration {
+                servers: address
+                    .map(|address| ServiceAddress { address })
+                    .into_iter()
+                    .collect(),
+            }),
+        }
+    }
+}
+
+impl TcpServiceConfiguration {
+    /// Adds the servers of `other`, a service of a replica.
+    fn extend_servers(&mut self, other: Self) {
+        let TcpServiceType::LoadBalancer(load_balancer) = &mut self.service_type;
+        let TcpServiceType::LoadBalancer(other) = other.service_type;
+        load_balancer.servers.extend(other.servers);
+    }
+}
+
+impl UdpRouterConfiguration {
+    fn new(config: &TraefikedContainerUdpConfig, service: &UdpServiceName) -> Self {
+        Self {
+            entry_points: config.entrypoints.clone(),
+            service: service.clone(),
+        }
+    }
+}
+
+impl UdpServiceConfiguration {
+    fn load_balancer(address: Option<String>) -> Self {
+        Self {
+            service_type: UdpServiceType::LoadBalancer(LoadBalancerUdpServiceConfiguration {
+                servers: address
+                    .map(|address| ServiceAddress { address })
+                    .into_iter()
+                    .collect(),
+            }),
+        }
+    }
+}
+
+impl UdpServiceConfiguration {
+    /// Adds the servers of `other`, a service of a replica.
+    fn extend_servers(&mut self, other: Self) {
+        let UdpServiceType::LoadBalancer(load_balancer) = &mut self.service_type;
+        let UdpServiceType::LoadBalancer(other) = other.service_type;
+        load_balancer.servers.extend(other.servers);
+    }
+}
+
+impl HttpServiceConfiguration {
+    /// A load balancer without servers when `urls` is empty, answering 503 until the container
+    /// publishes a port.
+    pub fn load_balancer(urls: Vec<Url>, load_balancer: &TraefikedLoadBalancerConfig) -> Self {
+        Self::load_balancer_servers(
+            urls.into_iter().map(ServiceUrl::new).collect(),
+            load_balancer,
+        )
+    }
+
+    /// Sets the weight of the servers reached on `url`, relative to the other servers of a load
+    /// balancer.
+    pub fn server_weight(mut self, url: &Url, weight: u32) -> Self {
+        if let HttpServiceType::LoadBalancer(load_balancer) = &mut self.service_type {
+            for server in &mut load_balancer.servers {
+                if server.url == *url {
+                    server.weight = Some(weight);
+                }
+            }
+        }
+        self
+    }
+
+    fn load_balancer_servers(
+        servers: Vec<ServiceUrl>,
+        load_balancer: &TraefikedLoadBalancerConfig,
+    ) -> Self {
+        Self {
+            service_type: HttpServiceType::LoadBalancer(LoadBalancerHttpServiceConfiguration {
+                servers,
+                pass_host_header: load_balancer.pass_host_header,
+                health_check: load_balancer
+                    .health_check
+                    .as_ref()
+                    .map(HealthCheckConfiguration::from),
+                sticky: load_balancer.sticky.as_ref().map(StickyConfiguration::from),
+                servers_transport: load_balancer.servers_transport.clone(),
+                response_forwarding: load_balancer
+                    .flush_interval
+                    .clone()
+                    .map(|flush_interval| ResponseForwardingConfiguration { flush_interval }),
+            }),
+        }
+    }
+}
+
+impl HttpServiceConfiguration {
+    /// Sets whether the servers of a load balancer keep the trailing slash of URLs without path.
+    fn set_trailing_slash(&mut self, trailing_slash: bool) {
+        if let HttpServiceType::LoadBalancer(load_balancer) = &mut self.service_type {
+            for server in &mut load_balancer.servers {
+                server.trailing_slash = trailing_slash;
+            }
+        }
+    }
+
+    /// Adds the servers of `other`, a service of a replica, weighted services being left as-is.
+    fn extend_servers(&mut self, other: Self) {
+        if let (
+            HttpServiceType::LoadBalancer(load_balancer),
+            HttpServiceType::LoadBalancer(other),
+        ) = (&mut self.service_type, other.service_type)
+        {
+            load_balancer.servers.extend(other.servers);
+        }
+    }
+
+    /// URLs of the servers of a load balancer, none for weighted services.
+    pub fn urls(&self) -> Vec<&Url> {
+        match &self.service_type {
+            HttpServiceType::LoadBalancer(load_balancer) => load_balancer
+                .servers
+                .iter()
+                .map(|server| &server.url)
+                .collect(),
+            HttpServiceType::Weighted(_) => vec![],
+        }
+    }
+
+    /// Names and weights of the services balanced by weighted services, none for load balancers.
+    pub fn weighted_services(&self) -> Vec<(&str, Option<u32>)> {
+        match &self.service_type {
+            HttpServiceType::LoadBalancer(_) => vec![],
+            HttpServiceType::Weighted(weighted) => weighted
+                .services
+                .iter()
+                .map(|service| (service.name.as_str(), service.weight))
+                .collect(),
+        }
+    }
+}
+
+impl From<&[TraefikedWeightedService]> for HttpServiceConfiguration {
+    fn from(services: &[TraefikedWeightedService]) -> Self {
+        Self {
+            service_type: HttpServiceType::Weighted(WeightedHttpServiceConfiguration {
+                services: services
+                    .iter()
+                    .map(|service| WeightedServiceReference {
+                        name: service.name.clone(),
+                        weight: service.weight,
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+impl From<&TraefikedHealthCheckConfig> for HealthCheckConfiguration {
+    fn from(health_check: &TraefikedHealthCheckConfig) -> Self {
+        Self {
+            path: health_check.path.clone(),
+            interval: health_check.interval.clone(),
+            timeout: health_check.timeout.clone(),
+            port: health_check.port,
+            scheme: health_check.scheme.clone(),
+        }
+    }
+}
+
+impl From<&TraefikedStickyConfig> for StickyConfiguration {
+    fn from(sticky: &TraefikedStickyConfig) -> Self {
+        Self {
+            cookie: StickyCookieConfiguration {
+                name: sticky.cookie.name.clone(),
+                secure: sticky.cookie.secure,
+                http_only: sticky.cookie.http_only,
+                same_site: sticky.cookie.same_site.clone(),
+            },
+        }
+    }
+}
+
+impl DynamicConfigurationBuilder {
+    pub fn new(base_url: Url) -> DynamicConfigurationBuilder {
+        DynamicConfigurationBuilder {
+            base_url,
+            https_base_url: None,
+            additional_base_urls: vec![],
+            server_weights: BTreeMap::default(),
+            routers: BTreeMap::default(),
+            services: BTreeMap::default(),
+            middlewares: BTreeMap::default(),
+            servers_transports: BTreeMap::default(),
+            tcp: TcpConfiguration::default(),
+            udp: UdpConfiguration::default(),
+            tls: TlsConfiguration::default(),
+            use_container_ip: false,
+            detect_https: false,
+            https_ports: BTreeSet::default(),
+            allow_empty_services: false,
+            default_entrypoints: vec![],
+            default_middlewares: vec![],
+            default_tls: None,
+            default_cert_resolver: None,
+            default_servers_transport: None,
+            strict: false,
+            compute_priorities: false,
+            containers: BTreeMap::default(),
+            compose_services: BTreeMap::default(),
+            collision_policy: CollisionPolicy::default(),
+            owners: BTreeMap::default(),
+            renames: BTreeMap::default(),
+            server_path_segment: ServerPathSegment::default(),
+            name_prefix: String::new(),
+            trailing_slash: true,
+            extra_configuration: None,
+            extra_configuration_policy: ExtraConfigurationPolicy::default(),
+            container_error_policy: ContainerErrorPolicy::default(),
+            skipped_containers: vec![],
+            rule_transform: None,
+            excluded_names: vec![],
+            changes: None,
+        }
+    }
+
+    /// Base URL of the servers using https, declared by a `scheme` label or detected, instead of
+    /// the base URL with its scheme switched, e.g. `https://my-host.local` next to
+    /// `http://my-host.local`. Servers reached through other base URLs keep switching schemes.
+    ///
+    /// Without it, containers with https servers fail to be added, unless https was only detected
+    /// on port 443 or 8443 by [`DynamicConfigurationBuilder::detect_https`], the base URL then
+    /// having its scheme switched.
+    pub fn with_https_base_url(mut self, https_base_url: Url) -> DynamicConfigurationBuilder {
+        self.https_base_url = Some(https_base_url);
+        self
+    }
+
+    /// Adds a base URL the containers are also reachable through, e.g. a direct link next to the
+    /// LAN address, HTTP services getting one server per base URL with the same port so Traefik
+    /// can fail over. TCP and UDP services, containers with their own base URL and containers
+    /// targeted on their IP address keep a single server.
+    pub fn with_additional_base_url(mut self, base_url: Url) -> DynamicConfigurationBuilder {
+        self.additional_base_urls.push(base_url);
+        self
+    }
+
+    /// Weight of the servers reached through `base_url`, the base URL, an additional one or the
+    /// one of a container, e.g. to shift traffic gradually from one to the other. Servers are
+    /// not weighted by default, Traefik balancing them evenly.
+    pub fn server_weight(mut self, base_url: Url, weight: u32) -> DynamicConfigurationBuilder {
+        self.server_weights.insert(base_url, weight);
+        self
+    }
+
+    /// Targets containers on their IP address and private ports instead of the base URL host
+    /// and public ports.
+    pub fn use_container_ip(mut self, use_container_ip: bool) -> DynamicConfigurationBuilder {
+        self.use_container_ip = use_container_ip;
+        self
+    }
+
+    /// Targets HTTP services on port 443 or 8443 with the `https` scheme, unless their
+    /// `server.scheme` label is set.
+    pub fn detect_https(mut self, detect_https: bool) -> DynamicConfigurationBuilder {
+        self.detect_https = detect_https;
+        self
+    }
+
+    /// Targets HTTP services on any of `ports` with the `https` scheme, unless their
+    /// `server.scheme` label is set, whether HTTPS detection is enabled or not.
+    pub fn https_ports(
+        mut self,
+        ports: impl IntoIterator<Item = u16>,
+    ) -> DynamicConfigurationBuilder {
+        self.https_ports = ports.into_iter().collect();
+        self
+    }
+
+    /// Keeps the routers of containers without ports, their services having no servers so that
+    /// Traefik answers 503 rather than 404.
+    pub fn allow_empty_services(
+        mut self,
+        allow_empty_services: bool,
+    ) -> DynamicConfigurationBuilder {
+        self.allow_empty_services = allow_empty_services;
+        self
+    }
+
+    /// Entrypoints of the HTTP and TCP routers that do not declare any. Empty by default, letting
+    /// Traefik use all of its entrypoints.
+    pub fn default_entrypoints(
+        mut self,
+        default_entrypoints: Vec<String>,
+    ) -> DynamicConfigurationBuilder {
+        self.default_entrypoints = default_entrypoints;
+        self
+    }
+
+    /// Servers transport of every HTTP load balancer generated from containers without a
+    /// `serverstransport` label, e.g. `internal-ca@file`.
+    pub fn with_default_servers_transport(
+        mut self,
+        default_servers_transport: impl Into<String>,
+    ) -> DynamicConfigurationBuilder {
+        self.default_servers_transport = Some(default_servers_transport.into());
+        self
+    }
+
+    /// Middlewares appended to the middlewares of every router generated from containers, e.g.
+    /// `compress@file`, unless the router declares an empty `middlewares` label.
+    pub fn with_default_middlewares(
+        mut self,
+        default_middlewares: Vec<String>,
+    ) -> DynamicConfigurationBuilder {
+        self.default_middlewares = default_middlewares;
+        self
+    }
+
+    /// Enables TLS on every router generated from containers without TLS labels, with
+    /// `cert_resolver` if any, so forgetting them does not expose a service over plain HTTP.
+    /// Routers labeled `tls=false` stay on plain HTTP.
+    pub fn with_default_tls(
+        mut self,
+        cert_resolver: Option<String>,
+    ) -> DynamicConfigurationBuilder {
+        self.default_tls = Some(TraefikedRouterTlsConfig {
+            cert_resolver,
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Certificate resolver of every router generated from containers that enables TLS without
+    /// naming one, e.g. labeled `tls=true` only. TCP routers passing TLS through are left as-is.
+    ///
+    /// Routers TLS is enabled on by [`with_default_tls`](Self::with_default_tls) get its
+    /// certificate resolver when it has one, this one otherwise.
+    pub fn with_default_cert_resolver(
+        mut self,
+        cert_resolver: impl Into<String>,
+    ) -> DynamicConfigurationBuilder {
+        self.default_cert_resolver = Some(cert_resolver.into());
+        self
+    }
+
+    /// Fails the build on router middleware references no container defines, instead of logging
+    /// them as warnings.
+    pub fn strict(mut self, strict: bool) -> DynamicConfigurationBuilder {
+        self.strict = strict;
+        self
+    }
+
+    /// Emits the priority of the HTTP routers generated from containers without a `priority`
+    /// label, Traefik defaulting it to the length of the rule, so the configuration shows which
+    /// router wins, e.g. ``Host(`a`) && PathPrefix(`/api`)`` over ``Host(`a`)``.
+    pub fn compute_priorities(mut self, compute_priorities: bool) -> DynamicConfigurationBuilder {
+        self.compute_priorities = compute_priorities;
+        self
+    }
+
+    pub fn server_path_segment(
+        mut self,
+        server_path_segment: ServerPathSegment,
+    ) -> DynamicConfigurationBuilder {
+        self.server_path_segment = server_path_segment;
+        self
+    }
+
+    /// Prefixes the names of the routers and services generated from containers, e.g. `host1-`
+    /// so the configurations of several Docker hosts can be merged. References to services of
+    /// other providers, e.g. `auth@file`, are left as-is.
+    pub fn with_name_prefix(
+        mut self,
+        name_prefix: impl Into<String>,
+    ) -> DynamicConfigurationBuilder {
+        self.name_prefix = name_prefix.into();
+        self
+    }
+
+    /// Whether server URLs without path end with a slash, e.g. `http://192.168.1.100:7878/`
+    /// (the default) rather than `http://192.168.1.100:7878`.
+    pub fn trailing_slash(mut self, trailing_slash: bool) -> DynamicConfigurationBuilder {
+        self.trailing_slash = trailing_slash;
+        self
+    }
+
+    pub fn collision_policy(
+        mut self,
+        collision_policy: CollisionPolicy,
+    ) -> DynamicConfigurationBuilder {
+        self.collision_policy = collision_policy;
+        self
+    }
+
+    /// Rewrites the rule of every HTTP and TCP router generated from a container before it is
+    /// inserted, e.g. ``Host(`x`)`` into ``Host(`x`) && !PathPrefix(`/admin`)`` or a shorthand
+    /// ``Host(`x`)`` into ``Host(`x.my-domain.com`)``. Routers added by
+    /// [`DynamicConfigurationBuilder::add_router`] or the extra configuration are left as-is.
+    pub fn with_rule_transform(
+        mut self,
+        rule_transform: impl Fn(&str, &TraefikedContainer) -> String + Send + Sync + 'static,
+    ) -> DynamicConfigurationBuilder {
+        self.rule_transform = Some(Arc::new(rule_transform));
+        self
+    }
+
+    /// Leaves out the containers any name of which matches one of `patterns`, e.g. `ops-*`, as
+    /// logged at debug level.
+    pub fn exclude_names(mut self, patterns: Vec<NamePattern>) -> DynamicConfigurationBuilder {
+        self.excluded_names.extend(patterns);
+        self
+    }
+
+    pub fn container_error_policy(
+        mut self,
+        container_error_policy: ContainerErrorPolicy,
+    ) -> DynamicConfigurationBuilder {
+        self.container_error_policy = container_error_policy;
+        self
+    }
+
+    /// Containers left out so far because they could not be added, under
+    /// [`ContainerErrorPolicy::SkipAndReport`].
+    pub fn skipped_containers(&self) -> &[SkippedContainer] {
+        &self.skipped_containers
+    }
+
+    /// Adds the routers and services of `container`, failing when it cannot be added unless
+    /// skipped according to the container error policy.
+    pub fn add_container(
+        mut self,
+        container: &TraefikedContainer,
+    ) -> anyhow::Result<DynamicConfigurationBuilder> {
+        self.try_insert_container(container)?;
+        Ok(self)
+    }
+
+    /// Adds every container, failing with the errors of all the containers that cannot be added
+    /// rather than only the first one, unless skipped according to the container error policy.
+    pub fn add_containers<'a>(
+        mut self,
+        containers: impl IntoIterator<Item = &'a TraefikedContainer>,
+    ) -> anyhow::Result<DynamicConfigurationBuilder> {
+        let errors: Vec<String> = containers
+            .into_iter()
+            .filter_map(|container| {
+                self.try_insert_container(container)
+                    .err()
+                    .map(|e| format!("Container '{}': {}", container.name, e))
+            })
+            .collect();
+
+        if !errors.is_empty() {
+            return Err(anyhow!(
+                "Cannot add {} container(s):\n{}",
+                errors.len(),
+                errors.join("\n")
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Inserts `container`, or under [`ContainerErrorPolicy::SkipAndReport`] leaves the builder as
+    /// it was and records the container as skipped when it cannot be inserted.
+    fn try_insert_container(&mut self, container: &TraefikedContainer) -> anyhow::Result<()> {
+        let excluded_by = self.excluded_names.iter().find(|pattern| {
+            std::iter::once(&container.name)
+                .chain(&container.aliases)
+                .any(|name| pattern.matches(name))
+        });
+        if let Some(pattern) = excluded_by {
+            tracing::debug!(
+                "Container '{}' excluded by pattern '{}'",
+                container.name,
+                pattern
+            );
+            return Ok(());
+        }
+
+        // A failing container may have inserted some of its routers and services already, which are
+        // undone rather than restoring a copy of the whole builder
+        if self.container_error_policy == ContainerErrorPolicy::SkipAndReport {
+            self.changes = Some(vec![]);
+        }
+        let result = self.insert_container(container);
+
+        match (result, self.changes.take()) {
+            (Err(e), Some(changes)) => {
+                self.undo(changes);
+                self.skipped_containers.push(SkippedContainer {
+                    name: container.name.clone(),
+                    reason: e.to_string(),
+                });
+                Ok(())
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Records the previous value of an entry about to change, when recording the changes of a
+    /// container.
+    fn record(&mut self, change: impl FnOnce(&Self) -> Change) {
+        if self.changes.is_some() {
+            let change = change(self);
+            self.changes.get_or_insert_with(Vec::new).push(change);
+        }
+    }
+
+    /// Undoes `changes`, most recent first.
+    fn undo(&mut self, changes: Vec<Change>) {
+        for change in changes.into_iter().rev() {
+            match change {
+                Change::Container(key) => {
+                    self.containers.remove(&key);
+                }
+                Change::ComposeService(key) => {
+                    self.compose_services.remove(&key);
+                }
+                Change::Owner(key, previous) => restore(&mut self.owners, key, previous),
+                Change::Rename(key, previous) => restore(&mut self.renames, key, previous),
+                Change::Router(key, previous) => restore(&mut self.routers, key, previous),
+                Change::Service(key, previous) => restore(&mut self.services, key, previous),
+                Change::TcpRouter(key, previous) => restore(&mut self.tcp.routers, key, previous),
+                Change::TcpService(key, previous) => restore(&mut self.tcp.services, key, previous),
+                Change::UdpRouter(key, previous) => restore(&mut self.udp.routers, key, previous),
+                Change::UdpService(key, previous) => restore(&mut self.udp.services, key, previous),
+            }
+        }
+    }
+
+    /// Inserts the HTTP service of a container, adding its servers to the one of the container it
+    /// is a replica of.
+    fn insert_service(
+        &mut self,
+        name: HttpServiceName,
+        service: HttpServiceConfiguration,
+        is_replica: bool,
+    ) {
+        self.record(|builder| Change::Service(name.clone(), builder.services.get(&name).cloned()));
+        match self.services.get_mut(&name) {
+            Some(existing) if is_replica => existing.extend_servers(service),
+            _ => {
+                self.services.insert(name, service);
+            }
+        }
+    }
+
+    fn insert_router(&mut self, name: HttpRouterName, router: HttpRouterConfiguration) {
+        self.record(|builder| Change::Router(name.clone(), builder.routers.get(&name).cloned()));
+        self.routers.insert(name, router);
+    }
+
+    fn insert_container(&mut self, container: &TraefikedContainer) -> anyhow::Result<()> {
+        let compose_replica = compose_replica(&self.compose_services, &self.containers, container);
+        let container = compose_replica.as_ref().unwrap_or(container);
+
+        // Replicas, e.g. of `docker compose up --scale`, add their servers to the services of the
+        // first one, their routers and services being claimed on its behalf.
+        let mut replica_of = None;
+        match self.containers.get(&container.service_name) {
+            None => {
+                if let Some(compose_service) = &container.compose_service {
+                    if !self.compose_services.contains_key(compose_service) {
+                        self.record(|_| Change::ComposeService(compose_service.clone()));
+                        self.compose_services
+                            .insert(compose_service.clone(), container.service_name.clone());
+                    }
+                }
+                self.record(|_| Change::Container(container.service_name.clone()));
+                self.containers
+                    .insert(container.service_name.clone(), container.clone());
+            }
+            Some(existing) if existing.name == container.name => {}
+            Some(existing) if are_replicas(existing, container) => {
+                replica_of = Some(existing.name.clone());
+            }
+            Some(existing) => {
+                // Other policies settle each of its routers and services when claimed
+                if self.collision_policy == CollisionPolicy::Error {
+                    return Err(anyhow!(
+                        "Containers '{}' and '{}' both map to the Traefik name '{}' but are not \
+                         replicas, their routers or services differing (collision policy: {})",
+                        existing.name,
+                        container.name,
+                        container.service_name,
+                        self.collision_policy
+                    ));
+                }
+            }
+        }
+        let is_replica = replica_of.is_some();
+        let owner = replica_of.as_ref().unwrap_or(&container.name);
+
+        match &container.config {
+            TraefikedContainerConfig::SinglePort(config) => {
+                let urls = self.server_urls(
+                    container,
+                    &container.service_name,
+                    &config.target_ports,
+                    &config.load_balancer,
+                )?;
+
+                let service_name = self.claim("HTTP service", &container.service_name, owner)?;
+                if let Some(service_name) = &service_name {
+                    let service = self.load_balancer_configuration(urls, &config.load_balancer);
+                    self.insert_service(service_name.clone(), service, is_replica);
+                }
+
+                if let Some(router_name) = self.claim("HTTP router", &config.router_name, owner)? {
+                    let service_name =
+                        service_name.unwrap_or_else(|| self.prefixed(&container.service_name));
+                    let router = self.router_configuration(container, config, &service_name);
+                    self.insert_router(router_name, router);
+                }
+            }
+            TraefikedContainerConfig::MultiplePorts(config) => {
+                for c in config {
+                    // Services of other providers are referenced as-is
+                    let service_name = if is_provider_qualified(&c.service_name) {
+                        None
+                    } else {
+                        let urls = self.server_urls(
+                            container,
+                            &c.service_name,
+                            &c.target_ports,
+                            &c.config.load_balancer,
+                        )?;
+
+                        let service_name = self.claim("HTTP service", &c.service_name, owner)?;
+                        if let Some(service_name) = &service_name {
+                            let service =
+                                self.load_balancer_configuration(urls, &c.config.load_balancer);
+                            self.insert_service(service_name.clone(), service, is_replica);
+                        }
+                        service_name
+                    };
+
+                    if let Some(router_name) =
+                        self.claim("HTTP router", &c.config.router_name, owner)?
+                    {
+                        let service_name =
+                            service_name.unwrap_or_else(|| self.prefixed(&c.service_name));
+                        let router = self.router_configuration(container, &c.config, &service_name);
+                        self.insert_router(router_name, router);
+                    }
+                }
+            }
+        }
+
+        for c in &container.tcp {
+            let declared_service_name = c.service_name.as_ref().unwrap_or(&container.service_name);
+            let service_name = if is_provider_qualified(declared_service_name) {
+                None
+            } else {
+                let address = if self.is_empty_service(container) {
+                    None
+                } else {
+                    Some(self.server_address(container, c.target_port)?)
+                };
+
+                let service_name = self.claim("TCP service", declared_service_name, owner)?;
+                if let Some(service_name) = &service_name {
+                    let service = TcpServiceConfiguration::load_balancer(address);
+                    self.record(|builder| {
+                        Change::TcpService(
+                            service_name.clone(),
+                            builder.tcp.services.get(service_name).cloned(),
+                        )
+                    });
+                    match self.tcp.services.get_mut(service_name) {
+                        Some(existing) if is_replica => existing.extend_servers(service),
+                        _ => {
+                            self.tcp.services.insert(service_name.clone(), service);
+                        }
+                    }
+                }
+                service_name
+            };
+
+            if let Some(router_name) = self.claim("TCP router", &c.router_name, owner)? {
+                let service_name =
+                    service_name.unwrap_or_else(|| self.prefixed(declared_service_name));
+                let mut router = TcpRouterConfiguration::new(c, &service_name);
+                router.rule = self.transformed_rule(&router.rule, container);
+                if let Some(tls) = &mut router.tls {
+                    if tls.cert_resolver.is_none() && tls.passthrough != Some(true) {
+                        tls.cert_resolver = self.default_cert_resolver.clone();
+                    }
+                }
+                self.record(|builder| {
+                    Change::TcpRouter(
+                        router_name.clone(),
+                        builder.tcp.routers.get(&router_name).cloned(),
+                    )
+                });
+                self.tcp.routers.insert(router_name, router);
+            }
+        }
+
+        for c in &container.udp {
+            let declared_service_name = c.service_name.as_ref().unwrap_or(&container.service_name);
+            let service_name = if is_provider_qualified(declared_service_name) {
+                None
+            } else {
+                let address = if self.is_empty_service(container) {
+                    None
+                } else {
+                    Some(self.server_address(container, c.target_port)?)
+                };
+
+                let service_name = self.claim("UDP service", declared_service_name, owner)?;
+                if let Some(service_name) = &service_name {
+                    let service = UdpServiceConfiguration::load_balancer(address);
+                    self.record(|builder| {
+                        Change::UdpService(
+                            service_name.clone(),
+                            builder.udp.services.get(service_name).cloned(),
+                        )
+                    });
+                    match self.udp.services.get_mut(service_name) {
+                        Some(existing) if is_replica => existing.extend_servers(service),
+                        _ => {
+                            self.udp.services.insert(service_name.clone(), service);
+                        }
+                    }
+                }
+                service_name
+            };
+
+            if let Some(router_name) = self.claim("UDP router", &c.router_name, owner)? {
+                let service_name =
+                    service_name.unwrap_or_else(|| self.prefixed(declared_service_name));
+                self.record(|builder| {
+                    Change::UdpRouter(
+                        router_name.clone(),
+                        builder.udp.routers.get(&router_name).cloned(),
+                    )
+                });
+                self.udp
+                    .routers
+                    .insert(router_name, UdpRouterConfiguration::new(c, &service_name));
+            }
+        }
+
+        for (service_name, services) in &container.weighted_services {
+            if let Some(service_name) = self.claim("HTTP service", service_name, owner)? {
+                let services: Vec<TraefikedWeightedService> = services
+                    .iter()
+                    .map(|service| TraefikedWeightedService {
+                        name: self.prefixed(&service.name),
+                        ..service.clone()
+                    })
+                    .collect();
+                self.insert_service(
+                    service_name,
+                    HttpServiceConfiguration::from(services.as_slice()),
+                    false,
+                );
+            }
+        }
+
+        // Nothing fails past this point, so these are not recorded
+        self.middlewares.extend(container.middlewares.clone());
+        self.servers_transports
+            .extend(container.servers_transports.clone());
+
+        Ok(())
+    }
+
+    /// Adds an HTTP router that is not declared by container labels, e.g. a redirection.
+    /// Routers of containers with the same name are handled by the collision policy.
+    pub fn add_router(
+        mut self,
+        name: impl Into<String>,
+        router: HttpRouterConfiguration,
+    ) -> anyhow::Result<DynamicConfigurationBuilder> {
+        if let Some(name) = self.claim("HTTP router", &name.into(), BUILDER_OWNER)? {
+            self.routers.insert(name, router);
+        }
+        Ok(self)
+    }
+
+    /// Adds an HTTP service that is not declared by container labels, e.g. a maintenance page.
+    /// Services of containers with the same name are handled by the collision policy.
+    pub fn add_service(
+        mut self,
+        name: impl Into<String>,
+        service: HttpServiceConfiguration,
+    ) -> anyhow::Result<DynamicConfigurationBuilder> {
+        if let Some(name) = self.claim("HTTP service", &name.into(), BUILDER_OWNER)? {
+            self.services.insert(name, service);
+        }
+        Ok(self)
+    }
+
+    /// Adds a middleware that is not declared by container labels, e.g. one shared by all
+    /// routers. Middlewares of containers with the same name replace it.
+    pub fn add_middleware(
+        mut self,
+        name: impl Into<String>,
+        middleware: impl Into<MiddlewareConfiguration>,
+    ) -> DynamicConfigurationBuilder {
+        self.middlewares.insert(name.into(), middleware.into());
+        self
+    }
+
+    /// Adds a servers transport that is not declared by container labels, which services can
+    /// reference through their `serverstransport` label. Servers transports of containers with
+    /// the same name replace it.
+    pub fn add_servers_transport(
+        mut self,
+        name: impl Into<String>,
+        servers_transport: ServersTransportConfiguration,
+    ) -> DynamicConfigurationBuilder {
+        self.servers_transports
+            .insert(name.into(), servers_transport);
+        self
+    }
+
+    /// Adds a certificate to serve, e.g. one signed by an internal CA.
+    pub fn add_certificate(mut self, certificate: TlsCertificate) -> DynamicConfigurationBuilder {
+        self.tls.certificates.push(certificate);
+        self
+    }
+
+    /// Adds TLS options that routers can reference by `name` through their `tls.options` label.
+    pub fn add_tls_options(
+        mut self,
+        name: impl Into<String>,
+        options: TlsOptions,
+    ) -> DynamicConfigurationBuilder {
+        self.tls.options.insert(name.into(), options);
+        self
+    }
+
+    /// Hand-written configuration to merge with the generated one, e.g. middlewares or services
+    /// that are not containers. Its routers are left as-is, without default entrypoints.
+    pub fn extra_configuration(
+        mut self,
+        extra_configuration: DynamicConfiguration,
+    ) -> DynamicConfigurationBuilder {
+        self.extra_configuration = Some(extra_configuration);
+        self
+    }
+
+    pub fn extra_configuration_policy(
+        mut self,
+        policy: ExtraConfigurationPolicy,
+    ) -> DynamicConfigurationBuilder {
+        self.extra_configuration_policy = policy;
+        self
+    }
+
+    /// Load balancer of `urls`, with the default servers transport unless `load_balancer`
+    /// declares one.
+    fn load_balancer_configuration(
+        &self,
+        urls: Vec<ServiceUrl>,
+        load_balancer: &TraefikedLoadBalancerConfig,
+    ) -> HttpServiceConfiguration {
+        let mut service = HttpServiceConfiguration::load_balancer_servers(urls, load_balancer);
+        if let HttpServiceType::LoadBalancer(load_balancer) = &mut service.service_type {
+            if load_balancer.servers_transport.is_none() {
+                load_balancer.servers_transport = self.default_servers_transport.clone();
+            }
+        }
+        service
+    }
+
+    /// Router of `config` targeting `service_name`, with the default middlewares it does not
+    /// declare already and the default TLS configuration when it has none.
+    fn router_configuration(
+        &self,
+        container: &TraefikedContainer,
+        config: &TraefikedContainerSinglePortConfig,
+        service_name: &HttpServiceName,
+    ) -> HttpRouterConfiguration {
+        let mut router = HttpRouterConfiguration::new(config, service_name);
+        router.rule = self.transformed_rule(&router.rule, container);
+        if self.compute_priorities && router.priority.is_none() {
+            // Traefik's default, the length of the rule in bytes
+            router.priority = i64::try_from(router.rule.len()).ok();
+        }
+
+        // An empty middlewares label opts out of the defaults
+        if config.middlewares.as_ref().is_none_or(|m| !m.is_empty()) {
+            for middleware in &self.default_middlewares {
+                if !router.middlewares.contains(middleware) {
+                    router.middlewares.push(middleware.clone());
+                }
+            }
+        }
+        if router.tls.is_none() && !config.tls_disabled {
+            router.tls = self
+                .default_tls
+                .as_ref()
+                .map(HttpRouterTlsConfiguration::from);
+        }
+        if let Some(tls) = &mut router.tls {
+            if tls.cert_resolver.is_none() {
+                tls.cert_resolver = self.default_cert_resolver.clone();
+            }
+        }
+        router
+    }
+
+    /// `rule` of a router of `container`, rewritten by the rule transform if any.
+    fn transformed_rule(&self, rule: &str, container: &TraefikedContainer) -> RuleValue {
+        match &self.rule_transform {
+            Some(rule_transform) => rule_transform(rule, container),
+            None => rule.to_owned(),
+        }
+    }
+
+    /// `name` with the name prefix, unless it references a service of another provider.
+    fn prefixed(&self, name: &str) -> String {
+        if is_provider_qualified(name) {
+            name.to_owned()
+        } else {
+            format!("{}{}", self.name_prefix, name)
+        }
+    }
+
+    /// Records `owner`, a container name or [`BUILDER_OWNER`], as the owner of the `kind` named
+    /// `name`, returning the name it should be inserted under according to the collision policy,
+    /// if any. Names claimed by containers get the name prefix.
+    fn claim(
+        &mut self,
+        kind: &'static str,
+        name: &str,
+        owner: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let prefixed;
+        let name = if owner == BUILDER_OWNER {
+            name
+        } else {
+            prefixed = self.prefixed(name);
+            prefixed.as_str()
+        };
+
+        if let Some(renamed) = self.renames.get(&(kind, name.to_owned(), owner.to_owned())) {
+            return Ok(Some(renamed.clone()));
+        }
+
+        let key = (kind, name.to_owned());
+        self.record(|builder| Change::Owner(key.clone(), builder.owners.get(&key).cloned()));
+
+        let mut previous_owner = match self.owners.entry((kind, name.to_owned())) {
+            Entry::Vacant(entry) => {
+                entry.insert(owner.to_owned());
+                return Ok(Some(name.to_owned()));
+            }
+            Entry::Occupied(entry) => entry,
+        };
+
+        if *previous_owner.get() == owner {
+            return Ok(Some(name.to_owned()));
+        }
+
+        match self.collision_policy {
+            CollisionPolicy::Error => Err(anyhow!(
+                "{} '{}' is declared by both {} (collision policy: {})",
+                kind,
+                name,
+                describe_owners(previous_owner.get(), owner),
+                self.collision_policy
+            )),
+            CollisionPolicy::FirstWins => {
+                tracing::warn!(
+                    "{} '{}' of {} ignored, already declared by {} (collision policy: {})",
+                    kind,
+                    name,
+                    describe_owner(owner),
+                    describe_owner(previous_owner.get()),
+                    self.collision_policy
+                );
+                Ok(None)
+            }
+            CollisionPolicy::LastWins => {
+                tracing::warn!(
+                    "{} '{}' of {} replaced by the one of {} (collision policy: {})",
+                    kind,
+                    name,
+                    describe_owner(previous_owner.get()),
+                    describe_owner(owner),
+                    self.collision_policy
+                );
+                previous_owner.insert(owner.to_owned());
+                Ok(Some(name.to_owned()))
+            }
+            CollisionPolicy::AutoSuffix => {
+                let previous_owner = previous_owner.get().clone();
+                let renamed = (2..)
+                    .map(|suffix| format!("{}-{}", name, suffix))
+                    .find(|renamed| !self.owners.contains_key(&(kind, renamed.clone())))
+                    .expect("Should find a free name");
+                tracing::warn!(
+                    "{} '{}' of {} renamed to '{}', already declared by {} (collision policy: {})",
+                    kind,
+                    name,
+                    describe_owner(owner),
+                    renamed,
+                    describe_owner(&previous_owner),
+                    self.collision_policy
+                );
+                let rename = (kind, name.to_owned(), owner.to_owned());
+                self.record(|_| Change::Owner((kind, renamed.clone()), None));
+                self.record(|_| Change::Rename(rename.clone(), None));
+                self.owners
+                    .insert((kind, renamed.clone()), owner.to_owned());
+                self.renames.insert(rename, renamed.clone());
+                Ok(Some(renamed))
+            }
+        }
+    }
+
+    /// Whether the services of `container` have no servers, the container having no ports to
+    /// target them on.
+    fn is_empty_service(&self, container: &TraefikedContainer) -> bool {
+        let ports = if self.use_container_ip {
+            container.private_ports()
+        } else {
+            container.public_ports()
+        };
+
+        self.allow_empty_services && ports.is_empty()
+    }
+
+    /// Base URL of `container`, its own when overridden by label.
+    fn container_base_url<'a>(&'a self, container: &'a TraefikedContainer) -> &'a Url {
+        container.base_url.as_ref().unwrap_or(&self.base_url)
+    }
+
+    /// Base URLs of the servers of `container`: its own when overridden by label, otherwise the
+    /// base URL and the additional ones, unless targeted on its IP address.
+    fn container_base_urls<'a>(&'a self, container: &'a TraefikedContainer) -> Vec<&'a Url> {
+        match &container.base_url {
+            Some(base_url) => vec![base_url],
+            None if self.use_container_ip => vec![&self.base_url],
+            None => std::iter::once(&self.base_url)
+                .chain(&self.additional_base_urls)
+                .collect(),
+        }
+    }
+
+    /// Host and port a container is reached on: the base URL host and the host port `target_port`
+    /// is published on or the first public port, or in container IP mode the container IP and
+    /// `target_port` or the first private port.
+    fn server_host_port<'a>(
+        &'a self,
+        container: &'a TraefikedContainer,
+        target_port: Option<u16>,
+    ) -> anyhow::Result<(&'a str, u16)> {
+        let (host, ports) = if self.use_container_ip {
+            (container.ip_address()?, container.private_ports())
+        } else {
+            let host = self
+                .container_base_url(container)
+                .host_str()
+                .ok_or(anyhow!("Cannot get a host from base_url."))?;
+            (host, container.public_ports())
+        };
+
+        let port = match target_port {
+            Some(port) if self.use_container_ip => port,
+            Some(port) => container.published_port(port).ok_or(anyhow!(
+                "Port {} of container '{}' is not published on the host",
+                port,
+                container.name
+            ))?,
+            None => ports.first().cloned().ok_or(anyhow!(
+                "No {} port specified for container '{}'",
+                if self.use_container_ip {
+                    "private"
+                } else {
+                    "public"
+                },
+                container.name
+            ))?,
+        };
+
+        Ok((host, port))
+    }
+
+    /// `host:port` address of a TCP or UDP server.
+    fn server_address(
+        &self,
+        container: &TraefikedContainer,
+        target_port: Option<u16>,
+    ) -> anyhow::Result<String> {
+        let (host, port) = self.server_host_port(container, target_port)?;
+
+        Ok(format!("{}:{}", host, port))
+    }
+
+    /// Servers of an HTTP service: its explicit URL, or one URL per target port and base URL,
+    /// the first public port being targeted without any.
+    fn server_urls(
+        &self,
+        container: &TraefikedContainer,
+        service_name: &str,
+        target_ports: &[u16],
+        load_balancer: &TraefikedLoadBalancerConfig,
+    ) -> anyhow::Result<Vec<ServiceUrl>> {
+        if let Some(url) = &load_balancer.url {
+            return Ok(vec![ServiceUrl::new(url.clone())]);
+        }
+        if self.is_empty_service(container) {
+            return Ok(vec![]);
+        }
+
+        let target_ports: Vec<Option<u16>> = match target_ports {
+            [] => vec![None],
+            ports => ports.iter().cloned().map(Some).collect(),
+        };
+        let base_urls = self.container_base_urls(container);
+
+        target_ports
+            .into_iter()
+            .cartesian_product(base_urls)
+            .map(|(target_port, base_url)| {
+                let url = self.server_url(
+                    container,
+                    base_url,
+                    service_name,
+                    target_port,
+                    load_balancer,
+                )?;
+                Ok(ServiceUrl::weighted(
+                    url,
+                    self.server_weights.get(base_url).copied(),
+                ))
+            })
+            .collect()
+    }
+
+    /// URL of a server, keeping the path of the base URL and appending the segment chosen by
+    /// [`ServerPathSegment`].
+    fn server_url(
+        &self,
+        container: &TraefikedContainer,
+        base_url: &Url,
+        service_name: &str,
+        target_port: Option<u16>,
+        load_balancer: &TraefikedLoadBalancerConfig,
+    ) -> anyhow::Result<Url> {
+        let (host, port) = self.server_host_port(container, target_port)?;
+
+        let is_https_port = |port: &u16| {
+            self.https_ports.contains(port) || (self.detect_https && HTTPS_PORTS.contains(port))
+        };
+        let detected_scheme = (load_balancer.scheme.is_none()
+            && [target_port, Some(port)]
+                .iter()
+                .flatten()
+                .any(is_https_port))
+        .then(|| {
+            tracing::info!(
+                "Using https for port {} of container '{}'",
+                port,
+                container.name
+            );
+            "https".to_owned()
+        });
+        let scheme = load_balancer.scheme.as_ref().or(detected_scheme.as_ref());
+        let is_https = scheme.is_some_and(|scheme| scheme == "https");
+
+        if is_https && self.https_base_url.is_none() {
+            // Only the opt-in detection of port 443 and 8443 keeps switching the scheme of the
+            // base URL, as it did before https base URLs
+            let detected = load_balancer.scheme.is_none()
+                && self.detect_https
+                && [target_port, Some(port)]
+                    .iter()
+                    .flatten()
+                    .any(|port| HTTPS_PORTS.contains(port));
+            if !detected {
+                return Err(anyhow!(
+                    "Container '{}' targets an https server on port {} but no https base URL was given",
+                    container.name,
+                    port
+                ));
+            }
+            tracing::debug!(
+                "No https base URL given, switching base URL '{}' to https for container '{}'",
+                base_url,
+                container.name
+            );
+        }
+
+        // https servers reached through the base URL use the https one
+        let https_base_url = self
+            .https_base_url
+            .as_ref()
+            .filter(|_| is_https && base_url == &self.base_url);
+        let mut url = https_base_url.unwrap_or(base_url).clone();
+
+        if self.use_container_ip {
+            url.set_host(Some(host))
+                .map_err(|_| anyhow!("Cannot set container IP '{}' on base_url.", host))?;
+        }
+
+        url.set_port(Some(port))
+            .map_err(|_| anyhow!("Cannot append container port to base_url."))?;
+
+        let segment = match self.server_path_segment {
+            ServerPathSegment::None => None,
+            ServerPathSegment::ContainerName => Some(container.name.as_str()),
+            ServerPathSegment::ServiceName => Some(service_name),
+        };
+        if let Some(segment) = segment {
+            url.path_segments_mut()
+                .map_err(|_| anyhow!("Cannot append a path segment to base_url."))?
+                .pop_if_empty()
+                .push(segment);
+        }
+
+        if let Some(scheme) = scheme.filter(|scheme| url.scheme() != scheme.as_str()) {
+            // `set_scheme` refuses to switch between special (http) and non-special (h2c) schemes
+            if url.set_scheme(scheme).is_err() {
+                url = Url::parse(&format!("{}{}", scheme, &url[Position::AfterScheme..])).map_err(
+                    |_| {
+                        anyhow!(
+                            "Cannot use scheme '{}' for container '{}' on base URL '{}'{}",
+                            scheme,
+                            container.name,
+                            base_url,
+                            if scheme == "https" && self.https_base_url.is_none() {
+                                ", an https base URL being required"
+                            } else {
+                                ""
+                            }
+                        )
+                    },
+                )?;
+            }
+        }
+
+        Ok(url)
+    }
+
+    /// Builds the configuration, failing with every problem found: routers targeting undefined
+    /// services or TLS options, empty names, services without servers unless empty services are
+    /// allowed, conflicts with the extra configuration under [`ExtraConfigurationPolicy::Error`]
+    /// and, in strict mode, references to undefined middlewares. Other issues are left to
+    /// [`DynamicConfiguration::validate`].
+    pub fn build(self) -> Result<DynamicConfiguration, ValidationErrors> {
+        let (configuration, errors) = self.assemble();
+
+        if errors.is_empty() {
+            Ok(configuration)
+        } else {
+            Err(ValidationErrors { errors })
+        }
+    }
+
+    /// Builds the configuration even when [`build`](Self::build) would fail, the problems being
+    /// logged as warnings.
+    pub fn build_unchecked(self) -> DynamicConfiguration {
+        let (configuration, errors) = self.assemble();
+
+        for error in &errors {
+            tracing::warn!("{}", error);
+        }
+        configuration
+    }
+
+    fn assemble(mut self) -> (DynamicConfiguration, Vec<ValidationError>) {
+        if !self.default_entrypoints.is_empty() {
+            let entry_points = self
+                .routers
+                .values_mut()
+                .map(|router| &mut router.entry_points)
+                .chain(
+                    self.tcp
+                        .routers
+                        .values_mut()
+                        .map(|router| &mut router.entry_points),
+                );
+            for entry_points in entry_points.filter(|entry_points| entry_points.is_empty()) {
+                entry_points.clone_from(&self.default_entrypoints);
+            }
+        }
+
+        let mut configuration = DynamicConfiguration {
+            http: HttpConfiguration {
+                routers: self.routers,
+                services: self.services,
+                middlewares: self.middlewares,
+                servers_transports: self.servers_transports,
+            },
+            tcp: self.tcp,
+            udp: self.udp,
+            tls: self.tls,
+        };
+        let mut errors = vec![];
+
+        if let Some(extra_configuration) = self.extra_configuration {
+            let policy = MergePolicy::from(self.extra_configuration_policy);
+            let conflicts = configuration.merge_entries(extra_configuration, policy);
+            match policy {
+                MergePolicy::Error => errors.extend(
+                    conflicts
+                        .into_iter()
+                        .map(ValidationError::ExtraConfigurationConflict),
+                ),
+                MergePolicy::PreferOther => {
+                    for conflict in &conflicts {
+                        tracing::warn!(
+                            "Generated {} replaced by the extra configuration",
+                            conflict
+                        );
+                    }
+                }
+                MergePolicy::PreferSelf => {
+                    for conflict in &conflicts {
+                        tracing::warn!(
+                            "Ignoring the {} of the extra configuration, already generated",
+                            conflict
+                        );
+                    }
+                }
+            }
+        }
+
+        if !self.trailing_slash {
+            for service in configuration.http.services.values_mut() {
+                service.set_trailing_slash(false);
+            }
+        }
+
+        // Undefined middlewares only make Traefik skip the router, which is tolerated unless strict
+        errors.extend(
+            configuration
+                .validate()
+                .into_iter()
+                .filter(|issue| match issue.error {
+                    ValidationError::UndefinedMiddleware { .. } => self.strict,
+                    ValidationError::NoServers { .. } => !self.allow_empty_services,
+                    _ => issue.severity == Severity::Error,
+                })
+                .map(|issue| issue.error),
+        );
+
+        (configuration, errors)
+    }
+}
+
+impl DynamicConfiguration {
+    /// Whether the configuration has no entries at all, e.g. when no container has Traefik labels.
+    pub fn is_empty(&self) -> bool {
+        self.http.is_empty() && self.tcp.is_empty() && self.udp.is_empty() && self.tls.is_empty()
+    }
+
+    /// Number of entries of each section, e.g. to notice that no router was generated.
+    pub fn stats(&self) -> ConfigStats {
+        ConfigStats {
+            routers: self.http.routers.len(),
+            services: self.http.services.len(),
+            middlewares: self.http.middlewares.len(),
+            servers_transports: self.http.servers_transports.len(),
+            tcp_routers: self.tcp.routers.len(),
+            tcp_services: self.tcp.services.len(),
+            udp_routers: self.udp.routers.len(),
+            udp_services: self.udp.services.len(),
+            tls_certificates: self.tls.certificates.len(),
+            tls_options: self.tls.options.len(),
+        }
+    }
+
+    /// Hash of the content of the configuration, e.g. for an `ETag` formatted as `{:016x}`.
+    ///
+    /// Computed with 64-bit FNV-1a over the compact JSON of the configuration with sorted keys, so
+    /// it is the same across runs and Rust versions for identical content, whatever order entries
+    /// were added in. URLs are normalized when parsed, e.g. `HTTP://Host:80` being `http://host/`,
+    /// and the configuration holds no floats.
+    pub fn fingerprint(&self) -> u64 {
+        // Every map has string keys, which always serialize
+        let canonical = serde_json::to_value(self)
+            .map(|value| value.to_string())
+            .unwrap_or_default();
+
+        canonical.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    /// One line per HTTP and TCP router with its rule, service and servers, e.g.
+    /// ``to-my-service: Host(`my-service.my-domain.com`) -> my-service [http://192.168.1.100:7878/]``,
+    /// TCP routers being prefixed with `TCP` and long rules truncated.
+    pub fn summary(&self) -> String {
+        let http = self.http.routers.iter().map(|(name, router)| {
+            let servers = match self.http.services.get(&router.service) {
+                Some(HttpServiceConfiguration {
+                    service_type: HttpServiceType::LoadBalancer(load_balancer),
+                }) => load_balancer
+                    .servers
+                    .iter()
+                    .map(|server| server.as_str().to_owned())
+                    .collect(),
+                Some(HttpServiceConfiguration {
+                    service_type: HttpServiceType::Weighted(weighted),
+                }) => weighted
+                    .services
+                    .iter()
+                    .map(|service| match service.weight {
+                        Some(weight) => format!("{} ({})", service.name, weight),
+                        None => service.name.clone(),
+                    })
+                    .collect(),
+                // e.g. a service of another provider
+                None => vec![],
+            };
+            summary_line(name, &router.rule, &router.service, &servers)
+        });
+        let tcp = self.tcp.routers.iter().map(|(name, router)| {
+            let servers = match self.tcp.services.get(&router.service) {
+                Some(TcpServiceConfiguration {
+                    service_type: TcpServiceType::LoadBalancer(load_balancer),
+                }) => load_balancer
+                    .servers
+                    .iter()
+                    .map(|server| server.address.clone())
+                    .collect(),
+                None => vec![],
+            };
+            summary_line(
+                &format!("TCP {}", name),
+                &router.rule,
+                &router.service,
+                &servers,
+            )
+        });
+
+        http.chain(tcp).join("\n")
+    }
+
+    /// HTTP routers by name.
+    pub fn routers(&self) -> &BTreeMap<String, HttpRouterConfiguration> {
+        &self.http.routers
+    }
+
+    pub fn router(&self, name: &str) -> Option<&HttpRouterConfiguration> {
+        self.http.routers.get(name)
+    }
+
+    /// HTTP services by name.
+    pub fn services(&self) -> &BTreeMap<String, HttpServiceConfiguration> {
+        &self.http.services
+    }
+
+    pub fn service(&self, name: &str) -> Option<&HttpServiceConfiguration> {
+        self.http.services.get(name)
+    }
+
+    /// Middlewares by name.
+    pub fn middlewares(&self) -> &BTreeMap<String, MiddlewareConfiguration> {
+        &self.http.middlewares
+    }
+
+    pub fn middleware(&self, name: &str) -> Option<&MiddlewareConfiguration> {
+        self.http.middlewares.get(name)
+    }
+
+    /// Configuration of `containers` reached through `base_url`, with the default builder options.
+    pub fn from_containers(
+        base_url: Url,
+        containers: &[TraefikedContainer],
+    ) -> anyhow::Result<DynamicConfiguration> {
+        Ok(DynamicConfigurationBuilder::new(base_url)
+            .add_containers(containers)?
+            .build()?)
+    }
+
+    /// Merges `other` into this configuration, routers, services, middlewares, servers transports
+    /// and TLS options being merged name by name, and TLS certificates being combined.
+    ///
+    /// Names declared by both configurations are resolved by `policy`, [`MergePolicy::Error`]
+    /// returning all of them at once.
+    pub fn merge(mut self, other: Self, policy: MergePolicy) -> Result<Self, MergeConflicts> {
+        let conflicts = self.merge_entries(other, policy);
+
+        if policy == MergePolicy::Error && !conflicts.is_empty() {
+            return Err(MergeConflicts { conflicts });
+        }
+        Ok(self)
+    }
+
+    /// Inserts the entries of `other`, replacing the ones of the same name when preferring
+    /// `other`, and returns the names declared by both.
+    fn merge_entries(&mut self, other: Self, policy: MergePolicy) -> Vec<MergeConflict> {
+        let prefer_other = policy == MergePolicy::PreferOther;
+
+        let mut conflicts = vec![];
+        conflicts.extend(merge_section(
+            "HTTP router",
+            &mut self.http.routers,
+            other.http.routers,
+            prefer_other,
+        ));
+        conflicts.extend(merge_section(
+            "HTTP service",
+            &mut self.http.services,
+            other.http.services,
+            prefer_other,
+        ));
+        conflicts.extend(merge_section(
+            "middleware",
+            &mut self.http.middlewares,
+            other.http.middlewares,
+            prefer_other,
+        ));
+        conflicts.extend(merge_section(
+            "servers transport",
+            &mut self.http.servers_transports,
+            other.http.servers_transports,
+            prefer_other,
+        ));
+        conflicts.extend(merge_section(
+            "TCP router",
+            &mut self.tcp.routers,
+            other.tcp.routers,
+            prefer_other,
+        ));
+        conflicts.extend(merge_section(
+            "TCP service",
+            &mut self.tcp.services,
+            other.tcp.services,
+            prefer_other,
+        ));
+        conflicts.extend(merge_section(
+            "UDP router",
+            &mut self.udp.routers,
+            other.udp.routers,
+            prefer_other,
+        ));
+        conflicts.extend(merge_section(
+            "UDP service",
+            &mut self.udp.services,
+            other.udp.services,
+            prefer_other,
+        ));
+        conflicts.extend(merge_section(
+            "TLS options",
+            &mut self.tls.options,
+            other.tls.options,
+            prefer_other,
+        ));
+
+        for certificate in other.tls.certificates {
+            if !self.tls.certificates.contains(&certificate) {
+                self.tls.certificates.push(certificate);
+            }
+        }
+
+        conflicts
+    }
+
+    /// Routers, services, middlewares, servers transports, TLS options and certificates added,
+    /// removed or modified in `other` compared to this configuration.
+    pub fn diff(&self, other: &Self) -> ConfigDiff {
+        let mut diff = ConfigDiff::default();
+        diff.section("HTTP router", &self.http.routers, &other.http.routers);
+        diff.section("HTTP service", &self.http.services, &other.http.services);
+        diff.section(
+            "middleware",
+            &self.http.middlewares,
+            &other.http.middlewares,
+        );
+        diff.section(
+            "servers transport",
+            &self.http.servers_transports,
+            &other.http.servers_transports,
+        );
+        diff.section("TCP router", &self.tcp.routers, &other.tcp.routers);
+        diff.section("TCP service", &self.tcp.services, &other.tcp.services);
+        diff.section("UDP router", &self.udp.routers, &other.udp.routers);
+        diff.section("UDP service", &self.udp.services, &other.udp.services);
+        diff.section("TLS options", &self.tls.options, &other.tls.options);
+        diff.section(
+            "TLS certificate",
+            &self.tls.certificates_by_file(),
+            &other.tls.certificates_by_file(),
+        );
+        diff
+    }
+
+    /// Checks the references of the configuration the way Traefik does: routers targeting
+    /// undefined services, middlewares or TLS options, entries with an empty name and services
+    /// without servers. References to other providers, e.g. `auth@file`, are not checked.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut errors = vec![];
+
+        let names = [
+            ("HTTP router", self.http.routers.contains_key("")),
+            ("HTTP service", self.http.services.contains_key("")),
+            ("middleware", self.http.middlewares.contains_key("")),
+            (
+                "servers transport",
+                self.http.servers_transports.contains_key(""),
+            ),
+            ("TCP router", self.tcp.routers.contains_key("")),
+            ("TCP service", self.tcp.services.contains_key("")),
+            ("UDP router", self.udp.routers.contains_key("")),
+            ("UDP service", self.udp.services.contains_key("")),
+            ("TLS options", self.tls.options.contains_key("")),
+        ];
+        errors.extend(
+            names
+                .into_iter()
+                .filter(|(_, empty)| *empty)
+                .map(|(kind, _)| ValidationError::EmptyName { kind }),
+        );
+
+        let router_services = self
+            .http
+            .routers
+            .iter()
+            .map(|(name, router)| {
+                let defined = self.http.services.contains_key(&router.service);
+                ("HTTP router", name, &router.service, defined)
+            })
+            .chain(self.tcp.routers.iter().map(|(name, router)| {
+                let defined = self.tcp.services.contains_key(&router.service);
+                ("TCP router", name, &router.service, defined)
+            }))
+            .chain(self.udp.routers.iter().map(|(name, router)| {
+                let defined = self.udp.services.contains_key(&router.service);
+                ("UDP router", name, &router.service, defined)
+            }));
+        errors.extend(
+            router_services
+                .filter(|(_, _, service, defined)| !defined && !is_provider_qualified(service))
+                .map(
+                    |(router_kind, router, service, _)| ValidationError::UndefinedService {
+                        router_kind,
+                        router: router.clone(),
+                        service: service.clone(),
+                    },
+                ),
+        );
+
+        errors.extend(self.dangling_middleware_references().into_iter().map(
+            |(router, middleware)| ValidationError::UndefinedMiddleware {
+                router: router.to_owned(),
+                middleware: middleware.to_owned(),
+            },
+        ));
+
+        // Traefik always defines the `default` TLS options
+        errors.extend(self.http.routers.iter().filter_map(|(name, router)| {
+            let options = router.tls.as_ref()?.options.as_ref()?;
+            let defined = options == "default"
+                || is_provider_qualified(options)
+                || self.tls.options.contains_key(options);
+            (!defined).then(|| ValidationError::UndefinedTlsOptions {
+                router: name.clone(),
+                options: options.clone(),
+            })
+        }));
+
+        let empty_services = self
+            .http
+            .services
+            .iter()
+            .filter(|(_, service)| {
+                matches!(
+                    &service.service_type,
+                    HttpServiceType::LoadBalancer(load_balancer) if load_balancer.servers.is_empty()
+                )
+            })
+            .map(|(name, _)| ("HTTP service", name))
+            .chain(
+                self.tcp
+                    .services
+                    .iter()
+                    .filter(|(_, service)| {
+                        let TcpServiceType::LoadBalancer(load_balancer) = &service.service_type;
+                        load_balancer.servers.is_empty()
+                    })
+                    .map(|(name, _)| ("TCP service", name)),
+            )
+            .chain(
+                self.udp
+                    .services
+                    .iter()
+                    .filter(|(_, service)| {
+                        let UdpServiceType::LoadBalancer(load_balancer) = &service.service_type;
+                        load_balancer.servers.is_empty()
+                    })
+                    .map(|(name, _)| ("UDP service", name)),
+            );
+        errors.extend(
+            empty_services.map(|(kind, name)| ValidationError::NoServers {
+                kind,
+                name: name.clone(),
+            }),
+        );
+
+        errors
+            .into_iter()
+            .map(|error| ValidationIssue {
+                // Traefik answers 503 for services without servers
+                severity: match error {
+                    ValidationError::NoServers { .. } => Severity::Warning,
+                    _ => Severity::Error,
+                },
+                error,
+            })
+            .collect()
+    }
+
+    /// Router middleware references that match no middleware of the configuration, as
+    /// `(router, middleware)` pairs. Provider-qualified references like `auth@file` are not checked.
+    fn dangling_middleware_references(&self) -> Vec<(&str, &str)> {
+        self.http
+            .routers
+            .iter()
+            .flat_map(|(router_name, router)| {
+                router
+                    .middlewares
+                    .iter()
+                    .map(move |middleware| (router_name.as_str(), middleware.as_str()))
+            })
+            .filter(|(_, middleware)| {
+                !is_provider_qualified(middleware)
+                    && !self.http.middlewares.contains_key(*middleware)
+            })
+            .collect()
+    }
+
+    /// Loads a hand-written configuration from a YAML, JSON or TOML file, TOML being told apart by
+    /// the `.toml` extension.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read '{}'", path.display()))?;
+
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+        {
+            crate::toml::from_str(&content)
+        } else {
+            serde_yaml::from_str(&content).map_err(anyhow::Error::from)
+        }
+        .with_context(|| format!("Cannot parse '{}'", path.display()))
+    }
+
+    /// The configuration in the [`OutputStyle::Pretty`] YAML style.
+    pub fn to_yaml(&self) -> Result<String, SerializationError> {
+        self.to_yaml_styled(OutputStyle::Pretty)
+    }
+
+    pub fn to_yaml_styled(&self, style: OutputStyle) -> Result<String, SerializationError> {
+        to_yaml_string(self, style)
+    }
+
+    /// The configuration in the [`OutputStyle::Pretty`] JSON style.
+    pub fn to_json(&self) -> Result<String, SerializationError> {
+        self.to_json_styled(OutputStyle::Pretty)
+    }
+
+    pub fn to_json_styled(&self, style: OutputStyle) -> Result<String, SerializationError> {
+        Ok(match style {
+            OutputStyle::Pretty => serde_json::to_string_pretty(self)?,
+            OutputStyle::Compact => serde_json::to_string(self)?,
+        })
+    }
+
+    /// The configuration in the TOML format of Traefik's file provider.
+    pub fn to_toml(&self) -> Result<String, SerializationError> {
+        crate::toml::to_string(self).map_err(SerializationError::Toml)
+    }
+}
+
+/// Layout of the serialized configuration. Entries are always sorted by name, and fields keep
+/// the order of Traefik's documentation, so the output only changes with the configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputStyle {
+    /// For humans, e.g. reviewing the configuration in a repository:
+    /// - YAML in block style, indented by two spaces, with sequence items (`- url: ...`) at the
+    ///   indentation of their key and strings only quoted when needed
+    /// - JSON indented by two spaces, one field per line
+    #[default]
+    Pretty,
+    /// For the wire, on a single line: JSON without whitespace. As YAML, this is the subset of
+    /// the flow style that JSON is (every string double-quoted, no anchors nor tags), which
+    /// Traefik's YAML parser reads like any other YAML.
+    Compact,
+}
+
+impl FromStr for OutputStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "pretty" => Ok(OutputStyle::Pretty),
+            "compact" => Ok(OutputStyle::Compact),
+            _ => Err(anyhow!(
+                "Unknown output style '{}', expected pretty or compact",
+                s
+            )),
+        }
+    }
+}
+
+fn to_yaml_string<T: Serialize>(
+    value: &T,
+    style: OutputStyle,
+) -> Result<String, SerializationError> {
+    Ok(match style {
+        OutputStyle::Pretty => serde_yaml::to_string(value)?,
+        OutputStyle::Compact => serde_json::to_string(value)?,
+    })
+}
+
+/// Media type of YAML responses, `text/yaml` being common but `application/yaml` the
+/// registered one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum YamlContentType {
+    #[default]
+    TextYaml,
+    ApplicationYaml,
+}
+
+impl YamlContentType {
+    pub fn header_value(self) -> HeaderValue {
+        match self {
+            YamlContentType::TextYaml => HeaderValue::from_static("text/yaml; charset=utf-8"),
+            YamlContentType::ApplicationYaml => {
+                HeaderValue::from_static("application/yaml; charset=utf-8")
+            }
+        }
+    }
+}
+
+impl FromStr for YamlContentType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text/yaml" => Ok(YamlContentType::TextYaml),
+            "application/yaml" => Ok(YamlContentType::ApplicationYaml),
+            _ => Err(anyhow!(
+                "Unknown YAML content type '{}', expected text/yaml or application/yaml",
+                s
+            )),
+        }
+    }
+}
+
+impl DynamicConfiguration {
+    /// The configuration as a YAML response in `style`, sent as `content_type`.
+    pub fn to_yaml_response(&self, content_type: YamlContentType, style: OutputStyle) -> Response {
+        yaml_response(self, content_type, style)
+    }
+}
+
+impl IntoResponse for DynamicConfiguration {
+    fn into_response(self) -> Response {
+        self.to_yaml_response(YamlContentType::default(), OutputStyle::default())
+    }
+}
+
+/// `value` as a YAML response, or a 500 JSON error when it cannot be serialized.
+fn yaml_response<T: Serialize>(
+    value: &T,
+    content_type: YamlContentType,
+    style: OutputStyle,
+) -> Response {
+    match to_yaml_string(value, style) {
+        Ok(payload) => (
+            [(header::CONTENT_TYPE, content_type.header_value())],
+            payload,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("{}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+    use crate::docker::DockerOptions;
+    use crate::middleware::{MiddlewareType, StripPrefixMiddleware};
+    use crate::tcp::TraefikedTcpRouterTlsConfig;
+    use crate::{
+        labels, LabelRegexes, PortMapping, PortProtocol, TraefikedContainerMultiPortConfig,
+        TraefikedStickyCookieConfig, TraefikedTlsDomain,
+    };
+    use bollard::models::{ContainerSummary, Port};
+
+    #[test]
+    fn test_yaml_serialize() -> anyhow::Result<()> {
+        let dynamic_configuration = DynamicConfiguration {
+            http: HttpConfiguration {
+                routers: [(
+                    "to-my-service".to_owned(),
+                    HttpRouterConfiguration {
+                        entry_points: vec![],
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        service: "my-service".to_owned(),
+                        middlewares: vec![],
+                        rule_syntax: None,
+                        priority: None,
+                        tls: None,
+                        observability: None,
+                    },
+                )]
+                .iter()
+                .cloned()
+                .collect(),
+                services: [(
+                    "my-service".to_owned(),
+                    HttpServiceConfiguration {
+                        service_type: HttpServiceType::LoadBalancer(
+                            LoadBalancerHttpServiceConfiguration {
+                                servers: vec![
+                                    ServiceUrl::new("http://192.168.1.100:7878".try_into()?),
+                                    ServiceUrl::new("http://my-service.local:7878".try_into()?),
+                                ],
+                                pass_host_header: None,
+                                health_check: None,
+                                sticky: None,
+                                servers_transport: None,
+                                response_forwarding: None,
+                            },
+                        ),
+                    },
+                )]
+                .iter()
+                .cloned()
+                .collect(),
+                middlewares: BTreeMap::new(),
+                servers_transports: BTreeMap::new(),
+            },
+            tcp: TcpConfiguration::default(),
+            udp: UdpConfiguration::default(),
+            tls: TlsConfiguration::default(),
+        };
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+        - url: http://my-service.local:7878/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+
+        let parsed: DynamicConfiguration = serde_yaml::from_str(expected)?;
+        assert_eq!(serde_yaml::to_string(&parsed)?, expected);
+        Ok(())
+    }
+
+    /// Fails to serialize, as a map with non-string keys would.
+    struct Unserializable;
+
+    impl Serialize for Unserializable {
+        fn serialize<S: Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("cannot serialize"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_yaml_response() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let response = DynamicConfiguration::from_containers(base_url, &[])?.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/yaml; charset=utf-8"))
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        assert_eq!(body, "http:\n  routers: {}\n  services: {}\n");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("text/yaml", "text/yaml; charset=utf-8")]
+    #[case(" Application/YAML", "application/yaml; charset=utf-8")]
+    fn test_yaml_content_type(
+        #[case] value: &str,
+        #[case] expected_header_value: &'static str,
+    ) -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let response = DynamicConfiguration::from_containers(base_url, &[])?
+            .to_yaml_response(value.parse()?, OutputStyle::default());
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static(expected_header_value))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_yaml_content_type_from_str_invalid() {
+        assert!("application/json".parse::<YamlContentType>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_yaml_response_serialization_error() -> anyhow::Result<()> {
+        let response = yaml_response(
+            &Unserializable,
+            YamlContentType::default(),
+            OutputStyle::default(),
+        );
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body)?,
+            json!({ "error": "Cannot serialize to YAML: cannot serialize" })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_yaml_deserialize_round_trip() -> anyhow::Result<()> {
+        let expected = r#"http:
+  routers:
+    api:
+      entryPoints:
+      - websecure
+      rule: Host(`api.my-domain.com`)
+      service: api
+      middlewares:
+      - strip-api
+      tls:
+        certResolver: letsencrypt
+        domains:
+        - main: my-domain.com
+          sans:
+          - '*.my-domain.com'
+  services:
+    api:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:8080/
+        passHostHeader: false
+        sticky:
+          cookie:
+            name: api
+    canary:
+      weighted:
+        services:
+        - name: api
+          weight: 3
+        - name: api-next
+  middlewares:
+    strip-api:
+      stripPrefix:
+        prefixes:
+        - /api
+tcp:
+  routers:
+    db:
+      rule: HostSNI(`*`)
+      service: db
+  services:
+    db:
+      loadBalancer:
+        servers:
+        - address: 192.168.1.100:5432
+tls:
+  certificates:
+  - certFile: /certs/cert.pem
+    keyFile: /certs/key.pem
+"#;
+
+        let parsed: DynamicConfiguration = serde_yaml::from_str(expected)?;
+
+        assert_eq!(serde_yaml::to_string(&parsed)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_yaml_deserialize_without_services() -> anyhow::Result<()> {
+        let parsed: DynamicConfiguration = serde_yaml::from_str("tcp: {}\n")?;
+
+        assert!(parsed.http.routers.is_empty());
+        assert!(parsed.http.services.is_empty());
+        assert!(parsed.tcp.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_serialize() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"{
+  "http": {
+    "routers": {
+      "to-my-service": {
+        "rule": "Host(`my-service.my-domain.com`)",
+        "service": "my-service"
+      }
+    },
+    "services": {
+      "my-service": {
+        "loadBalancer": {
+          "servers": [
+            {
+              "url": "http://192.168.1.100:7878/"
+            }
+          ]
+        }
+      }
+    }
+  }
+}"#;
+
+        assert_eq!(dynamic_configuration.to_json()?, expected);
+        assert_eq!(
+            serde_yaml::to_string(&dynamic_configuration)?,
+            dynamic_configuration.to_yaml()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_serialize() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"{"http":{"routers":{"to-my-service":{"rule":"Host(`my-service.my-domain.com`)","service":"my-service"}},"services":{"my-service":{"loadBalancer":{"servers":[{"url":"http://192.168.1.100:7878/"}]}}}}}"#;
+
+        assert_eq!(
+            dynamic_configuration.to_json_styled(OutputStyle::Compact)?,
+            expected
+        );
+        let compact_yaml = dynamic_configuration.to_yaml_styled(OutputStyle::Compact)?;
+        assert_eq!(compact_yaml, expected);
+        assert_eq!(
+            serde_yaml::from_str::<DynamicConfiguration>(&compact_yaml)?.to_yaml()?,
+            dynamic_configuration.to_yaml()?
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("pretty", OutputStyle::Pretty)]
+    #[case(" Compact", OutputStyle::Compact)]
+    fn test_output_style_from_str(
+        #[case] value: &str,
+        #[case] expected: OutputStyle,
+    ) -> anyhow::Result<()> {
+        assert_eq!(value.parse::<OutputStyle>()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_toml_serialize() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("db")
+                    .public_port(5432)
+                    .tcp(TraefikedContainerTcpConfig {
+                        router_name: "db".to_owned(),
+                        rule: "HostSNI(`*`)".to_owned(),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"[http.routers.to-my-service]
+rule = "Host(`my-service.my-domain.com`)"
+service = "my-service"
+
+[[http.services.my-service.loadBalancer.servers]]
+url = "http://192.168.1.100:7878/"
+
+[tcp.routers.db]
+rule = "HostSNI(`*`)"
+service = "db"
+
+[[tcp.services.db.loadBalancer.servers]]
+address = "192.168.1.100:5432"
+"#;
+
+        assert_eq!(dynamic_configuration.to_toml()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(true, "http://192.168.1.100", "http://192.168.1.100:7878/")]
+    #[case(false, "http://192.168.1.100", "http://192.168.1.100:7878")]
+    #[case(false, "http://192.168.1.100/apps/", "http://192.168.1.100:7878/apps/")]
+    #[case(
+        false,
+        "http://192.168.1.100/?debug",
+        "http://192.168.1.100:7878/?debug"
+    )]
+    fn test_builder_trailing_slash(
+        #[case] trailing_slash: bool,
+        #[case] base_url: Url,
+        #[case] expected_url: &str,
+    ) -> anyhow::Result<()> {
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .trailing_slash(trailing_slash)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = format!(
+            r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: {}
+"#,
+            expected_url
+        );
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_add_router_and_service() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_router(
+                "maintenance",
+                HttpRouterConfiguration::new(
+                    &TraefikedContainerSinglePortConfig {
+                        rule: "Host(`shop.my-domain.com`)".to_owned(),
+                        priority: Some(1),
+                        ..Default::default()
+                    },
+                    &"maintenance-page".to_owned(),
+                ),
+            )?
+            .add_service(
+                "maintenance-page",
+                HttpServiceConfiguration::load_balancer(
+                    vec![Url::parse("http://192.168.1.50:8080")?],
+                    &TraefikedLoadBalancerConfig::default(),
+                ),
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    maintenance:
+      rule: Host(`shop.my-domain.com`)
+      service: maintenance-page
+      priority: 1
+  services:
+    maintenance-page:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.50:8080/
+"#;
+
+        assert_eq!(dynamic_configuration.to_yaml()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_add_router_collision() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url)
+            .add_router(
+                "to-my-service",
+                HttpRouterConfiguration::new(
+                    &TraefikedContainerSinglePortConfig {
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        ..Default::default()
+                    },
+                    &"my-service".to_owned(),
+                ),
+            )?
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            );
+
+        assert_eq!(
+            result.err().map(|e| e.to_string()).as_deref(),
+            Some("HTTP router 'to-my-service' is declared by both the builder and container 'my-service' (collision policy: error)")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_add_router_undefined_service() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url)
+            .add_router(
+                "maintenance",
+                HttpRouterConfiguration::new(
+                    &TraefikedContainerSinglePortConfig {
+                        rule: "Host(`shop.my-domain.com`)".to_owned(),
+                        ..Default::default()
+                    },
+                    &"maintenance-page".to_owned(),
+                ),
+            )?
+            .build();
+
+        assert_eq!(
+            result.err().map(|e| e.errors),
+            Some(vec![ValidationError::UndefinedService {
+                router_kind: "HTTP router",
+                router: "maintenance".to_owned(),
+                service: "maintenance-page".to_owned(),
+            }])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_container_base_url() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .base_url(Url::parse("https://10.0.0.7")?)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("other-service")
+                    .public_port(7879)
+                    .router("to-other-service", "Host(`other-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+    to-other-service:
+      rule: Host(`other-service.my-domain.com`)
+      service: other-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: https://10.0.0.7:7878/
+    other-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7879/
+"#;
+
+        assert_eq!(dynamic_configuration.to_yaml()?, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(
+        "http://192.168.1.100",
+        ServerPathSegment::None,
+        "http://192.168.1.100:7878/"
+    )]
+    #[case(
+        "http://gateway.internal/apps",
+        ServerPathSegment::None,
+        "http://gateway.internal:7878/apps"
+    )]
+    #[case(
+        "http://gateway.internal/apps/",
+        ServerPathSegment::None,
+        "http://gateway.internal:7878/apps/"
+    )]
+    #[case(
+        "http://192.168.1.100",
+        ServerPathSegment::ContainerName,
+        "http://192.168.1.100:7878/my.service"
+    )]
+    #[case(
+        "http://gateway.internal/apps",
+        ServerPathSegment::ServiceName,
+        "http://gateway.internal:7878/apps/my-service"
+    )]
+    #[case(
+        "http://gateway.internal/apps/",
+        ServerPathSegment::ContainerName,
+        "http://gateway.internal:7878/apps/my.service"
+    )]
+    fn test_builder_server_path(
+        #[case] base_url: &str,
+        #[case] server_path_segment: ServerPathSegment,
+        #[case] expected_url: &str,
+    ) -> anyhow::Result<()> {
+        let dynamic_configuration = DynamicConfigurationBuilder::new(Url::parse(base_url)?)
+            .server_path_segment(server_path_segment)
+            .add_container(
+                &TraefikedContainer::builder("my.service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let HttpServiceType::LoadBalancer(load_balancer) =
+            &dynamic_configuration.http.services["my-service"].service_type
+        else {
+            panic!("Should be a load balancer");
+        };
+        assert_eq!(load_balancer.servers[0].url.as_str(), expected_url);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("none", ServerPathSegment::None)]
+    #[case("Container-Name", ServerPathSegment::ContainerName)]
+    #[case("service-name", ServerPathSegment::ServiceName)]
+    fn test_server_path_segment_from_str(
+        #[case] value: &str,
+        #[case] expected: ServerPathSegment,
+    ) -> anyhow::Result<()> {
+        assert_eq!(value.parse::<ServerPathSegment>()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_empty() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        assert!(DynamicConfigurationBuilder::new(base_url.clone())
+            .add_containers(&[])?
+            .build()?
+            .is_empty());
+
+        let with_middleware = DynamicConfigurationBuilder::new(base_url)
+            .add_middleware(
+                "strip",
+                MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                    prefixes: vec!["/api".to_owned()],
+                    force_slash: None,
+                }),
+            )
+            .build()?;
+        assert!(!with_middleware.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary() -> anyhow::Result<()> {
+        let long_rule = format!(
+            "Host(`my-service.my-domain.com`) && ({})",
+            ["/api", "/admin", "/metrics", "/health"]
+                .iter()
+                .map(|path| format!("PathPrefix(`{}`)", path))
+                .join(" || ")
+        );
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_additional_base_url(Url::parse("http://10.0.0.2")?)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("api")
+                    .public_port(8080)
+                    .router("api", &long_rule)
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("auth-proxy")
+                    .public_port(9090)
+                    .router("auth", "Host(`auth.my-domain.com`)")
+                    .router_service("auth", "central-auth@file")
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("db")
+                    .public_port(5432)
+                    .tcp(TraefikedContainerTcpConfig {
+                        router_name: "db".to_owned(),
+                        rule: "HostSNI(`*`)".to_owned(),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"api: Host(`my-service.my-domain.com`) && (PathPrefix(`/api`) || PathPrefix(`/admin... -> api [http://192.168.1.100:8080/, http://10.0.0.2:8080/]
+auth: Host(`auth.my-domain.com`) -> central-auth@file
+to-my-service: Host(`my-service.my-domain.com`) -> my-service [http://192.168.1.100:7878/, http://10.0.0.2:7878/]
+TCP db: HostSNI(`*`) -> db [192.168.1.100:5432]"#;
+
+        assert_eq!(dynamic_configuration.summary(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint() -> anyhow::Result<()> {
+        let strip = || {
+            MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                prefixes: vec!["/api".to_owned()],
+                force_slash: None,
+            })
+        };
+        let containers = [
+            TraefikedContainer::builder("a")
+                .public_port(7878)
+                .router("a", "Host(`a.local`)")
+                .build()?,
+            TraefikedContainer::builder("b")
+                .public_port(7879)
+                .router("b", "Host(`b.local`)")
+                .build()?,
+        ];
+
+        let first = DynamicConfigurationBuilder::new(Url::parse("http://192.168.1.100")?)
+            .with_additional_base_url(Url::parse("http://10.0.0.2")?)
+            .add_middleware("strip", strip())
+            .add_middleware("other-strip", strip())
+            .add_container(&containers[0])?
+            .add_container(&containers[1])?
+            .build()?;
+        let second = DynamicConfigurationBuilder::new(Url::parse("HTTP://192.168.1.100:80/")?)
+            .with_additional_base_url(Url::parse("http://10.0.0.2/")?)
+            .add_container(&containers[1])?
+            .add_middleware("other-strip", strip())
+            .add_container(&containers[0])?
+            .add_middleware("strip", strip())
+            .build()?;
+        assert_eq!(first.fingerprint(), second.fingerprint());
+
+        let other = DynamicConfigurationBuilder::new(Url::parse("http://192.168.1.100")?)
+            .add_container(&containers[0])?
+            .build()?;
+        assert_ne!(first.fingerprint(), other.fingerprint());
+        // Stable across runs, e.g. for ETags surviving a restart
+        assert_eq!(format!("{:016x}", other.fingerprint()), "aed20bfe125e6e8d");
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let empty = DynamicConfigurationBuilder::new(base_url.clone()).build()?;
+        assert_eq!(empty.stats(), ConfigStats::default());
+        assert_eq!(empty.stats().to_string(), "no entries");
+
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_middleware(
+                "strip",
+                MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                    prefixes: vec!["/api".to_owned()],
+                    force_slash: None,
+                }),
+            )
+            .add_containers(&[
+                TraefikedContainer::builder("a")
+                    .public_port(7878)
+                    .router("a", "Host(`a.local`)")
+                    .build()?,
+                TraefikedContainer::builder("b")
+                    .public_port(7879)
+                    .router("b", "Host(`b.local`)")
+                    .build()?,
+            ])?
+            .build()?;
+
+        assert_eq!(
+            dynamic_configuration.stats(),
+            ConfigStats {
+                routers: 2,
+                services: 2,
+                middlewares: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            dynamic_configuration.stats().to_string(),
+            "2 routers, 2 services, 1 middlewares"
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("serve", EmptyConfigurationPolicy::Serve)]
+    #[case(" No-Content ", EmptyConfigurationPolicy::NoContent)]
+    fn test_empty_configuration_policy_from_str(
+        #[case] value: &str,
+        #[case] expected: EmptyConfigurationPolicy,
+    ) -> anyhow::Result<()> {
+        assert_eq!(value.parse::<EmptyConfigurationPolicy>()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_accessors() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        entrypoints: vec!["websecure".to_owned()],
+                        middlewares: Some(vec!["strip".to_owned()]),
+                        priority: Some(10),
+                        ..Default::default()
+                    })
+                    .middleware(
+                        "strip",
+                        MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                            prefixes: vec!["/api".to_owned()],
+                            force_slash: None,
+                        }),
+                    )
+                    .weighted_service(
+                        "canary",
+                        vec![TraefikedWeightedService {
+                            name: "my-service".to_owned(),
+                            weight: Some(3),
+                        }],
+                    )
+                    .build()?,
+            )?
+            .build()?;
+
+        assert_eq!(
+            dynamic_configuration.routers().keys().collect::<Vec<_>>(),
+            vec!["to-my-service"]
+        );
+        let router = dynamic_configuration
+            .router("to-my-service")
+            .expect("Should have the router");
+        assert_eq!(router.rule(), "Host(`my-service.my-domain.com`)");
+        assert_eq!(router.service(), "my-service");
+        assert_eq!(router.entry_points(), ["websecure"]);
+        assert_eq!(router.middlewares(), ["strip"]);
+        assert_eq!(router.priority(), Some(10));
+
+        assert_eq!(dynamic_configuration.services().len(), 2);
+        assert_eq!(
+            dynamic_configuration
+                .service("my-service")
+                .map(HttpServiceConfiguration::urls),
+            Some(vec![&Url::parse("http://192.168.1.100:7878")?])
+        );
+        assert_eq!(
+            dynamic_configuration
+                .service("canary")
+                .map(HttpServiceConfiguration::weighted_services),
+            Some(vec![("my-service", Some(3))])
+        );
+        assert!(dynamic_configuration.service("unknown").is_none());
+        assert!(dynamic_configuration.middleware("strip").is_some());
+        assert_eq!(dynamic_configuration.middlewares().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_containers() -> anyhow::Result<()> {
+        let containers = [
+            TraefikedContainer::builder("my-service")
+                .public_port(7878)
+                .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                .build()?,
+            TraefikedContainer::builder("other-service")
+                .public_port(7879)
+                .router("to-other-service", "Host(`other-service.my-domain.com`)")
+                .build()?,
+        ];
+
+        let dynamic_configuration = DynamicConfiguration::from_containers(
+            Url::parse("http://192.168.1.100")?,
+            &containers,
+        )?;
+
+        assert_eq!(
+            dynamic_configuration
+                .http
+                .routers
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["to-my-service", "to-other-service"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_containers_reports_every_error() -> anyhow::Result<()> {
+        let container = |name: &str| {
+            TraefikedContainer::builder(name)
+                .router(name, "Host(`my-service.my-domain.com`)")
+                .build()
+        };
+        let containers = [container("a")?, container("b")?];
+
+        let result = DynamicConfigurationBuilder::new(Url::parse("http://192.168.1.100")?)
+            .add_containers(&containers);
+
+        assert_eq!(
+            result.err().map(|e| e.to_string()).as_deref(),
+            Some(
+                "Cannot add 2 container(s):\n\
+                Container 'a': No public port specified for container 'a'\n\
+                Container 'b': No public port specified for container 'b'"
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_containers_skip_and_report() -> anyhow::Result<()> {
+        let containers = [
+            TraefikedContainer::builder("a")
+                .public_port(7878)
+                .router("a", "Host(`a.my-domain.com`)")
+                .build()?,
+            // No public port
+            TraefikedContainer::builder("b")
+                .router("b", "Host(`b.my-domain.com`)")
+                .build()?,
+            // Its HTTP router and service are added before its TCP router fails
+            TraefikedContainer::builder("c")
+                .published_port(80, 32771)
+                .router("c", "Host(`c.my-domain.com`)")
+                .tcp(TraefikedContainerTcpConfig {
+                    router_name: "c-tcp".to_owned(),
+                    rule: "HostSNI(`*`)".to_owned(),
+                    target_port: Some(5432),
+                    ..Default::default()
+                })
+                .build()?,
+            // Free to declare the router of the skipped container
+            TraefikedContainer::builder("d")
+                .public_port(7879)
+                .router("c", "Host(`d.my-domain.com`)")
+                .build()?,
+        ];
+
+        let builder = DynamicConfigurationBuilder::new(Url::parse("http://192.168.1.100")?)
+            .container_error_policy(ContainerErrorPolicy::SkipAndReport)
+            .add_containers(&containers)?;
+
+        assert_eq!(
+            builder
+                .skipped_containers()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "Container 'b' skipped: No public port specified for container 'b'",
+                "Container 'c' skipped: Port 5432 of container 'c' is not published on the host"
+            ]
+        );
+
+        let dynamic_configuration = builder.build()?;
+        assert_eq!(
+            dynamic_configuration.routers().keys().collect::<Vec<_>>(),
+            vec!["a", "c"]
+        );
+        assert_eq!(
+            dynamic_configuration
+                .router("c")
+                .map(|router| router.rule()),
+            Some("Host(`d.my-domain.com`)")
+        );
+        assert_eq!(
+            dynamic_configuration.services().keys().collect::<Vec<_>>(),
+            vec!["a", "d"]
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("fail-fast", ContainerErrorPolicy::FailFast)]
+    #[case(" Skip-And-Report", ContainerErrorPolicy::SkipAndReport)]
+    fn test_container_error_policy_from_str(
+        #[case] value: &str,
+        #[case] expected: ContainerErrorPolicy,
+    ) -> anyhow::Result<()> {
+        assert_eq!(value.parse::<ContainerErrorPolicy>()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_entrypoints() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        entrypoints: vec!["websecure".to_owned(), "web".to_owned()],
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      entryPoints:
+      - websecure
+      - web
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(vec![], None)]
+    #[case(vec!["websecure"], Some(vec!["websecure"]))]
+    fn test_builder_default_entrypoints(
+        #[case] default_entrypoints: Vec<&str>,
+        #[case] expected: Option<Vec<&str>>,
+    ) -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .default_entrypoints(default_entrypoints.into_iter().map(str::to_owned).collect())
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("app", "Host(`app.my-domain.com`)")
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "internal".to_owned(),
+                        rule: "Host(`internal.my-domain.com`)".to_owned(),
+                        entrypoints: vec!["web".to_owned()],
+                        ..Default::default()
+                    })
+                    .service("my-service", 7878)
+                    .router_service("app", "my-service")
+                    .router_service("internal", "my-service")
+                    .build()?,
+            )?
+            .build()?;
+
+        let entry_points = |router: &str| {
+            let router = &dynamic_configuration.http.routers[router];
+            (!router.entry_points.is_empty()).then_some(router.entry_points.clone())
+        };
+
+        assert_eq!(
+            entry_points("app"),
+            expected.map(|expected| expected.into_iter().map(str::to_owned).collect())
+        );
+        assert_eq!(entry_points("internal"), Some(vec!["web".to_owned()]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_middlewares() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        middlewares: Some(vec!["auth@file".to_owned(), "compress@file".to_owned()]),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+      middlewares:
+      - auth@file
+      - compress@file
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_default_middlewares() -> anyhow::Result<()> {
+        let container = |name: &str, middlewares: Option<Vec<String>>| {
+            TraefikedContainer::builder(name)
+                .public_port(7878)
+                .router_config(TraefikedContainerSinglePortConfig {
+                    router_name: name.to_owned(),
+                    rule: format!("Host(`{}.my-domain.com`)", name),
+                    middlewares,
+                    ..Default::default()
+                })
+                .build()
+        };
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_default_middlewares(vec![
+                "compress@file".to_owned(),
+                "security-headers@file".to_owned(),
+            ])
+            .add_containers(&[
+                container("unlabeled", None)?,
+                container(
+                    "labeled",
+                    Some(vec!["auth@file".to_owned(), "compress@file".to_owned()]),
+                )?,
+                container("opted-out", Some(vec![]))?,
+            ])?
+            .build()?;
+
+        let middlewares = |router: &str| {
+            dynamic_configuration
+                .router(router)
+                .map(HttpRouterConfiguration::middlewares)
+        };
+        assert_eq!(
+            middlewares("unlabeled"),
+            Some(
+                [
+                    "compress@file".to_owned(),
+                    "security-headers@file".to_owned()
+                ]
+                .as_slice()
+            )
+        );
+        assert_eq!(
+            middlewares("labeled"),
+            Some(
+                [
+                    "auth@file".to_owned(),
+                    "compress@file".to_owned(),
+                    "security-headers@file".to_owned()
+                ]
+                .as_slice()
+            )
+        );
+        assert_eq!(middlewares("opted-out"), Some([].as_slice()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_default_cert_resolver() -> anyhow::Result<()> {
+        let container = |name: &str, tls: Option<TraefikedRouterTlsConfig>| {
+            TraefikedContainer::builder(name)
+                .public_port(7878)
+                .router_config(TraefikedContainerSinglePortConfig {
+                    router_name: name.to_owned(),
+                    rule: format!("Host(`{}.my-domain.com`)", name),
+                    tls,
+                    ..Default::default()
+                })
+                .build()
+        };
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_default_cert_resolver("le")
+            .add_containers(&[
+                container("tls", Some(TraefikedRouterTlsConfig::default()))?,
+                container(
+                    "resolver",
+                    Some(TraefikedRouterTlsConfig {
+                        cert_resolver: Some("internal-ca".to_owned()),
+                        ..Default::default()
+                    }),
+                )?,
+                container("plain", None)?,
+            ])?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    plain:
+      rule: Host(`plain.my-domain.com`)
+      service: plain
+    resolver:
+      rule: Host(`resolver.my-domain.com`)
+      service: resolver
+      tls:
+        certResolver: internal-ca
+    tls:
+      rule: Host(`tls.my-domain.com`)
+      service: tls
+      tls:
+        certResolver: le
+"#;
+
+        let mut configuration_yaml = dynamic_configuration.to_yaml()?;
+        configuration_yaml.truncate(configuration_yaml.find("  services:").unwrap_or_default());
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(Some("default-tls"), "default-tls", "le")]
+    #[case(None, "le", "le")]
+    fn test_builder_default_cert_resolver_with_default_tls(
+        #[case] default_tls_cert_resolver: Option<&str>,
+        #[case] expected_default_tls: &str,
+        #[case] expected_tls: &str,
+    ) -> anyhow::Result<()> {
+        let container = |name: &str, tls: Option<TraefikedRouterTlsConfig>| {
+            TraefikedContainer::builder(name)
+                .public_port(7878)
+                .router_config(TraefikedContainerSinglePortConfig {
+                    router_name: name.to_owned(),
+                    rule: format!("Host(`{}.my-domain.com`)", name),
+                    tls,
+                    ..Default::default()
+                })
+                .build()
+        };
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_default_tls(default_tls_cert_resolver.map(str::to_owned))
+            .with_default_cert_resolver("le")
+            .add_containers(&[
+                container("default-tls", None)?,
+                container("tls", Some(TraefikedRouterTlsConfig::default()))?,
+            ])?
+            .build()?;
+
+        let cert_resolver = |router: &str| {
+            dynamic_configuration
+                .router(router)
+                .and_then(|router| router.tls.as_ref())
+                .and_then(|tls| tls.cert_resolver.clone())
+        };
+        assert_eq!(
+            cert_resolver("default-tls").as_deref(),
+            Some(expected_default_tls)
+        );
+        assert_eq!(cert_resolver("tls").as_deref(), Some(expected_tls));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(Some(false), Some("le"))]
+    #[case(None, Some("le"))]
+    #[case(Some(true), None)]
+    fn test_builder_default_cert_resolver_tcp(
+        #[case] passthrough: Option<bool>,
+        #[case] expected: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_default_cert_resolver("le")
+            .add_container(
+                &TraefikedContainer::builder("db")
+                    .public_port(5432)
+                    .tcp(TraefikedContainerTcpConfig {
+                        router_name: "db".to_owned(),
+                        rule: "HostSNI(`db.my-domain.com`)".to_owned(),
+                        tls: Some(TraefikedTcpRouterTlsConfig {
+                            passthrough,
+                            cert_resolver: None,
+                        }),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        assert_eq!(
+            dynamic_configuration.tcp.routers["db"]
+                .tls
+                .as_ref()
+                .and_then(|tls| tls.cert_resolver.as_deref()),
+            expected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_default_tls() -> anyhow::Result<()> {
+        let container = |name: &str, tls: Option<TraefikedRouterTlsConfig>, tls_disabled: bool| {
+            TraefikedContainer::builder(name)
+                .public_port(7878)
+                .router_config(TraefikedContainerSinglePortConfig {
+                    router_name: name.to_owned(),
+                    rule: format!("Host(`{}.my-domain.com`)", name),
+                    tls,
+                    tls_disabled,
+                    ..Default::default()
+                })
+                .build()
+        };
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_default_tls(Some("le".to_owned()))
+            .add_containers(&[
+                container("unlabeled", None, false)?,
+                container(
+                    "labeled",
+                    Some(TraefikedRouterTlsConfig {
+                        options: Some("modern@file".to_owned()),
+                        ..Default::default()
+                    }),
+                    false,
+                )?,
+                container("disabled", None, true)?,
+            ])?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    disabled:
+      rule: Host(`disabled.my-domain.com`)
+      service: disabled
+    labeled:
+      rule: Host(`labeled.my-domain.com`)
+      service: labeled
+      tls:
+        options: modern@file
+    unlabeled:
+      rule: Host(`unlabeled.my-domain.com`)
+      service: unlabeled
+      tls:
+        certResolver: le
+"#;
+
+        let mut configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+        configuration_yaml.truncate(configuration_yaml.find("  services:").unwrap_or_default());
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_add_middleware() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .strict(true)
+            .add_middleware(
+                "strip-api",
+                MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                    prefixes: vec!["/api".to_owned(), "/v1".to_owned()],
+                    force_slash: Some(false),
+                }),
+            )
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        middlewares: Some(vec!["strip-api".to_owned()]),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+      middlewares:
+      - strip-api
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+  middlewares:
+    strip-api:
+      stripPrefix:
+        prefixes:
+        - /api
+        - /v1
+        forceSlash: false
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_tls_configuration() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_certificate(TlsCertificate {
+                cert_file: "/certs/internal.crt".to_owned(),
+                key_file: "/certs/internal.key".to_owned(),
+                ..Default::default()
+            })
+            .add_tls_options(
+                "modern",
+                TlsOptions {
+                    min_version: Some("VersionTLS13".to_owned()),
+                    sni_strict: Some(true),
+                    ..Default::default()
+                },
+            )
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+tls:
+  certificates:
+  - certFile: /certs/internal.crt
+    keyFile: /certs/internal.key
+  options:
+    modern:
+      minVersion: VersionTLS13
+      sniStrict: true
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_provider_qualified_service() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .strict(true)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("auth", "Host(`auth.my-domain.com`)")
+                    .router_service("auth", "central-auth@file")
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    auth:
+      rule: Host(`auth.my-domain.com`)
+      service: central-auth@file
+  services: {}
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(true, true, true)]
+    #[case(false, false, true)]
+    #[case(false, true, false)]
+    fn test_builder_dangling_middleware_references(
+        #[case] defined: bool,
+        #[case] strict: bool,
+        #[case] expected_ok: bool,
+    ) -> anyhow::Result<()> {
+        let mut auth_container = TraefikedContainer::builder("auth")
+            .public_port(9091)
+            .router("auth", "Host(`auth.my-domain.com`)");
+        if defined {
+            auth_container = auth_container.middleware(
+                "auth",
+                MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                    prefixes: vec!["/auth".to_owned()],
+                    force_slash: None,
+                }),
+            );
+        }
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url)
+            .strict(strict)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        middlewares: Some(vec!["auth".to_owned(), "compress@file".to_owned()]),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .add_container(&auth_container.build()?)?
+            .build();
+
+        assert_eq!(result.is_ok(), expected_ok);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_middleware_definitions() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        middlewares: Some(vec!["strip-api".to_owned()]),
+                        ..Default::default()
+                    })
+                    .middleware(
+                        "strip-api",
+                        MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                            prefixes: vec!["/api".to_owned()],
+                            force_slash: None,
+                        }),
+                    )
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+      middlewares:
+      - strip-api
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+  middlewares:
+    strip-api:
+      stripPrefix:
+        prefixes:
+        - /api
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(None, None, "      tls: {}\n")]
+    #[case(Some("le"), None, "      tls:\n        certResolver: le\n")]
+    #[case(
+        Some("le"),
+        Some("modern@file"),
+        "      tls:\n        certResolver: le\n        options: modern@file\n"
+    )]
+    fn test_builder_tls(
+        #[case] cert_resolver: Option<&str>,
+        #[case] options: Option<&str>,
+        #[case] expected_tls: &str,
+    ) -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        tls: Some(TraefikedRouterTlsConfig {
+                            cert_resolver: cert_resolver.map(str::to_owned),
+                            options: options.map(str::to_owned),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = format!(
+            r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+{expected_tls}  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#
+        );
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_tls_domains() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        tls: Some(TraefikedRouterTlsConfig {
+                            cert_resolver: Some("le".to_owned()),
+                            options: None,
+                            domains: vec![
+                                TraefikedTlsDomain {
+                                    main: "my-domain.com".to_owned(),
+                                    sans: vec!["*.my-domain.com".to_owned()],
+                                },
+                                TraefikedTlsDomain {
+                                    main: "other-domain.com".to_owned(),
+                                    sans: vec![],
+                                },
+                            ],
+                        }),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+      tls:
+        certResolver: le
+        domains:
+        - main: my-domain.com
+          sans:
+          - '*.my-domain.com'
+        - main: other-domain.com
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_priority() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        priority: Some(100),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+      priority: 100
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_rule_syntax() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        rule_syntax: Some("v2".to_owned()),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      ruleSyntax: v2
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_observability() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        observability: Some(TraefikedObservabilityConfig {
+                            access_logs: Some(false),
+                            metrics: None,
+                            tracing: Some(true),
+                        }),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+      observability:
+        accessLogs: false
+        tracing: true
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_routers_sharing_service() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .published_port(8080, 32771)
+                    .router("internal", "Host(`my-service.local`)")
+                    .router("public", "Host(`my-service.my-domain.com`)")
+                    .service("my-service", 8080)
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    internal:
+      rule: Host(`my-service.local`)
+      service: my-service
+    public:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:32771/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_target_port() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .published_port(9000, 9100)
+                    .published_port(8080, 32771)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        target_ports: vec![8080],
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:32771/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_multiple_target_ports() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .published_port(8080, 32771)
+                    .published_port(8081, 32772)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .service("my-service", 8080)
+                    .service("my-service", 8081)
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:32771/
+        - url: http://192.168.1.100:32772/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_additional_base_urls() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_additional_base_url(Url::parse("http://10.0.0.2")?)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .published_port(8080, 32771)
+                    .published_port(8081, 32772)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .service("my-service", 8080)
+                    .service("my-service", 8081)
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("nas")
+                    .public_port(5000)
+                    .base_url(Url::parse("http://192.168.1.50")?)
+                    .router("to-nas", "Host(`nas.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+    to-nas:
+      rule: Host(`nas.my-domain.com`)
+      service: nas
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:32771/
+        - url: http://10.0.0.2:32771/
+        - url: http://192.168.1.100:32772/
+        - url: http://10.0.0.2:32772/
+    nas:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.50:5000/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_server_weight() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url.clone())
+            .with_additional_base_url(Url::parse("http://10.0.0.2")?)
+            .server_weight(base_url, 1)
+            .server_weight(Url::parse("http://10.0.0.2")?, 3)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .add_service(
+                "maintenance",
+                HttpServiceConfiguration::load_balancer(
+                    vec![
+                        Url::parse("http://10.0.0.3:8080")?,
+                        Url::parse("http://10.0.0.4:8080")?,
+                    ],
+                    &TraefikedLoadBalancerConfig::default(),
+                )
+                .server_weight(&Url::parse("http://10.0.0.4:8080")?, 2),
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    maintenance:
+      loadBalancer:
+        servers:
+        - url: http://10.0.0.3:8080/
+        - url: http://10.0.0.4:8080/
+          weight: 2
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+          weight: 1
+        - url: http://10.0.0.2:7878/
+          weight: 3
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        assert_eq!(
+            serde_yaml::to_string(&serde_yaml::from_str::<DynamicConfiguration>(expected)?)?,
+            expected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_target_port_not_published() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url).add_container(
+            &TraefikedContainer::builder("my-service")
+                .published_port(80, 32771)
+                .private_port(8080)
+                .router_config(TraefikedContainerSinglePortConfig {
+                    router_name: "to-my-service".to_owned(),
+                    rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                    target_ports: vec![8080],
+                    ..Default::default()
+                })
+                .build()?,
+        );
+
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_container_ip() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .use_container_ip(true)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .published_port(8080, 7878)
+                    .network("bridge", "172.17.0.2")
+                    .network("proxy", "172.20.0.5")
+                    .docker_network("proxy")
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .tcp(TraefikedContainerTcpConfig {
+                        router_name: "db".to_owned(),
+                        rule: "HostSNI(`*`)".to_owned(),
+                        target_port: Some(5432),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://172.20.0.5:8080/
+tcp:
+  routers:
+    db:
+      rule: HostSNI(`*`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - address: 172.20.0.5:5432
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_container_ip_ambiguous_network() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url)
+            .use_container_ip(true)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .published_port(8080, 7878)
+                    .network("bridge", "172.17.0.2")
+                    .network("proxy", "172.20.0.5")
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            );
+
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_service_name_collision() -> anyhow::Result<()> {
+        let container = |name: &str| {
+            TraefikedContainer::builder(name)
+                .public_port(7878)
+                .router(name, "Host(`my-service.my-domain.com`)")
+                .build()
+        };
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let builder = DynamicConfigurationBuilder::new(base_url)
+            .add_container(&container("my.service")?)?
+            .add_container(&container("my.service")?)?;
+
+        assert!(builder.add_container(&container("my-service")?).is_err());
+        Ok(())
+    }
+
+    /// Container declaring the `web` router and service, shared by all such containers.
+    fn web_container(name: &str) -> TraefikedContainer {
+        TraefikedContainer {
+            name: name.to_owned(),
+            service_name: name.to_owned(),
+            compose_project: None,
+            compose_service: None,
+            aliases: vec![],
+            config: TraefikedContainerConfig::MultiplePorts(vec![
+                TraefikedContainerMultiPortConfig {
+                    config: TraefikedContainerSinglePortConfig {
+                        router_name: "web".to_owned(),
+                        rule: format!("Host(`{}.local`)", name),
+                        ..Default::default()
+                    },
+                    service_name: "web".to_owned(),
+                    target_ports: vec![8080],
+                },
+            ]),
+            ports: vec![PortMapping {
+                private_port: 8080,
+                public_port: Some(7878),
+                host_ip: None,
+                protocol: PortProtocol::Tcp,
+            }],
+            networks: BTreeMap::new(),
+            base_url: None,
+            network: None,
+            middlewares: BTreeMap::new(),
+            servers_transports: BTreeMap::new(),
+            weighted_services: BTreeMap::new(),
+            tcp: vec![],
+            udp: vec![],
+        }
+    }
+
+    #[rstest]
+    #[case(CollisionPolicy::FirstWins, "Host(`a.local`)")]
+    #[case(CollisionPolicy::LastWins, "Host(`b.local`)")]
+    fn test_builder_collision_policy(
+        #[case] collision_policy: CollisionPolicy,
+        #[case] expected_rule: &str,
+    ) -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .collision_policy(collision_policy)
+            .add_container(&web_container("a"))?
+            .add_container(&web_container("b"))?
+            .build()?;
+
+        assert_eq!(dynamic_configuration.http.routers.len(), 1);
+        assert_eq!(
+            dynamic_configuration.http.routers["web"].rule,
+            expected_rule
+        );
+        assert_eq!(dynamic_configuration.http.services.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_collision_auto_suffix() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .collision_policy(CollisionPolicy::AutoSuffix)
+            .add_container(&web_container("a"))?
+            .add_container(&web_container("b"))?
+            .add_container(&web_container("c"))?
+            .build()?;
+
+        let routers = &dynamic_configuration.http.routers;
+        assert_eq!(
+            routers
+                .iter()
+                .map(|(name, router)| (name.as_str(), router.service.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("web", "web"), ("web-2", "web-2"), ("web-3", "web-3")]
+        );
+        assert_eq!(routers["web-2"].rule, "Host(`b.local`)");
+        assert_eq!(dynamic_configuration.http.services.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_collision_auto_suffix_container_name() -> anyhow::Result<()> {
+        let container = |name: &str, router_name: &str| {
+            TraefikedContainer::builder(name)
+                .public_port(7878)
+                .router(router_name, "Host(`my-service.my-domain.com`)")
+                .build()
+        };
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .collision_policy(CollisionPolicy::AutoSuffix)
+            .add_container(&container("my.service", "first")?)?
+            .add_container(&container("my-service", "second")?)?
+            .build()?;
+
+        assert_eq!(
+            dynamic_configuration.http.routers["second"].service,
+            "my-service-2"
+        );
+        assert_eq!(
+            dynamic_configuration
+                .http
+                .services
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            vec!["my-service", "my-service-2"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_collision_error() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url)
+            .add_container(&web_container("a"))?
+            .add_container(&web_container("b"));
+
+        assert_eq!(
+            result.err().map(|e| e.to_string()).as_deref(),
+            Some("HTTP service 'web' is declared by both containers 'a' and 'b' (collision policy: error)")
+        );
+        Ok(())
+    }
+
+    /// Replica of the `web` compose service, e.g. started by `docker compose up --scale web=3`.
+    fn web_replica(name: &str, public_port: u16, rule: &str) -> anyhow::Result<TraefikedContainer> {
+        compose_web_replica(name, public_port, rule, &DockerOptions::default())
+    }
+
+    /// [`web_replica`] with its services named after the `web` compose service.
+    fn named_web_replica(
+        name: &str,
+        public_port: u16,
+        rule: &str,
+    ) -> anyhow::Result<TraefikedContainer> {
+        let options = DockerOptions {
+            use_compose_service_name: true,
+            ..Default::default()
+        };
+        compose_web_replica(name, public_port, rule, &options)
+    }
+
+    fn compose_web_replica(
+        name: &str,
+        public_port: u16,
+        rule: &str,
+        options: &DockerOptions,
+    ) -> anyhow::Result<TraefikedContainer> {
+        Ok(TraefikedContainer::from_summary(
+            ContainerSummary {
+                names: Some(vec![format!("/{}", name)]),
+                ports: Some(vec![Port {
+                    private_port: 80,
+                    public_port: Some(public_port),
+                    ..Default::default()
+                }]),
+                labels: Some(labels(&[
+                    ("traefik.http.routers.web.rule", rule),
+                    ("com.docker.compose.project", "app"),
+                    ("com.docker.compose.service", "web"),
+                ])),
+                ..Default::default()
+            },
+            options,
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?)
+    }
+
+    #[rstest]
+    #[case(web_replica, "app-web-1")]
+    #[case(named_web_replica, "web")]
+    fn test_builder_replicas(
+        #[case] replica: fn(&str, u16, &str) -> anyhow::Result<TraefikedContainer>,
+        #[case] expected_service: &str,
+    ) -> anyhow::Result<()> {
+        let rule = "Host(`web.my-domain.com`)";
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_containers(&[
+                replica("app-web-1", 32771, rule)?,
+                replica("app-web-2", 32772, rule)?,
+                replica("app-web-3", 32773, rule)?,
+            ])?
+            .build()?;
+
+        let expected = format!(
+            r#"http:
+  routers:
+    web:
+      rule: Host(`web.my-domain.com`)
+      service: {expected_service}
+  services:
+    {expected_service}:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:32771/
+        - url: http://192.168.1.100:32772/
+        - url: http://192.168.1.100:32773/
+"#
+        );
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_named_replicas_with_different_rules() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url)
+            .add_container(&named_web_replica(
+                "app-web-1",
+                32771,
+                "Host(`web.my-domain.com`)",
+            )?)?
+            .add_container(&named_web_replica(
+                "app-web-2",
+                32772,
+                "Host(`www.my-domain.com`)",
+            )?);
+
+        assert_eq!(
+            result.err().map(|e| e.to_string()).as_deref(),
+            Some(
+                "Containers 'app-web-1' and 'app-web-2' both map to the Traefik name 'web' but are \
+                 not replicas, their routers or services differing (collision policy: error)"
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_replicas_with_different_rules() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url)
+            .add_container(&web_replica(
+                "app-web-1",
+                32771,
+                "Host(`web.my-domain.com`)",
+            )?)?
+            .add_container(&web_replica(
+                "app-web-2",
+                32772,
+                "Host(`www.my-domain.com`)",
+            )?);
+
+        assert_eq!(
+            result.err().map(|e| e.to_string()).as_deref(),
+            Some(
+                "HTTP router 'web' is declared by both containers 'app-web-1' and 'app-web-2' \
+                 (collision policy: error)"
+            )
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(CollisionPolicy::FirstWins, "Host(`web.my-domain.com`)", 32771)]
+    #[case(CollisionPolicy::LastWins, "Host(`www.my-domain.com`)", 32772)]
+    fn test_builder_replicas_with_different_rules_collision_policy(
+        #[case] collision_policy: CollisionPolicy,
+        #[case] expected_rule: &str,
+        #[case] expected_port: u16,
+    ) -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .collision_policy(collision_policy)
+            .add_container(&web_replica(
+                "app-web-1",
+                32771,
+                "Host(`web.my-domain.com`)",
+            )?)?
+            .add_container(&web_replica(
+                "app-web-2",
+                32772,
+                "Host(`www.my-domain.com`)",
+            )?)?
+            .build()?;
+
+        let router = dynamic_configuration.router("web");
+        assert_eq!(router.map(|router| router.rule()), Some(expected_rule));
+        assert_eq!(
+            router
+                .and_then(|router| dynamic_configuration.service(router.service()))
+                .map(|service| service.urls()),
+            Some(vec![&Url::parse(&format!(
+                "http://192.168.1.100:{}/",
+                expected_port
+            ))?])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_name_prefix() -> anyhow::Result<()> {
+        let host = |name_prefix: &str, base_url: &str| -> anyhow::Result<DynamicConfiguration> {
+            Ok(DynamicConfigurationBuilder::new(Url::parse(base_url)?)
+                .with_name_prefix(name_prefix)
+                .add_container(&web_container("web"))?
+                .add_container(
+                    &TraefikedContainer::builder("auth-proxy")
+                        .public_port(9090)
+                        .router("auth", "Host(`auth.my-domain.com`)")
+                        .router_service("auth", "central-auth@file")
+                        .build()?,
+                )?
+                .build()?)
+        };
+
+        let dynamic_configuration = host("host1-", "http://192.168.1.100")?
+            .merge(host("host2-", "http://192.168.1.101")?, MergePolicy::Error)?;
+
+        assert_eq!(
+            dynamic_configuration.routers().keys().collect::<Vec<_>>(),
+            vec!["host1-auth", "host1-web", "host2-auth", "host2-web"]
+        );
+        assert_eq!(
+            dynamic_configuration.services().keys().collect::<Vec<_>>(),
+            vec!["host1-web", "host2-web"]
+        );
+        for host in ["host1", "host2"] {
+            let router = dynamic_configuration
+                .router(&format!("{}-web", host))
+                .expect("Should have the web router");
+            assert_eq!(router.service(), format!("{}-web", host));
+            assert_eq!(
+                dynamic_configuration
+                    .router(&format!("{}-auth", host))
+                    .map(HttpRouterConfiguration::service),
+                Some("central-auth@file")
+            );
+        }
+        assert_eq!(
+            dynamic_configuration
+                .service("host2-web")
+                .map(HttpServiceConfiguration::urls),
+            Some(vec![&Url::parse("http://192.168.1.101:7878")?])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_name_prefix_weighted_service() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_name_prefix("host1-")
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "canary".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        ..Default::default()
+                    })
+                    .weighted_service(
+                        "canary",
+                        vec![
+                            TraefikedWeightedService {
+                                name: "my-service".to_owned(),
+                                weight: Some(3),
+                            },
+                            TraefikedWeightedService {
+                                name: "app-v2@file".to_owned(),
+                                weight: Some(1),
+                            },
+                        ],
+                    )
+                    .build()?,
+            )?
+            .build()?;
+
+        assert_eq!(
+            dynamic_configuration
+                .service("host1-canary")
+                .map(HttpServiceConfiguration::weighted_services),
+            Some(vec![
+                ("host1-my-service", Some(3)),
+                ("app-v2@file", Some(1))
+            ])
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("ops-*", "ops-grafana", true)]
+    #[case("ops-*", "grafana", false)]
+    #[case("backup", "backup", true)]
+    #[case("backup", "backup-2", false)]
+    #[case("web-?", "web-1", true)]
+    #[case("web-?", "web-10", false)]
+    #[case("app.*", "appx", false)]
+    fn test_name_pattern(
+        #[case] pattern: &str,
+        #[case] name: &str,
+        #[case] expected: bool,
+    ) -> anyhow::Result<()> {
+        assert_eq!(pattern.parse::<NamePattern>()?.matches(name), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_exclude_names() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let mut aliased = web_container("grafana");
+        aliased.aliases = vec!["grafana".to_owned(), "ops-grafana".to_owned()];
+
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .exclude_names(vec!["ops-*".parse()?, "backup".parse()?])
+            .add_containers(&[
+                web_container("backup"),
+                aliased,
+                web_container("ops-prometheus"),
+                web_container("web"),
+            ])?
+            .build()?;
+
+        assert_eq!(
+            dynamic_configuration.services().keys().collect::<Vec<_>>(),
+            vec!["web"]
+        );
+        assert_eq!(
+            dynamic_configuration
+                .service("web")
+                .map(HttpServiceConfiguration::urls),
+            Some(vec![&Url::parse("http://192.168.1.100:7878")?])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_compute_priorities() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .compute_priorities(true)
+            .add_container(
+                &TraefikedContainer::builder("front")
+                    .public_port(7878)
+                    .router("front", "Host(`my-domain.com`)")
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("api")
+                    .public_port(7879)
+                    .router("api", "Host(`my-domain.com`) && PathPrefix(`/api`)")
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("fallback")
+                    .public_port(7880)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "fallback".to_owned(),
+                        rule: "PathPrefix(`/`)".to_owned(),
+                        priority: Some(1),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    api:
+      rule: Host(`my-domain.com`) && PathPrefix(`/api`)
+      service: api
+      priority: 43
+    fallback:
+      rule: PathPrefix(`/`)
+      service: fallback
+      priority: 1
+    front:
+      rule: Host(`my-domain.com`)
+      service: front
+      priority: 21
+  services:
+    api:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7879/
+    fallback:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7880/
+    front:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#;
+
+        assert_eq!(dynamic_configuration.to_yaml()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_rule_transform() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_rule_transform(|rule, _| format!("{} && !PathPrefix(`/admin`)", rule))
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`) && !PathPrefix(`/admin`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+"#;
+
+        assert_eq!(dynamic_configuration.to_yaml()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_rule_transform_domain_suffix() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_rule_transform(|rule, container| {
+                // Shorthand rules only name the container, e.g. Host(`grafana`)
+                rule.replace(
+                    &format!("Host(`{}`)", container.name),
+                    &format!("Host(`{}.my-domain.com`)", container.name),
+                )
+            })
+            .add_container(
+                &TraefikedContainer::builder("grafana")
+                    .public_port(3000)
+                    .router("grafana", "Host(`grafana`)")
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.local`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        assert_eq!(
+            dynamic_configuration
+                .router("grafana")
+                .map(HttpRouterConfiguration::rule),
+            Some("Host(`grafana.my-domain.com`)")
+        );
+        assert_eq!(
+            dynamic_configuration
+                .router("to-my-service")
+                .map(HttpRouterConfiguration::rule),
+            Some("Host(`my-service.local`)")
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("error", CollisionPolicy::Error)]
+    #[case("First-Wins", CollisionPolicy::FirstWins)]
+    #[case("last-wins", CollisionPolicy::LastWins)]
+    #[case("auto-suffix", CollisionPolicy::AutoSuffix)]
+    fn test_collision_policy_from_str(
+        #[case] value: &str,
+        #[case] expected: CollisionPolicy,
+    ) -> anyhow::Result<()> {
+        assert_eq!(value.parse::<CollisionPolicy>()?, expected);
+        assert_eq!(expected.to_string().parse::<CollisionPolicy>()?, expected);
+        Ok(())
+    }
+
+    const EXTRA_CONFIGURATION: &str = r#"http:
+  routers:
+    nas:
+      rule: Host(`nas.my-domain.com`)
+      service: nas
+    my-service:
+      rule: Host(`extra.my-domain.com`)
+      service: nas
+  services:
+    nas:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.50:5000/
+  middlewares:
+    strip:
+      stripPrefix:
+        prefixes:
+        - /api
+"#;
+
+    #[test]
+    fn test_builder_extra_configuration() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .strict(true)
+            .default_entrypoints(vec!["websecure".to_owned()])
+            .extra_configuration(serde_yaml::from_str(EXTRA_CONFIGURATION)?)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        middlewares: Some(vec!["strip".to_owned()]),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    my-service:
+      entryPoints:
+      - websecure
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+      middlewares:
+      - strip
+    nas:
+      rule: Host(`nas.my-domain.com`)
+      service: nas
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+    nas:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.50:5000/
+  middlewares:
+    strip:
+      stripPrefix:
+        prefixes:
+        - /api
+"#;
+
+        assert_eq!(dynamic_configuration.to_yaml()?, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(
+        ExtraConfigurationPolicy::PreferGenerated,
+        "Host(`my-service.my-domain.com`)",
+        "my-service"
+    )]
+    #[case(
+        ExtraConfigurationPolicy::PreferExtra,
+        "Host(`extra.my-domain.com`)",
+        "nas"
+    )]
+    fn test_builder_extra_configuration_policy(
+        #[case] policy: ExtraConfigurationPolicy,
+        #[case] expected_rule: &str,
+        #[case] expected_service: &str,
+    ) -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .extra_configuration(serde_yaml::from_str(EXTRA_CONFIGURATION)?)
+            .extra_configuration_policy(policy)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        let router = &dynamic_configuration.http.routers["my-service"];
+        assert_eq!(router.rule, expected_rule);
+        assert_eq!(router.service, expected_service);
+        assert!(dynamic_configuration.http.routers.contains_key("nas"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_extra_configuration_conflict_error() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url)
+            .extra_configuration(serde_yaml::from_str(EXTRA_CONFIGURATION)?)
+            .extra_configuration_policy(ExtraConfigurationPolicy::Error)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build();
+
+        assert_eq!(
+            result.err().map(|e| e.to_string()).as_deref(),
+            Some("Invalid dynamic configuration:\nHTTP router 'my-service' of the extra configuration is already generated")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_validation() -> anyhow::Result<()> {
+        let extra_configuration = r#"http:
+  routers:
+    '':
+      rule: Host(`empty.my-domain.com`)
+      service: nas
+    nas:
+      rule: Host(`nas.my-domain.com`)
+      service: nas
+    auth:
+      rule: Host(`auth.my-domain.com`)
+      service: auth@file
+  services:
+    nas:
+      loadBalancer:
+        servers: []
+tcp:
+  routers:
+    db:
+      rule: HostSNI(`*`)
+      service: postgres
+  services: {}
+"#;
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let result = DynamicConfigurationBuilder::new(base_url)
+            .extra_configuration(serde_yaml::from_str(extra_configuration)?)
+            .build();
+
+        assert_eq!(
+            result.err(),
+            Some(ValidationErrors {
+                errors: vec![
+                    ValidationError::EmptyName {
+                        kind: "HTTP router"
+                    },
+                    ValidationError::UndefinedService {
+                        router_kind: "TCP router",
+                        router: "db".to_owned(),
+                        service: "postgres".to_owned(),
+                    },
+                    ValidationError::NoServers {
+                        kind: "HTTP service",
+                        name: "nas".to_owned(),
+                    },
+                ]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() -> anyhow::Result<()> {
+        let configuration = r#"http:
+  routers:
+    api:
+      rule: Host(`api.my-domain.com`)
+      service: api-v2
+      middlewares:
+      - strip
+      - auth@file
+      tls:
+        options: modern
+    nas:
+      rule: Host(`nas.my-domain.com`)
+      service: nas
+      tls:
+        options: default
+    auth:
+      rule: Host(`auth.my-domain.com`)
+      service: auth@file
+      tls:
+        options: modern@file
+  services:
+    nas:
+      loadBalancer:
+        servers: []
+"#;
+
+        let dynamic_configuration: DynamicConfiguration = serde_yaml::from_str(configuration)?;
+
+        assert_eq!(
+            dynamic_configuration
+                .validate()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "error: HTTP router 'api' targets the undefined service 'api-v2'",
+                "error: HTTP router 'api' references the undefined middleware 'strip'",
+                "error: HTTP router 'api' references the undefined TLS options 'modern'",
+                "warning: HTTP service 'nas' has no servers",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_validation_allow_empty_services() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .allow_empty_services(true)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .router("my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .build()?;
+
+        assert_eq!(dynamic_configuration.http.services.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_unchecked() -> anyhow::Result<()> {
+        let extra_configuration = r#"http:
+  routers:
+    nas:
+      rule: Host(`nas.my-domain.com`)
+      service: nas
+"#;
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .extra_configuration(serde_yaml::from_str(extra_configuration)?)
+            .build_unchecked();
+
+        assert!(dynamic_configuration.http.routers.contains_key("nas"));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("prefer-generated", ExtraConfigurationPolicy::PreferGenerated)]
+    #[case("Prefer-Extra", ExtraConfigurationPolicy::PreferExtra)]
+    #[case("error", ExtraConfigurationPolicy::Error)]
+    fn test_extra_configuration_policy_from_str(
+        #[case] value: &str,
+        #[case] expected: ExtraConfigurationPolicy,
+    ) -> anyhow::Result<()> {
+        assert_eq!(value.parse::<ExtraConfigurationPolicy>()?, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::yaml("yml", EXTRA_CONFIGURATION)]
+    #[case::toml(
+        "toml",
+        r#"[http.routers.nas]
+rule = "Host(`nas.my-domain.com`)"
+service = "nas"
+
+[http.routers.my-service]
+rule = "Host(`extra.my-domain.com`)"
+service = "nas"
+
+[[http.services.nas.loadBalancer.servers]]
+url = "http://192.168.1.50:5000/"
+
+[http.middlewares.strip.stripPrefix]
+prefixes = ["/api"]
+"#
+    )]
+    fn test_from_file(#[case] extension: &str, #[case] content: &str) -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("extra-{}.{}", std::process::id(), extension));
+        std::fs::write(&path, content)?;
+
+        let configuration = DynamicConfiguration::from_file(&path);
+        std::fs::remove_file(&path)?;
+
+        let configuration = configuration?;
+        assert_eq!(
+            configuration.http.routers.keys().collect::<Vec<_>>(),
+            vec!["my-service", "nas"]
+        );
+        assert!(configuration.http.middlewares.contains_key("strip"));
+        assert!(configuration.http.services.contains_key("nas"));
+        Ok(())
+    }
+
+    /// Configuration from `source` declaring routers, services and middlewares with each of `names`.
+    fn configuration(source: &str, names: &[&str]) -> anyhow::Result<DynamicConfiguration> {
+        let mut builder = DynamicConfigurationBuilder::new(Url::parse("http://192.168.1.100")?);
+        for name in names {
+            builder = builder.add_middleware(
+                *name,
+                MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                    prefixes: vec![format!("/{}", source)],
+                    ..Default::default()
+                }),
+            );
+            builder = builder.add_container(
+                &TraefikedContainer::builder(*name)
+                    .public_port(7878)
+                    .router(*name, format!("Host(`{}.{}`)", name, source))
+                    .build()?,
+            )?;
+        }
+        Ok(builder.build()?)
+    }
+
+    #[test]
+    fn test_merge_is_associative_under_prefer_self() -> anyhow::Result<()> {
+        let names = ["a", "b", "c"];
+        // Every subset of names, as a bit mask
+        let subsets: Vec<Vec<&str>> = (0..1 << names.len())
+            .map(|mask| {
+                names
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, name)| *name)
+                    .collect()
+            })
+            .collect();
+
+        for (x, y, z) in itertools::iproduct!(&subsets, &subsets, &subsets) {
+            let x = configuration("x", x)?;
+            let y = configuration("y", y)?;
+            let z = configuration("z", z)?;
+
+            let left = x
+                .clone()
+                .merge(y.clone(), MergePolicy::PreferSelf)?
+                .merge(z.clone(), MergePolicy::PreferSelf)?;
+            let right = x.merge(
+                y.merge(z, MergePolicy::PreferSelf)?,
+                MergePolicy::PreferSelf,
+            )?;
+
+            assert_eq!(left.to_yaml()?, right.to_yaml()?);
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(MergePolicy::PreferSelf, "Host(`b.self`)", "/self")]
+    #[case(MergePolicy::PreferOther, "Host(`b.other`)", "/other")]
+    fn test_merge_policy(
+        #[case] policy: MergePolicy,
+        #[case] expected_rule: &str,
+        #[case] expected_prefix: &str,
+    ) -> anyhow::Result<()> {
+        let merged = configuration("self", &["a", "b"])?
+            .merge(configuration("other", &["b", "c"])?, policy)?;
+
+        assert_eq!(
+            merged.http.routers.keys().collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(merged.http.routers["b"].rule, expected_rule);
+        assert_eq!(
+            merged.http.middlewares["b"],
+            MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                prefixes: vec![expected_prefix.to_owned()],
+                ..Default::default()
+            })
+            .into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_conflicts() -> anyhow::Result<()> {
+        let result = configuration("self", &["a", "b"])?
+            .merge(configuration("other", &["b", "c"])?, MergePolicy::Error);
+
+        let conflict = |kind, name: &str| MergeConflict {
+            kind,
+            name: name.to_owned(),
+        };
+        assert_eq!(
+            result.err(),
+            Some(MergeConflicts {
+                conflicts: vec![
+                    conflict("HTTP router", "b"),
+                    conflict("HTTP service", "b"),
+                    conflict("middleware", "b"),
+                ]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff() -> anyhow::Result<()> {
+        let old = configuration("old", &["a", "b"])?;
+        let new = configuration("new", &["b", "c"])?;
+
+        let diff = old.diff(&new);
+
+        assert_eq!(
+            diff.to_string(),
+            r#"HTTP router 'c' added
+HTTP service 'c' added
+middleware 'c' added
+HTTP router 'a' removed
+HTTP service 'a' removed
+middleware 'a' removed
+HTTP router 'b' modified, rule: "Host(`b.old`)" -> "Host(`b.new`)"
+middleware 'b' modified, stripPrefix.prefixes: ["/old"] -> ["/new"]"#
+        );
+        assert!(old.diff(&old.clone()).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_scheme() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_https_base_url(Url::parse("https://192.168.1.100")?)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            scheme: Some("https".to_owned()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: https://192.168.1.100:7878/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_scheme_per_service() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_https_base_url(Url::parse("https://192.168.1.100")?)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .published_port(8080, 32771)
+                    .published_port(8443, 32772)
+                    .router("admin", "Host(`admin.my-domain.com`)")
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "api".to_owned(),
+                        rule: "Host(`api.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            scheme: Some("https".to_owned()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .service("admin", 8080)
+                    .service("api", 8443)
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    admin:
+      rule: Host(`admin.my-domain.com`)
+      service: admin
+    api:
+      rule: Host(`api.my-domain.com`)
+      service: api
+  services:
+    admin:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:32771/
+    api:
+      loadBalancer:
+        servers:
+        - url: https://192.168.1.100:32772/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(false, None, "http://192.168.1.100:7443/")]
+    #[case(true, None, "https://192.168.1.100:7443/")]
+    #[case(true, Some("h2c"), "h2c://192.168.1.100:7443/")]
+    fn test_builder_detect_https(
+        #[case] detect_https: bool,
+        #[case] scheme: Option<&str>,
+        #[case] expected_url: &str,
+    ) -> anyhow::Result<()> {
+        let mut container = web_container("a");
+        container.ports[0].private_port = 443;
+        container.ports[0].public_port = Some(7443);
+        let TraefikedContainerConfig::MultiplePorts(configs) = &mut container.config else {
+            panic!("Should be a multiple ports config");
+        };
+        configs[0].target_ports = vec![443];
+        configs[0].config.load_balancer.scheme = scheme.map(str::to_owned);
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .detect_https(detect_https)
+            .add_container(&container)?
+            .build()?;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert!(configuration_yaml.contains(&format!("url: {}", expected_url)));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(false, &[9443], None, "https://192.168.1.100:7443/")]
+    #[case(false, &[], None, "http://192.168.1.100:7443/")]
+    #[case(true, &[], None, "http://192.168.1.100:7443/")]
+    #[case(false, &[9443], Some("http"), "http://192.168.1.100:7443/")]
+    #[case(false, &[9443], Some("h2c"), "h2c://192.168.1.100:7443/")]
+    fn test_builder_https_ports(
+        #[case] detect_https: bool,
+        #[case] https_ports: &[u16],
+        #[case] scheme: Option<&str>,
+        #[case] expected_url: &str,
+    ) -> anyhow::Result<()> {
+        let mut container = web_container("a");
+        container.ports[0].private_port = 9443;
+        container.ports[0].public_port = Some(7443);
+        let TraefikedContainerConfig::MultiplePorts(configs) = &mut container.config else {
+            panic!("Should be a multiple ports config");
+        };
+        configs[0].target_ports = vec![9443];
+        configs[0].config.load_balancer.scheme = scheme.map(str::to_owned);
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_https_base_url(Url::parse("https://192.168.1.100")?)
+            .detect_https(detect_https)
+            .https_ports(https_ports.iter().copied())
+            .add_container(&container)?
+            .build()?;
+
+        let HttpServiceType::LoadBalancer(load_balancer) =
+            &dynamic_configuration.http.services["web"].service_type
+        else {
+            panic!("Should be a load balancer");
+        };
+        assert_eq!(load_balancer.servers[0].url.as_str(), expected_url);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(&[9443], None, "https://secure.my-host.local:7443/")]
+    #[case(&[], Some("https"), "https://secure.my-host.local:7443/")]
+    #[case(&[], None, "http://192.168.1.100:7443/")]
+    #[case(&[9443], Some("h2c"), "h2c://192.168.1.100:7443/")]
+    fn test_builder_https_base_url(
+        #[case] https_ports: &[u16],
+        #[case] scheme: Option<&str>,
+        #[case] expected_url: &str,
+    ) -> anyhow::Result<()> {
+        let mut container = web_container("a");
+        container.ports[0].private_port = 9443;
+        container.ports[0].public_port = Some(7443);
+        let TraefikedContainerConfig::MultiplePorts(configs) = &mut container.config else {
+            panic!("Should be a multiple ports config");
+        };
+        configs[0].target_ports = vec![9443];
+        configs[0].config.load_balancer.scheme = scheme.map(str::to_owned);
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_https_base_url(Url::parse("https://secure.my-host.local")?)
+            .https_ports(https_ports.iter().copied())
+            .add_container(&container)?
+            .build()?;
+
+        assert_eq!(
+            dynamic_configuration
+                .service("web")
+                .map(HttpServiceConfiguration::urls),
+            Some(vec![&Url::parse(expected_url)?])
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(false, 9443, &[9443], None, None)]
+    #[case(false, 9443, &[], Some("https"), None)]
+    #[case(true, 443, &[], Some("https"), None)]
+    #[case(true, 443, &[], None, Some("https://192.168.1.100:7443/"))]
+    fn test_builder_https_without_https_base_url(
+        #[case] detect_https: bool,
+        #[case] private_port: u16,
+        #[case] https_ports: &[u16],
+        #[case] scheme: Option<&str>,
+        #[case] expected_url: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut container = web_container("a");
+        container.ports[0].private_port = private_port;
+        container.ports[0].public_port = Some(7443);
+        let TraefikedContainerConfig::MultiplePorts(configs) = &mut container.config else {
+            panic!("Should be a multiple ports config");
+        };
+        configs[0].target_ports = vec![private_port];
+        configs[0].config.load_balancer.scheme = scheme.map(str::to_owned);
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let builder = DynamicConfigurationBuilder::new(base_url)
+            .detect_https(detect_https)
+            .https_ports(https_ports.iter().copied())
+            .add_container(&container);
+
+        match expected_url {
+            Some(expected_url) => assert_eq!(
+                builder?.build()?.service("web").map(HttpServiceConfiguration::urls),
+                Some(vec![&Url::parse(expected_url)?])
+            ),
+            None => assert_eq!(
+                builder.err().map(|error| error.to_string()),
+                Some(
+                    "Container 'a' targets an https server on port 7443 but no https base URL was given"
+                        .to_owned()
+                )
+            ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_https_base_url_additional_base_urls() -> anyhow::Result<()> {
+        let mut container = web_container("a");
+        container.ports[0].public_port = Some(443);
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_https_base_url(Url::parse("https://secure.my-host.local")?)
+            .with_additional_base_url(Url::parse("http://10.0.0.2")?)
+            .detect_https(true)
+            .add_container(&container)?
+            .build()?;
+
+        assert_eq!(
+            dynamic_configuration
+                .service("web")
+                .map(HttpServiceConfiguration::urls),
+            Some(vec![
+                &Url::parse("https://secure.my-host.local/")?,
+                &Url::parse("https://10.0.0.2:443/")?
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_allow_empty_services() -> anyhow::Result<()> {
+        let mut container = web_container("a");
+        container.ports = vec![];
+        container.tcp = vec![TraefikedContainerTcpConfig {
+            router_name: "db".to_owned(),
+            rule: "HostSNI(`*`)".to_owned(),
+            ..Default::default()
+        }];
+
+        let base_url = Url::parse("http://192.168.1.100")?;
+        assert!(DynamicConfigurationBuilder::new(base_url.clone())
+            .add_container(&container)
+            .is_err());
+
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .allow_empty_services(true)
+            .add_container(&container)?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    web:
+      rule: Host(`a.local`)
+      service: web
+  services:
+    web:
+      loadBalancer:
+        servers: []
+tcp:
+  routers:
+    db:
+      rule: HostSNI(`*`)
+      service: a
+  services:
+    a:
+      loadBalancer:
+        servers: []
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_non_special_scheme() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            scheme: Some("h2c".to_owned()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert!(configuration_yaml.contains("- url: h2c://192.168.1.100:7878/\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_server_url_override() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            url: Some(Url::parse("https://10.0.0.5:9443/internal/")?),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: https://10.0.0.5:9443/internal/
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_pass_host_header() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            pass_host_header: Some(false),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+        passHostHeader: false
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_health_check() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            health_check: Some(TraefikedHealthCheckConfig {
+                                path: "/health".to_owned(),
+                                interval: Some("10s".to_owned()),
+                                timeout: Some("3s".to_owned()),
+                                port: None,
+                                scheme: None,
+                            }),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+        healthCheck:
+          path: /health
+          interval: 10s
+          timeout: 3s
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(
+        TraefikedStickyCookieConfig::default(),
+        "        sticky:\n          cookie: {}\n"
+    )]
+    #[case(
+        TraefikedStickyCookieConfig {
+            name: Some("session".to_owned()),
+            secure: Some(true),
+            http_only: Some(true),
+            same_site: None,
+        },
+        "        sticky:\n          cookie:\n            name: session\n            secure: true\n            httpOnly: true\n"
+    )]
+    fn test_builder_sticky_cookie(
+        #[case] cookie: TraefikedStickyCookieConfig,
+        #[case] expected_sticky: &str,
+    ) -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            sticky: Some(TraefikedStickyConfig { cookie }),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = format!(
+            r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+{expected_sticky}"#
+        );
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_servers_transport() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            servers_transport: Some("my-transport@file".to_owned()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+        serversTransport: my-transport@file
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_default_servers_transport() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_default_servers_transport("internal-ca@file")
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router("to-my-service", "Host(`my-service.my-domain.com`)")
+                    .build()?,
+            )?
+            .add_container(
+                &TraefikedContainer::builder("other-service")
+                    .public_port(7879)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-other-service".to_owned(),
+                        rule: "Host(`other-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            servers_transport: Some("insecure@file".to_owned()),
+                            pass_host_header: Some(false),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+    to-other-service:
+      rule: Host(`other-service.my-domain.com`)
+      service: other-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+        serversTransport: internal-ca@file
+    other-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7879/
+        passHostHeader: false
+        serversTransport: insecure@file
+"#;
+
+        assert_eq!(dynamic_configuration.to_yaml()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_flush_interval() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            flush_interval: Some("1ms".to_owned()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+        responseForwarding:
+          flushInterval: 1ms
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_weighted_services() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("app-v2")
+                    .public_port(7878)
+                    .router("app-v2", "Host(`app-v2.my-domain.com`)")
+                    .weighted_service(
+                        "canary",
+                        vec![
+                            TraefikedWeightedService {
+                                name: "app-v1@file".to_owned(),
+                                weight: Some(90),
+                            },
+                            TraefikedWeightedService {
+                                name: "app-v2".to_owned(),
+                                weight: Some(10),
+                            },
+                        ],
+                    )
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    app-v2:
+      rule: Host(`app-v2.my-domain.com`)
+      service: app-v2
+  services:
+    app-v2:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+    canary:
+      weighted:
+        services:
+        - name: app-v1@file
+          weight: 90
+        - name: app-v2
+          weight: 10
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_servers_transport_definitions() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            servers_transport: Some("insecure".to_owned()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .servers_transport(
+                        "insecure",
+                        ServersTransportConfiguration {
+                            insecure_skip_verify: Some(true),
+                            ..Default::default()
+                        },
+                    )
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+        serversTransport: insecure
+  serversTransports:
+    insecure:
+      insecureSkipVerify: true
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_add_servers_transport() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .with_https_base_url(Url::parse("https://192.168.1.100")?)
+            .add_servers_transport(
+                "internal-ca",
+                ServersTransportConfiguration {
+                    server_name: Some("my-service.internal".to_owned()),
+                    insecure_skip_verify: Some(false),
+                    root_cas: vec!["/certs/internal-ca.crt".to_owned()],
+                    max_idle_conns_per_host: Some(7),
+                },
+            )
+            .add_container(
+                &TraefikedContainer::builder("my-service")
+                    .public_port(7878)
+                    .router_config(TraefikedContainerSinglePortConfig {
+                        router_name: "to-my-service".to_owned(),
+                        rule: "Host(`my-service.my-domain.com`)".to_owned(),
+                        load_balancer: TraefikedLoadBalancerConfig {
+                            scheme: Some("https".to_owned()),
+                            servers_transport: Some("internal-ca".to_owned()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      rule: Host(`my-service.my-domain.com`)
+      service: my-service
+  services:
+    my-service:
+      loadBalancer:
+        servers:
+        - url: https://192.168.1.100:7878/
+        serversTransport: internal-ca
+  serversTransports:
+    internal-ca:
+      serverName: my-service.internal
+      insecureSkipVerify: false
+      rootCAs:
+      - /certs/internal-ca.crt
+      maxIdleConnsPerHost: 7
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_tcp() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("postgres")
+                    .published_port(5432, 15432)
+                    .tcp(TraefikedContainerTcpConfig {
+                        router_name: "db".to_owned(),
+                        rule: "HostSNI(`*`)".to_owned(),
+                        entrypoints: vec!["postgres".to_owned()],
+                        tls: None,
+                        service_name: Some("db".to_owned()),
+                        target_port: Some(5432),
+                    })
+                    .tcp(TraefikedContainerTcpConfig {
+                        router_name: "db-tls".to_owned(),
+                        rule: "HostSNI(`db.my-domain.com`)".to_owned(),
+                        tls: Some(TraefikedTcpRouterTlsConfig {
+                            passthrough: Some(true),
+                            cert_resolver: None,
+                        }),
+                        ..Default::default()
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers: {}
+  services: {}
+tcp:
+  routers:
+    db:
+      entryPoints:
+      - postgres
+      rule: HostSNI(`*`)
+      service: db
+    db-tls:
+      rule: HostSNI(`db.my-domain.com`)
+      service: postgres
+      tls:
+        passthrough: true
+  services:
+    db:
+      loadBalancer:
+        servers:
+        - address: 192.168.1.100:15432
+    postgres:
+      loadBalancer:
+        servers:
+        - address: 192.168.1.100:15432
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_udp() -> anyhow::Result<()> {
+        let base_url = Url::parse("http://192.168.1.100")?;
+        let dynamic_configuration = DynamicConfigurationBuilder::new(base_url)
+            .add_container(
+                &TraefikedContainer::builder("wireguard")
+                    .published_port(51820, 51820)
+                    .udp(TraefikedContainerUdpConfig {
+                        router_name: "vpn".to_owned(),
+                        entrypoints: vec!["wg".to_owned()],
+                        service_name: Some("vpn".to_owned()),
+                        target_port: Some(51820),
+                    })
+                    .build()?,
+            )?
+            .build()?;
+
+        let expected = r#"http:
+  routers: {}
+  services: {}
+udp:
+  routers:
+    vpn:
+      entryPoints:
+      - wg
+      service: vpn
+  services:
+    vpn:
+      loadBalancer:
+        servers:
+        - address: 192.168.1.100:51820
+"#;
+
+        let configuration_yaml = serde_yaml::to_string(&dynamic_configuration)?;
+
+        assert_eq!(configuration_yaml, expected);
+        Ok(())
+    }
+}
+
+/// Snapshots of a configuration with every optional field set, checked against Traefik's
+/// [file provider reference](https://doc.traefik.io/traefik/v2.10/reference/dynamic-configuration/file/).
+///
+/// The configuration is built from struct literals without `..Default::default()`, so adding a
+/// field fails to compile until these snapshots are updated and checked against the reference.
+/// Middlewares are covered by the tests of [`crate::middleware`].
+#[cfg(test)]
+mod conformance_tests {
+    use crate::tls::TlsClientAuth;
+
+    use super::*;
+
+    fn fully_populated() -> anyhow::Result<DynamicConfiguration> {
+        let router = HttpRouterConfiguration {
+            entry_points: vec!["websecure".to_owned()],
+            rule: "Host(`my-service.my-domain.com`)".to_owned(),
+            rule_syntax: Some("v3".to_owned()),
+            service: "my-service".to_owned(),
+            middlewares: vec!["compress@file".to_owned()],
+            priority: Some(10),
+            tls: Some(HttpRouterTlsConfiguration {
+                cert_resolver: Some("le".to_owned()),
+                options: Some("modern".to_owned()),
+                domains: vec![TlsDomain {
+                    main: "my-domain.com".to_owned(),
+                    sans: vec!["*.my-domain.com".to_owned()],
+                }],
+            }),
+            observability: Some(ObservabilityConfiguration {
+                access_logs: Some(false),
+                metrics: Some(true),
+                tracing: Some(false),
+            }),
+        };
+        let load_balancer = HttpServiceConfiguration {
+            service_type: HttpServiceType::LoadBalancer(LoadBalancerHttpServiceConfiguration {
+                servers: vec![ServiceUrl {
+                    url: Url::parse("http://192.168.1.100:7878")?,
+                    weight: Some(3),
+                    trailing_slash: true,
+                }],
+                pass_host_header: Some(false),
+                health_check: Some(HealthCheckConfiguration {
+                    path: "/health".to_owned(),
+                    interval: Some("10s".to_owned()),
+                    timeout: Some("3s".to_owned()),
+                    port: Some(8080),
+                    scheme: Some("http".to_owned()),
+                }),
+                sticky: Some(StickyConfiguration {
+                    cookie: StickyCookieConfiguration {
+                        name: Some("session".to_owned()),
+                        secure: Some(true),
+                        http_only: Some(true),
+                        same_site: Some("strict".to_owned()),
+                    },
+                }),
+                servers_transport: Some("internal-ca@file".to_owned()),
+                response_forwarding: Some(ResponseForwardingConfiguration {
+                    flush_interval: "100ms".to_owned(),
+                }),
+            }),
+        };
+        let weighted = HttpServiceConfiguration {
+            service_type: HttpServiceType::Weighted(WeightedHttpServiceConfiguration {
+                services: vec![WeightedServiceReference {
+                    name: "my-service".to_owned(),
+                    weight: Some(1),
+                }],
+            }),
+        };
+
+        Ok(DynamicConfiguration {
+            http: HttpConfiguration {
+                routers: BTreeMap::from([("to-my-service".to_owned(), router)]),
+                services: BTreeMap::from([
+                    ("my-service".to_owned(), load_balancer),
+                    ("canary".to_owned(), weighted),
+                ]),
+                middlewares: BTreeMap::new(),
+                servers_transports: BTreeMap::from([(
+                    "internal-ca".to_owned(),
+                    ServersTransportConfiguration {
+                        server_name: Some("my-service.internal".to_owned()),
+                        insecure_skip_verify: Some(false),
+                        root_cas: vec!["/certs/ca.pem".to_owned()],
+                        max_idle_conns_per_host: Some(4),
+                    },
+                )]),
+            },
+            tcp: TcpConfiguration {
+                routers: BTreeMap::from([(
+                    "db".to_owned(),
+                    TcpRouterConfiguration {
+                        entry_points: vec!["postgres".to_owned()],
+                        rule: "HostSNI(`db.my-domain.com`)".to_owned(),
+                        service: "db".to_owned(),
+                        tls: Some(TcpRouterTlsConfiguration {
+                            passthrough: Some(true),
+                            cert_resolver: Some("le".to_owned()),
+                        }),
+                    },
+                )]),
+                services: BTreeMap::from([(
+                    "db".to_owned(),
+                    TcpServiceConfiguration {
+                        service_type: TcpServiceType::LoadBalancer(
+                            LoadBalancerTcpServiceConfiguration {
+                                servers: vec![ServiceAddress {
+                                    address: "192.168.1.100:5432".to_owned(),
+                                }],
+                            },
+                        ),
+                    },
+                )]),
+            },
+            udp: UdpConfiguration {
+                routers: BTreeMap::from([(
+                    "dns".to_owned(),
+                    UdpRouterConfiguration {
+                        entry_points: vec!["dns".to_owned()],
+                        service: "dns".to_owned(),
+                    },
+                )]),
+                services: BTreeMap::from([(
+                    "dns".to_owned(),
+                    UdpServiceConfiguration {
+                        service_type: UdpServiceType::LoadBalancer(
+                            LoadBalancerUdpServiceConfiguration {
+                                servers: vec![ServiceAddress {
+                                    address: "192.168.1.100:53".to_owned(),
+                                }],
+                            },
+                        ),
+                    },
+                )]),
+            },
+            tls: TlsConfiguration {
+                certificates: vec![TlsCertificate {
+                    cert_file: "/certs/my-domain.com.crt".to_owned(),
+                    key_file: "/certs/my-domain.com.key".to_owned(),
+                    stores: vec!["default".to_owned()],
+                }],
+                options: BTreeMap::from([(
+                    "modern".to_owned(),
+                    TlsOptions {
+                        min_version: Some("VersionTLS12".to_owned()),
+                        max_version: Some("VersionTLS13".to_owned()),
+                        cipher_suites: vec!["TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384".to_owned()],
+                        curve_preferences: vec!["CurveP521".to_owned()],
+                        client_auth: Some(TlsClientAuth {
+                            ca_files: vec!["/certs/client-ca.pem".to_owned()],
+                            client_auth_type: Some("RequireAndVerifyClientCert".to_owned()),
+                        }),
+                        sni_strict: Some(true),
+                        alpn_protocols: vec!["h2".to_owned()],
+                    },
+                )]),
+            },
+        })
+    }
+
+    #[test]
+    fn test_yaml_conformance() -> anyhow::Result<()> {
+        let expected = r#"http:
+  routers:
+    to-my-service:
+      entryPoints:
+      - websecure
+      rule: Host(`my-service.my-domain.com`)
+      ruleSyntax: v3
+      service: my-service
+      middlewares:
+      - compress@file
+      priority: 10
+      tls:
+        certResolver: le
+        options: modern
+        domains:
+        - main: my-domain.com
+          sans:
+          - '*.my-domain.com'
+      observability:
+        accessLogs: false
+        metrics: true
+        tracing: false
+  services:
+    canary:
+      weighted:
+        services:
+        - name: my-service
+          weight: 1
+    my-service:
+      loadBalancer:
+        servers:
+        - url: http://192.168.1.100:7878/
+          weight: 3
+        passHostHeader: false
+        healthCheck:
+          path: /health
+          interval: 10s
+          timeout: 3s
+          port: 8080
+          scheme: http
+        sticky:
+          cookie:
+            name: session
+            secure: true
+            httpOnly: true
+            sameSite: strict
+        serversTransport: internal-ca@file
+        responseForwarding:
+          flushInterval: 100ms
+  serversTransports:
+    internal-ca:
+      serverName: my-service.internal
+      insecureSkipVerify: false
+      rootCAs:
+      - /certs/ca.pem
+      maxIdleConnsPerHost: 4
+tcp:
+  routers:
+    db:
+      entryPoints:
+      - postgres
+      rule: HostSNI(`db.my-domain.com`)
+      service: db
+      tls:
+        passthrough: true
+        certResolver: le
+  services:
+    db:
+      loadBalancer:
+        servers:
+        - address: 192.168.1.100:5432
+udp:
+  routers:
+    dns:
+      entryPoints:
+      - dns
+      service: dns
+  services:
+    dns:
+      loadBalancer:
+        servers:
+        - address: 192.168.1.100:53
+tls:
+  certificates:
+  - certFile: /certs/my-domain.com.crt
+    keyFile: /certs/my-domain.com.key
+    stores:
+    - default
+  options:
+    modern:
+      minVersion: VersionTLS12
+      maxVersion: VersionTLS13
+      cipherSuites:
+      - TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384
+      curvePreferences:
+      - CurveP521
+      clientAuth:
+        caFiles:
+        - /certs/client-ca.pem
+        clientAuthType: RequireAndVerifyClientCert
+      sniStrict: true
+      alpnProtocols:
+      - h2
+"#;
+
+        assert_eq!(fully_populated()?.to_yaml()?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_conformance() -> anyhow::Result<()> {
+        let expected = r#"{
+  "http": {
+    "routers": {
+      "to-my-service": {
+        "entryPoints": [
+          "websecure"
+        ],
+        "rule": "Host(`my-service.my-domain.com`)",
+        "ruleSyntax": "v3",
+        "service": "my-service",
+        "middlewares": [
+          "compress@file"
+        ],
+        "priority": 10,
+        "tls": {
+          "certResolver": "le",
+          "options": "modern",
+          "domains": [
+            {
+              "main": "my-domain.com",
+              "sans": [
+                "*.my-domain.com"
+              ]
+            }
+          ]
+        },
+        "observability": {
+          "accessLogs": false,
+          "metrics": true,
+          "tracing": false
+        }
+      }
+    },
+    "services": {
+      "canary": {
+        "weighted": {
+          "services": [
+            {
+              "name": "my-service",
+              "weight": 1
+            }
+          ]
+        }
+      },
+      "my-service": {
+        "loadBalancer": {
+          "servers": [
+            {
+              "url": "http://192.168.1.100:7878/",
+              "weight": 3
+            }
+          ],
+          "passHostHeader": false,
+          "healthCheck": {
+            "path": "/health",
+            "interval": "10s",
+            "timeout": "3s",
+            "port": 8080,
+            "scheme": "http"
+          },
+          "sticky": {
+            "cookie": {
+              "name": "session",
+              "secure": true,
+              "httpOnly": true,
+              "sameSite": "strict"
+            }
+          },
+          "serversTransport": "internal-ca@file",
+          "responseForwarding": {
+            "flushInterval": "100ms"
+          }
+        }
+      }
+    },
+    "serversTransports": {
+      "internal-ca": {
+        "serverName": "my-service.internal",
+        "insecureSkipVerify": false,
+        "rootCAs": [
+          "/certs/ca.pem"
+        ],
+        "maxIdleConnsPerHost": 4
+      }
+    }
+  },
+  "tcp": {
+    "routers": {
+      "db": {
+        "entryPoints": [
+          "postgres"
+        ],
+        "rule": "HostSNI(`db.my-domain.com`)",
+        "service": "db",
+        "tls": {
+          "passthrough": true,
+          "certResolver": "le"
+        }
+      }
+    },
+    "services": {
+      "db": {
+        "loadBalancer": {
+          "servers": [
+            {
+              "address": "192.168.1.100:5432"
+            }
+          ]
+        }
+      }
+    }
+  },
+  "udp": {
+    "routers": {
+      "dns": {
+        "entryPoints": [
+          "dns"
+        ],
+        "service": "dns"
+      }
+    },
+    "services": {
+      "dns": {
+        "loadBalancer": {
+          "servers": [
+            {
+              "address": "192.168.1.100:53"
+            }
+          ]
+        }
+      }
+    }
+  },
+  "tls": {
+    "certificates": [
+      {
+        "certFile": "/certs/my-domain.com.crt",
+        "keyFile": "/certs/my-domain.com.key",
+        "stores": [
+          "default"
+        ]
+      }
+    ],
+    "options": {
+      "modern": {
+        "minVersion": "VersionTLS12",
+        "maxVersion": "VersionTLS13",
+        "cipherSuites": [
+          "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"
+        ],
+        "curvePreferences": [
+          "CurveP521"
+        ],
+        "clientAuth": {
+          "caFiles": [
+            "/certs/client-ca.pem"
+          ],
+          "clientAuthType": "RequireAndVerifyClientCert"
+        },
+        "sniStrict": true,
+        "alpnProtocols": [
+          "h2"
+        ]
+      }
+    }
+  }
+}"#;
+
+        assert_eq!(fully_populated()?.to_json()?, expected);
+        Ok(())
+    }
+
+    /// Reading the snapshot back, as the file provider would, gives the same configuration.
+    #[test]
+    fn test_yaml_conformance_round_trip() -> anyhow::Result<()> {
+        let configuration = fully_populated()?;
+        let read_back: DynamicConfiguration = serde_yaml::from_str(&configuration.to_yaml()?)?;
+
+        assert_eq!(read_back.to_yaml()?, configuration.to_yaml()?);
         Ok(())
     }
 }
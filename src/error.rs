@@ -0,0 +1,137 @@
+use std::fmt::{Display, Formatter};
+
+use itertools::Itertools;
+use thiserror::Error;
+
+/// Reason a Docker container could not be converted into a
+/// [`TraefikedContainer`](crate::TraefikedContainer).
+///
+/// Being a standard error, it converts into an [`anyhow::Error`] with `?`.
+#[derive(Debug, Error)]
+pub enum ContainerConversionError {
+    #[error("No container name found")]
+    MissingName,
+    #[error("Cannot derive a Traefik name from container name '{name}'")]
+    InvalidName { name: String },
+    #[error("No ports specified")]
+    NoPorts,
+    /// A port label whose value is not a port between 1 and 65535.
+    #[error("Invalid port '{value}' in label '{label}'")]
+    InvalidPortValue { label: String, value: String },
+    /// Docker reported a port 0, which nothing can be reached on.
+    #[error("Port 0 cannot be targeted")]
+    ZeroPort,
+    #[error("Could not find a traefik rule label")]
+    NoTraefikLabels,
+    /// Labeled `traefik.enable=false`, or without the label when not exposed by default.
+    #[error("Traefik is not enabled on the container")]
+    TraefikDisabled,
+    #[error("Cannot substitute placeholders in '{label}': {reason}")]
+    InvalidPlaceholder { label: String, reason: String },
+    #[error("Invalid base URL '{value}': {reason}")]
+    InvalidBaseUrl { value: String, reason: String },
+    /// Labels that are individually valid but cannot be combined, e.g. routers that cannot be
+    /// paired with services.
+    #[error(transparent)]
+    InvalidLabels(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ContainerConversionError {
+    /// Recovers the conversion errors raised while parsing labels, other errors being
+    /// [`InvalidLabels`](ContainerConversionError::InvalidLabels).
+    fn from(error: anyhow::Error) -> Self {
+        error
+            .downcast()
+            .unwrap_or_else(ContainerConversionError::InvalidLabels)
+    }
+}
+
+/// Reason a [`DynamicConfiguration`](crate::dynamic_configuration::DynamicConfiguration) could
+/// not be serialized.
+#[derive(Debug, Error)]
+pub enum SerializationError {
+    #[error("Cannot serialize to YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Cannot serialize to JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Cannot serialize to TOML: {0}")]
+    Toml(anyhow::Error),
+}
+
+/// Names declared by both configurations given to
+/// [`DynamicConfiguration::merge`](crate::dynamic_configuration::DynamicConfiguration::merge)
+/// with [`MergePolicy::Error`](crate::dynamic_configuration::MergePolicy::Error).
+#[derive(Debug, Error, PartialEq)]
+#[error("Conflicting entries: {}", .conflicts.iter().join(", "))]
+pub struct MergeConflicts {
+    pub conflicts: Vec<MergeConflict>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeConflict {
+    /// e.g. `HTTP router` or `middleware`
+    pub kind: &'static str,
+    pub name: String,
+}
+
+impl Display for MergeConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} '{}'", self.kind, self.name)
+    }
+}
+
+/// Problems found by
+/// [`DynamicConfigurationBuilder::build`](crate::dynamic_configuration::DynamicConfigurationBuilder::build).
+#[derive(Debug, Error, PartialEq)]
+#[error("Invalid dynamic configuration:\n{}", .errors.iter().join("\n"))]
+pub struct ValidationErrors {
+    pub errors: Vec<ValidationError>,
+}
+
+/// How Traefik treats a [`ValidationIssue`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Accepted by Traefik, though likely unintended, e.g. a service answering 503.
+    Warning,
+    /// Rejected by Traefik, e.g. a router referencing something undefined is not served.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Problem found by
+/// [`DynamicConfiguration::validate`](crate::dynamic_configuration::DynamicConfiguration::validate),
+/// e.g. ``error: HTTP router 'api' targets the undefined service 'api-v2'``.
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("{severity}: {error}")]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub error: ValidationError,
+}
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ValidationError {
+    #[error("{router_kind} '{router}' targets the undefined service '{service}'")]
+    UndefinedService {
+        router_kind: &'static str,
+        router: String,
+        service: String,
+    },
+    #[error("HTTP router '{router}' references the undefined middleware '{middleware}'")]
+    UndefinedMiddleware { router: String, middleware: String },
+    #[error("{kind} with an empty name")]
+    EmptyName { kind: &'static str },
+    #[error("{kind} '{name}' has no servers")]
+    NoServers { kind: &'static str, name: String },
+    #[error("HTTP router '{router}' references the undefined TLS options '{options}'")]
+    UndefinedTlsOptions { router: String, options: String },
+    #[error("{0} of the extra configuration is already generated")]
+    ExtraConfigurationConflict(MergeConflict),
+}
@@ -1,137 +1,4502 @@
 use anyhow::anyhow;
-use bollard::models::ContainerSummary;
+use bollard::models::{ContainerSummary, Port, PortTypeEnum};
 use itertools::Itertools;
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashMap;
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use url::Url;
 
+use crate::diagnostics::{Diagnostics, LabelParseError};
+use crate::docker::DockerOptions;
+use crate::error::ContainerConversionError;
+use crate::middleware::{extract_middlewares, MiddlewareConfiguration};
+use crate::rule::{rule_hostnames, validate_rule};
+use crate::servers_transport::{extract_servers_transports, ServersTransportConfiguration};
+use crate::tcp::{
+    extract_tcp_config, has_tcp_routers, TcpLabelRegexes, TraefikedContainerTcpConfig,
+};
+use crate::template::{substitute, substitute_labels, TemplateVariables};
+use crate::udp::{
+    extract_udp_config, has_udp_routers, TraefikedContainerUdpConfig, UdpLabelRegexes,
+};
+use crate::weighted::{extract_weighted_services, TraefikedWeightedService};
+
+pub mod diagnostics;
+pub mod diff;
 pub mod docker;
 pub mod dynamic_configuration;
+pub mod error;
+pub mod middleware;
+pub mod rule;
+pub mod servers_transport;
+pub mod tcp;
+mod template;
+pub mod tls;
+mod toml;
+pub mod udp;
+pub mod weighted;
+
+pub const DEFAULT_LABEL_PREFIX: &str = "traefik";
+
+const ENABLE_LABEL: &str = "enable";
+const DOCKER_NETWORK_LABEL: &str = "docker.network";
+const COMPOSE_SERVICE_LABEL: &str = "com.docker.compose.service";
+const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+/// Maximum length of the router and service names derived from container names.
+const MAX_NAME_LENGTH: usize = 63;
+/// Traefik v1 label declaring the container port, superseded by `loadbalancer.server.port`.
+const LEGACY_PORT_LABEL: &str = "port";
+/// Label generating a ``Host(`<hostname>.{{ domain }}`)`` router for containers without a rule label.
+const HOSTNAME_LABEL: &str = "traefik-provider.hostname";
+/// Label overriding the base URL the container is reached through.
+const BASE_URL_LABEL: &str = "traefik-provider.base-url";
+
+/// Label regexes compiled for a label prefix, `traefik` unless configured otherwise.
+pub(crate) struct LabelRegexes {
+    prefix: String,
+    routers: Regex,
+    router_entrypoints: Regex,
+    router_middlewares: Regex,
+    router_service: Regex,
+    router_priority: Regex,
+    router_rule_syntax: Regex,
+    router_observability: Regex,
+    router_tls: Regex,
+    router_tls_cert_resolver: Regex,
+    router_tls_options: Regex,
+    router_tls_domain: Regex,
+    service_name: Regex,
+    service_scheme: Regex,
+    service_pass_host_header: Regex,
+    service_health_check: Regex,
+    service_sticky_cookie: Regex,
+    service_servers_transport: Regex,
+    service_flush_interval: Regex,
+    service_url: Regex,
+    service_port: Regex,
+    pub(crate) tcp: TcpLabelRegexes,
+    pub(crate) udp: UdpLabelRegexes,
+    pub(crate) middlewares: Regex,
+    pub(crate) servers_transports: Regex,
+    pub(crate) weighted_services: Regex,
+    /// Any router or service label, supported or not
+    router_or_service: Regex,
+    /// The regexes of [`HttpOption::ALL`], matched at once.
+    http_options: RegexSet,
+}
+
+/// HTTP router and service options, each matched by a regex of [`LabelRegexes`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HttpOption {
+    Rule,
+    Entrypoints,
+    Middlewares,
+    Service,
+    Priority,
+    RuleSyntax,
+    Observability,
+    Tls,
+    TlsCertResolver,
+    TlsOptions,
+    TlsDomain,
+    /// Any label of a service, supported or not.
+    ServiceName,
+    Scheme,
+    PassHostHeader,
+    HealthCheck,
+    StickyCookie,
+    ServersTransport,
+    FlushInterval,
+    Url,
+    Port,
+    Weighted,
+}
+
+impl HttpOption {
+    const ALL: [HttpOption; 21] = [
+        HttpOption::Rule,
+        HttpOption::Entrypoints,
+        HttpOption::Middlewares,
+        HttpOption::Service,
+        HttpOption::Priority,
+        HttpOption::RuleSyntax,
+        HttpOption::Observability,
+        HttpOption::Tls,
+        HttpOption::TlsCertResolver,
+        HttpOption::TlsOptions,
+        HttpOption::TlsDomain,
+        HttpOption::ServiceName,
+        HttpOption::Scheme,
+        HttpOption::PassHostHeader,
+        HttpOption::HealthCheck,
+        HttpOption::StickyCookie,
+        HttpOption::ServersTransport,
+        HttpOption::FlushInterval,
+        HttpOption::Url,
+        HttpOption::Port,
+        HttpOption::Weighted,
+    ];
+}
+
+/// HTTP router and service labels of a container sorted by option in a single pass, so the regex
+/// of an option only runs on its own labels to capture their names.
+struct HttpLabels<'a> {
+    regexes: &'a LabelRegexes,
+    /// Labels matching each option, in the order of [`HttpOption::ALL`].
+    options: Vec<Vec<(&'a String, &'a String)>>,
+}
+
+impl<'a> HttpLabels<'a> {
+    fn new(labels: &'a HashMap<String, String>, regexes: &'a LabelRegexes) -> Self {
+        let mut options = vec![vec![]; HttpOption::ALL.len()];
+        for label in labels {
+            for index in regexes.http_options.matches(label.0) {
+                options[index].push(label);
+            }
+        }
+
+        Self { regexes, options }
+    }
+
+    /// Labels matching `option`.
+    fn matching(&self, option: HttpOption) -> impl Iterator<Item = (&'a String, &'a String)> + '_ {
+        self.options[option as usize].iter().copied()
+    }
+
+    /// See [`capture_labels`].
+    fn capture(&self, option: HttpOption) -> HashMap<String, &'a String> {
+        capture_labels(self.matching(option), self.regexes.http_option(option))
+    }
+
+    /// See [`capture_grouped_labels`].
+    fn capture_grouped(&self, option: HttpOption) -> HashMap<String, BTreeMap<String, &'a String>> {
+        capture_grouped_labels(self.matching(option), self.regexes.http_option(option))
+    }
+}
+
+impl LabelRegexes {
+    pub(crate) fn new(prefix: &str) -> Self {
+        let mut regexes = Self {
+            prefix: prefix.to_owned(),
+            routers: label_regex(prefix, r"http\.routers\.([^.]+)\.rule$"),
+            router_entrypoints: label_regex(prefix, r"http\.routers\.([^.]+)\.entrypoints$"),
+            router_middlewares: label_regex(prefix, r"http\.routers\.([^.]+)\.middlewares$"),
+            router_service: label_regex(prefix, r"http\.routers\.([^.]+)\.service$"),
+            router_priority: label_regex(prefix, r"http\.routers\.([^.]+)\.priority$"),
+            router_rule_syntax: label_regex(prefix, r"http\.routers\.([^.]+)\.rulesyntax$"),
+            router_observability: label_regex(
+                prefix,
+                r"http\.routers\.([^.]+)\.observability\.([^.]+)$",
+            ),
+            router_tls: label_regex(prefix, r"http\.routers\.([^.]+)\.tls$"),
+            router_tls_cert_resolver: label_regex(
+                prefix,
+                r"http\.routers\.([^.]+)\.tls\.certresolver$",
+            ),
+            router_tls_options: label_regex(prefix, r"http\.routers\.([^.]+)\.tls\.options$"),
+            router_tls_domain: label_regex(
+                prefix,
+                r"http\.routers\.([^.]+)\.tls\.domains\[([^\]]*)\]\.([^.]+)$",
+            ),
+            service_name: label_regex(prefix, r"http\.services\.([^.]+)\."),
+            service_scheme: label_regex(
+                prefix,
+                r"http\.services\.([^.]+)\.loadbalancer\.server\.scheme$",
+            ),
+            service_pass_host_header: label_regex(
+                prefix,
+                r"http\.services\.([^.]+)\.loadbalancer\.passhostheader$",
+            ),
+            service_health_check: label_regex(
+                prefix,
+                r"http\.services\.([^.]+)\.loadbalancer\.healthcheck\.([^.]+)$",
+            ),
+            service_sticky_cookie: label_regex(
+                prefix,
+                r"http\.services\.([^.]+)\.loadbalancer\.sticky\.cookie(?:\.([^.]+))?$",
+            ),
+            service_flush_interval: label_regex(
+                prefix,
+                r"http\.services\.([^.]+)\.loadbalancer\.responseforwarding\.flushinterval$",
+            ),
+            service_servers_transport: label_regex(
+                prefix,
+                r"http\.services\.([^.]+)\.loadbalancer\.serverstransport$",
+            ),
+            service_url: label_regex(
+                prefix,
+                r"http\.services\.([^.]+)\.loadbalancer\.server\.url$",
+            ),
+            service_port: label_regex(
+                prefix,
+                r"http\.services\.([^.]+)\.loadbalancer\.server\.port$",
+            ),
+            tcp: TcpLabelRegexes::new(prefix),
+            udp: UdpLabelRegexes::new(prefix),
+            middlewares: label_regex(prefix, r"http\.middlewares\.([^.]+)\.([^.]+)\.(.+)$"),
+            servers_transports: label_regex(prefix, r"http\.serverstransports\.([^.]+)\.([^.]+)$"),
+            weighted_services: label_regex(
+                prefix,
+                r"http\.services\.([^.]+)\.weighted\.services\[([^\]]*)\]\.([^.]+)$",
+            ),
+            router_or_service: label_regex(prefix, r"(http|tcp|udp)\.(routers|services)\.([^.]*)"),
+            http_options: RegexSet::empty(),
+        };
+        regexes.http_options =
+            RegexSet::new(HttpOption::ALL.map(|option| regexes.http_option(option).as_str()))
+                .expect("Label regexes should be valid");
+        regexes
+    }
+
+    fn http_option(&self, option: HttpOption) -> &Regex {
+        match option {
+            HttpOption::Rule => &self.routers,
+            HttpOption::Entrypoints => &self.router_entrypoints,
+            HttpOption::Middlewares => &self.router_middlewares,
+            HttpOption::Service => &self.router_service,
+            HttpOption::Priority => &self.router_priority,
+            HttpOption::RuleSyntax => &self.router_rule_syntax,
+            HttpOption::Observability => &self.router_observability,
+            HttpOption::Tls => &self.router_tls,
+            HttpOption::TlsCertResolver => &self.router_tls_cert_resolver,
+            HttpOption::TlsOptions => &self.router_tls_options,
+            HttpOption::TlsDomain => &self.router_tls_domain,
+            HttpOption::ServiceName => &self.service_name,
+            HttpOption::Scheme => &self.service_scheme,
+            HttpOption::PassHostHeader => &self.service_pass_host_header,
+            HttpOption::HealthCheck => &self.service_health_check,
+            HttpOption::StickyCookie => &self.service_sticky_cookie,
+            HttpOption::ServersTransport => &self.service_servers_transport,
+            HttpOption::FlushInterval => &self.service_flush_interval,
+            HttpOption::Url => &self.service_url,
+            HttpOption::Port => &self.service_port,
+            HttpOption::Weighted => &self.weighted_services,
+        }
+    }
+
+    /// Whether `label_key` is a supported HTTP router or service label.
+    fn is_supported_http(&self, label_key: &str) -> bool {
+        self.http_options
+            .matches(label_key)
+            .iter()
+            .any(|index| HttpOption::ALL[index] != HttpOption::ServiceName)
+    }
+
+    /// Whether `label_key` starts with `<prefix>.`, compared case-insensitively like the regexes.
+    ///
+    /// This is much cheaper than running the regexes, so foreign labels (compose, orchestration...)
+    /// are discarded with it before parsing.
+    pub(crate) fn is_own_label(&self, label_key: &str) -> bool {
+        label_key
+            .get(..self.prefix.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&self.prefix))
+            && label_key[self.prefix.len()..].starts_with('.')
+    }
+
+    /// The `<prefix>.*` labels among `labels`.
+    pub(crate) fn own_labels(&self, labels: HashMap<String, String>) -> HashMap<String, String> {
+        labels
+            .into_iter()
+            .filter(|(label_key, _)| self.is_own_label(label_key))
+            .collect()
+    }
+
+    /// Full key of the `<prefix>.<name>` label.
+    pub(crate) fn label(&self, name: &str) -> String {
+        format!("{}.{}", self.prefix, name)
+    }
+
+    /// Full key of the `<prefix>.http.routers.<router_name>.<option>` label.
+    fn router_label(&self, router_name: &str, option: &str) -> String {
+        self.label(&format!("http.routers.{}.{}", router_name, option))
+    }
+
+    /// Full key of the `<prefix>.http.services.<service_name>.loadbalancer.<option>` label.
+    fn service_label(&self, service_name: &str, option: &str) -> String {
+        self.label(&format!(
+            "http.services.{}.loadbalancer.{}",
+            service_name, option
+        ))
+    }
+}
 
-lazy_static! {
-    static ref ROUTERS_LABEL_REGEX: Regex =
-        Regex::new(r"traefik\.http\.routers\.(.+)\.rule").unwrap();
-    static ref SERVICE_LABEL_REGEX: Regex =
-        Regex::new(r"traefik\.http\.services.(.+).loadbalancer.server.port").unwrap();
+impl Default for LabelRegexes {
+    fn default() -> Self {
+        Self::new(DEFAULT_LABEL_PREFIX)
+    }
+}
+
+/// Compiles a case-insensitive regex matching `<prefix>.<pattern>` label keys.
+pub(crate) fn label_regex(prefix: &str, pattern: &str) -> Regex {
+    Regex::new(&format!(r"(?i)^{}\.{}", regex::escape(prefix), pattern))
+        .expect("Label regex should be valid")
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TraefikedContainer {
     pub name: String,
-    pub public_ports: Vec<u16>,
+    /// All the names Docker reports for the container, sorted, including `name`.
+    pub aliases: Vec<String>,
+    /// `name`, or the compose service name when enabled, sanitized into a Traefik identifier
+    /// naming the services of the container.
+    pub service_name: String,
+    /// Compose project of the container, used to qualify `service_name` on collisions.
+    /// Only set when naming services after compose services.
+    pub compose_project: Option<String>,
+    /// Compose service the container was started for, whatever its services are named after,
+    /// identifying its replicas.
+    pub compose_service: Option<ComposeService>,
+    /// Ports of the container as reported by Docker, once per protocol and host IP binding.
+    pub ports: Vec<PortMapping>,
+    /// IP address of the container on each network it is attached to.
+    pub networks: BTreeMap<String, String>,
+    /// Network declared by the `traefik.docker.network` label.
+    pub network: Option<String>,
+    /// Base URL declared by the `traefik-provider.base-url` label, reaching the container
+    /// instead of the base URL of the configuration.
+    pub base_url: Option<Url>,
     pub config: TraefikedContainerConfig,
+    pub middlewares: BTreeMap<String, MiddlewareConfiguration>,
+    pub servers_transports: BTreeMap<String, ServersTransportConfiguration>,
+    /// Weighted services declared by the labels, balancing services by name.
+    pub weighted_services: BTreeMap<String, Vec<TraefikedWeightedService>>,
+    pub tcp: Vec<TraefikedContainerTcpConfig>,
+    pub udp: Vec<TraefikedContainerUdpConfig>,
 }
 
-#[derive(Clone, Debug)]
+/// Compose service of a container, from the `com.docker.compose.project` and
+/// `com.docker.compose.service` labels, e.g. `web` of project `app` for container `app-web-1`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ComposeService {
+    pub project: String,
+    pub service: String,
+}
+
+/// A container-side port, possibly published on the host.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PortMapping {
+    pub private_port: u16,
+    pub public_port: Option<u16>,
+    /// Host IP the port is published on, e.g. `0.0.0.0` for all IPv4 interfaces.
+    pub host_ip: Option<IpAddr>,
+    pub protocol: PortProtocol,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortProtocol {
+    #[default]
+    Tcp,
+    Udp,
+    Sctp,
+}
+
+impl From<&Port> for PortMapping {
+    fn from(port: &Port) -> Self {
+        Self {
+            private_port: port.private_port,
+            public_port: port.public_port,
+            host_ip: port.ip.as_deref().and_then(|ip| ip.parse().ok()),
+            protocol: match port.typ {
+                Some(PortTypeEnum::UDP) => PortProtocol::Udp,
+                Some(PortTypeEnum::SCTP) => PortProtocol::Sctp,
+                _ => PortProtocol::Tcp,
+            },
+        }
+    }
+}
+
+/// HTTP routers of a container. A container with only TCP/UDP routers has no `MultiplePorts` entries.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum TraefikedContainerConfig {
     SinglePort(TraefikedContainerSinglePortConfig),
     MultiplePorts(Vec<TraefikedContainerMultiPortConfig>),
 }
 
-#[derive(Clone, Debug)]
+impl TraefikedContainerConfig {
+    /// Whether every router targets a service with an explicit `server.url` or a service of
+    /// another provider, none of them needing the container ports.
+    pub fn targets_explicit_urls(&self) -> bool {
+        match self {
+            TraefikedContainerConfig::SinglePort(config) => config.load_balancer.url.is_some(),
+            TraefikedContainerConfig::MultiplePorts(configs) => configs.iter().all(|c| {
+                c.config.load_balancer.url.is_some() || is_provider_qualified(&c.service_name)
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct TraefikedContainerSinglePortConfig {
     pub router_name: String,
     pub rule: String,
+    pub entrypoints: Vec<String>,
+    /// Middlewares declared by the `middlewares` label, an empty label opting out of the default
+    /// middlewares.
+    pub middlewares: Option<Vec<String>>,
+    pub priority: Option<i64>,
+    /// Rule syntax of the router, `v2` keeping the Traefik v2 rule semantics on Traefik v3.
+    pub rule_syntax: Option<String>,
+    pub tls: Option<TraefikedRouterTlsConfig>,
+    /// Set by a `tls=false` label, opting out of the default TLS configuration.
+    pub tls_disabled: bool,
+    /// Set when at least one `observability.*` option is declared.
+    pub observability: Option<TraefikedObservabilityConfig>,
+    /// Options of the load balancer service targeted by this router.
+    pub load_balancer: TraefikedLoadBalancerConfig,
+    /// Container-side ports declared by the `loadbalancer.server.port` label, each one targeted
+    /// through the host port it is published on. The first public port is targeted when empty.
+    pub target_ports: Vec<u16>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedLoadBalancerConfig {
+    pub scheme: Option<String>,
+    /// Server URL used verbatim instead of being derived from the base URL and port.
+    pub url: Option<Url>,
+    pub pass_host_header: Option<bool>,
+    pub health_check: Option<TraefikedHealthCheckConfig>,
+    pub sticky: Option<TraefikedStickyConfig>,
+    /// Name of the servers transport to use, possibly provider-qualified (e.g. `my-transport@file`)
+    pub servers_transport: Option<String>,
+    /// Duration between flushes of the buffered response (e.g. `1ms`), passed through as-is
+    pub flush_interval: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedStickyConfig {
+    pub cookie: TraefikedStickyCookieConfig,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedStickyCookieConfig {
+    pub name: Option<String>,
+    pub secure: Option<bool>,
+    pub http_only: Option<bool>,
+    pub same_site: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedHealthCheckConfig {
+    pub path: String,
+    /// Duration, as accepted by Traefik (e.g. `10s`)
+    pub interval: Option<String>,
+    /// Duration, as accepted by Traefik (e.g. `3s`)
+    pub timeout: Option<String>,
+    pub port: Option<u16>,
+    pub scheme: Option<String>,
+}
+
+/// Per-router toggles of the Traefik v3 observability features.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedObservabilityConfig {
+    pub access_logs: Option<bool>,
+    pub metrics: Option<bool>,
+    pub tracing: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedRouterTlsConfig {
+    pub cert_resolver: Option<String>,
+    /// Name of the TLS options to use, provider suffix included, e.g. `modern@file`.
+    pub options: Option<String>,
+    pub domains: Vec<TraefikedTlsDomain>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedTlsDomain {
+    pub main: String,
+    pub sans: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TraefikedContainerMultiPortConfig {
     pub config: TraefikedContainerSinglePortConfig,
     pub service_name: String,
-    pub target_port: u16,
+    /// Container-side ports of the service, each one targeted through the host port it is
+    /// published on.
+    pub target_ports: Vec<u16>,
 }
 
 impl TryFrom<ContainerSummary> for TraefikedContainer {
-    type Error = anyhow::Error;
+    type Error = ContainerConversionError;
 
     fn try_from(value: ContainerSummary) -> Result<Self, Self::Error> {
-        let name = value
-            .names
-            .ok_or(anyhow!("No container name found"))?
-            .first()
-            .expect("Container should have a name")
-            .clone()[1..] // Remove leading / in container name
-            .to_owned();
-
-        let public_ports = value
-            .ports
-            .ok_or(anyhow!("No ports specified"))?
+        let diagnostics = Diagnostics::default();
+        let container = TraefikedContainer::from_summary(
+            value,
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &diagnostics,
+        )?;
+
+        for error in diagnostics.into_errors() {
+            tracing::warn!("Container '{}': {}", container.name, error);
+        }
+
+        Ok(container)
+    }
+}
+
+impl TraefikedContainer {
+    /// Converts a container summary, generating a router from its `traefik-provider.hostname`
+    /// label or the `default_rule` template when the container has no rule label.
+    ///
+    /// `{{ container_name }}` and `{{ domain }}` placeholders are substituted in rules
+    /// and middleware options, an unknown placeholder failing the conversion.
+    pub(crate) fn from_summary(
+        value: ContainerSummary,
+        options: &DockerOptions,
+        regexes: &LabelRegexes,
+        diagnostics: &Diagnostics,
+    ) -> Result<Self, ContainerConversionError> {
+        let no_labels = HashMap::new();
+        if !is_traefik_enabled(
+            value.labels.as_ref().unwrap_or(&no_labels),
+            regexes,
+            options.exposed_by_default,
+        ) {
+            return Err(ContainerConversionError::TraefikDisabled);
+        }
+        let default_router = generated_router(&value, options, regexes);
+
+        let (name, aliases) = container_names(value.names.as_deref())?;
+        let base_url = base_url_override(value.labels.as_ref())?;
+        let compose_label = |key: &str| {
+            value
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.get(key))
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        };
+        let (service_name, compose_project) = match compose_label(COMPOSE_SERVICE_LABEL) {
+            Some(compose_service) if options.use_compose_service_name => (
+                sanitize_name(&compose_service)?,
+                compose_label(COMPOSE_PROJECT_LABEL),
+            ),
+            _ => (sanitize_name(&name)?, None),
+        };
+        let compose_service = compose_label(COMPOSE_SERVICE_LABEL).map(|service| ComposeService {
+            project: compose_label(COMPOSE_PROJECT_LABEL).unwrap_or_default(),
+            service,
+        });
+
+        // Containers without ports are checked once their labels are parsed, as services with
+        // an explicit URL do not need any
+        let has_ports = value.ports.is_some();
+        let ports = value.ports.unwrap_or_default();
+        if ports
             .iter()
-            .filter_map(|p| p.public_port)
+            .any(|p| p.private_port == 0 || p.public_port == Some(0))
+        {
+            return Err(ContainerConversionError::ZeroPort);
+        }
+        let ports = ports.iter().map(PortMapping::from).collect();
+
+        let networks = value
+            .network_settings
+            .and_then(|settings| settings.networks)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(network, endpoint)| {
+                endpoint
+                    .ip_address
+                    .filter(|ip| !ip.is_empty())
+                    .map(|ip| (network, ip))
+            })
             .collect();
 
-        let config = value
-            .labels
-            .as_ref()
-            .and_then(extract_traefik_config)
-            .ok_or(anyhow!("Could not find a traefik rule label"))?;
+        let variables = TemplateVariables {
+            container_name: &name,
+            domain: options.domain.as_deref(),
+        };
+        let labels = substitute_labels(
+            regexes.own_labels(value.labels.unwrap_or_default()),
+            regexes,
+            &variables,
+        )?;
+        // The default router is named after the services of the container
+        let default_router = default_router
+            .map(|(origin, (_, rule))| {
+                substitute(&rule, &variables)
+                    .map(|rule| (service_name.clone(), rule))
+                    .map_err(|reason| ContainerConversionError::InvalidPlaceholder {
+                        label: origin.to_owned(),
+                        reason,
+                    })
+            })
+            .transpose()?;
+
+        report_unrecognized_labels(&labels, regexes, diagnostics);
+        let tcp = extract_tcp_config(&labels, regexes, diagnostics)?;
+        let udp = extract_udp_config(&labels, regexes, diagnostics)?;
+        let weighted_services = extract_weighted_services(&labels, regexes, diagnostics);
+        let config = match extract_traefik_config(
+            &labels,
+            regexes,
+            options.exposed_by_default,
+            default_router,
+            diagnostics,
+        )? {
+            Some(config) => config,
+            None if !tcp.is_empty() || !udp.is_empty() || !weighted_services.is_empty() => {
+                TraefikedContainerConfig::MultiplePorts(vec![])
+            }
+            None => return Err(ContainerConversionError::NoTraefikLabels),
+        };
+        let needs_ports = !config.targets_explicit_urls() || !tcp.is_empty() || !udp.is_empty();
+        if !has_ports && !options.allow_empty_services && needs_ports {
+            return Err(ContainerConversionError::NoPorts);
+        }
+
+        let middlewares = extract_middlewares(&labels, regexes, diagnostics);
+        let servers_transports = extract_servers_transports(&labels, regexes, diagnostics);
+        let network = docker_network(&labels, regexes);
 
         Ok(TraefikedContainer {
             name,
-            public_ports,
+            aliases,
+            service_name,
+            compose_project,
+            compose_service,
+            ports,
+            networks,
+            network,
+            base_url,
             config,
+            middlewares,
+            servers_transports,
+            weighted_services,
+            tcp,
+            udp,
         })
     }
 }
 
-pub(crate) fn extract_traefik_config(
-    labels: &HashMap<String, String>,
-) -> Option<TraefikedContainerConfig> {
-    let routers: Vec<(String, String)> = labels
+/// Canonical name and sorted aliases of a container, without the leading `/` added by Docker.
+///
+/// Docker lists names in no stable order, so the canonical name is the one with the fewest `/`
+/// segments (link aliases look like `other/alias`), then the shortest, then the first alphabetically.
+fn container_names(
+    names: Option<&[String]>,
+) -> Result<(String, Vec<String>), ContainerConversionError> {
+    let aliases: Vec<String> = names
+        .unwrap_or_default()
         .iter()
-        .filter_map(|(label_key, label_value)| {
-            ROUTERS_LABEL_REGEX
-                .captures(label_key)
-                .and_then(|captures| captures.get(1))
-                .map(|router_name| (router_name.as_str().to_owned(), label_value.clone()))
+        .map(|name| name.strip_prefix('/').unwrap_or(name))
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+        .sorted()
+        .dedup()
+        .collect();
+
+    let name = aliases
+        .iter()
+        .min_by_key(|name| (name.matches('/').count(), name.len(), name.as_str()))
+        .ok_or(ContainerConversionError::MissingName)?
+        .clone();
+
+    Ok((name, aliases))
+}
+
+/// Turns a container name into a router or service name: characters other than ASCII
+/// alphanumerics, `-` and `_` are replaced by `-`, repeated `-` are collapsed and
+/// the result is truncated to [`MAX_NAME_LENGTH`] characters.
+///
+/// Distinct names can end up identical, such collisions being settled by the
+/// [`CollisionPolicy`](crate::dynamic_configuration::CollisionPolicy) of the builder.
+pub(crate) fn sanitize_name(name: &str) -> Result<String, ContainerConversionError> {
+    let sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
         })
-        .sorted_by(|a, b| Ord::cmp(&a.0, &b.0))
+        .dedup_by(|a, b| *a == '-' && *b == '-')
+        .take(MAX_NAME_LENGTH)
         .collect();
+    let sanitized = sanitized.trim_matches('-');
 
-    if routers.is_empty() {
+    if sanitized.is_empty() {
+        return Err(ContainerConversionError::InvalidName {
+            name: name.to_owned(),
+        });
+    }
+
+    Ok(sanitized.to_owned())
+}
+
+impl TraefikedContainer {
+    /// Prefixes `service_name` with the compose project, along with the router named after it,
+    /// returning whether the container has a compose project.
+    pub(crate) fn qualify_with_compose_project(&mut self) -> bool {
+        let Some(compose_project) = &self.compose_project else {
+            return false;
+        };
+        let Ok(qualified_name) =
+            sanitize_name(&format!("{}-{}", compose_project, self.service_name))
+        else {
+            return false;
+        };
+
+        if let TraefikedContainerConfig::SinglePort(config) = &mut self.config {
+            if config.router_name == self.service_name {
+                config.router_name = qualified_name.clone();
+            }
+        }
+        self.service_name = qualified_name;
+
+        true
+    }
+
+    /// Distinct host ports the container is published on, in Docker order.
+    ///
+    /// Ports published over several protocols or host IPs are listed once.
+    pub fn public_ports(&self) -> Vec<u16> {
+        self.ports
+            .iter()
+            .filter_map(|port| port.public_port)
+            .unique()
+            .collect()
+    }
+
+    /// Distinct container-side ports, targeted instead of public ports in container IP mode.
+    pub fn private_ports(&self) -> Vec<u16> {
+        self.ports
+            .iter()
+            .map(|port| port.private_port)
+            .unique()
+            .collect()
+    }
+
+    /// Host port the container-side `private_port` is first published on.
+    pub fn published_port(&self, private_port: u16) -> Option<u16> {
+        self.ports
+            .iter()
+            .filter(|port| port.private_port == private_port)
+            .find_map(|port| port.public_port)
+    }
+
+    /// Distinct hostnames matched by the `Host` and `HostRegexp` matchers of the HTTP router
+    /// rules, in router order.
+    pub fn hostnames(&self) -> Vec<String> {
+        let rules: Vec<&str> = match &self.config {
+            TraefikedContainerConfig::SinglePort(config) => vec![&config.rule],
+            TraefikedContainerConfig::MultiplePorts(configs) => {
+                configs.iter().map(|c| c.config.rule.as_str()).collect()
+            }
+        };
+
+        rules
+            .into_iter()
+            .flat_map(rule_hostnames)
+            .unique()
+            .collect()
+    }
+
+    /// IP address of the container on the network named by its `traefik.docker.network` label,
+    /// or on its only network when the label is missing.
+    pub fn ip_address(&self) -> anyhow::Result<&str> {
+        match &self.network {
+            Some(network) => self.networks.get(network).ok_or(anyhow!(
+                "Container '{}' has no IP address on network '{}'",
+                self.name,
+                network
+            )),
+            None if self.networks.len() == 1 => Ok(self.networks.values().next().unwrap()),
+            None if self.networks.is_empty() => Err(anyhow!(
+                "Container '{}' has no IP address on any network",
+                self.name
+            )),
+            None => Err(anyhow!(
+                "Container '{}' is attached to several networks, set the '{}' label to choose one",
+                self.name,
+                DOCKER_NETWORK_LABEL
+            )),
+        }
+        .map(String::as_str)
+    }
+}
+
+impl TraefikedContainer {
+    /// Builds a container by hand rather than from a Docker container summary, e.g. in tests
+    /// or when embedding the provider.
+    pub fn builder(name: impl Into<String>) -> TraefikedContainerBuilder {
+        TraefikedContainerBuilder {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Builder of a [`TraefikedContainer`], routers being paired with services as they would be
+/// from labels.
+#[derive(Clone, Debug, Default)]
+pub struct TraefikedContainerBuilder {
+    name: String,
+    ports: Vec<PortMapping>,
+    networks: BTreeMap<String, String>,
+    network: Option<String>,
+    base_url: Option<Url>,
+    routers: Vec<TraefikedContainerSinglePortConfig>,
+    router_services: HashMap<String, String>,
+    services: BTreeMap<String, Vec<u16>>,
+    middlewares: BTreeMap<String, MiddlewareConfiguration>,
+    servers_transports: BTreeMap<String, ServersTransportConfiguration>,
+    weighted_services: BTreeMap<String, Vec<TraefikedWeightedService>>,
+    tcp: Vec<TraefikedContainerTcpConfig>,
+    udp: Vec<TraefikedContainerUdpConfig>,
+}
+
+impl TraefikedContainerBuilder {
+    pub fn port(mut self, port: PortMapping) -> Self {
+        self.ports.push(port);
+        self
+    }
+
+    /// TCP port published on the same host port.
+    pub fn public_port(self, port: u16) -> Self {
+        self.published_port(port, port)
+    }
+
+    /// Container-side TCP `private_port` published on the host `public_port`.
+    pub fn published_port(self, private_port: u16, public_port: u16) -> Self {
+        self.port(PortMapping {
+            private_port,
+            public_port: Some(public_port),
+            host_ip: None,
+            protocol: PortProtocol::Tcp,
+        })
+    }
+
+    /// Container-side TCP port, only reachable in container IP mode.
+    pub fn private_port(self, port: u16) -> Self {
+        self.port(PortMapping {
+            private_port: port,
+            public_port: None,
+            host_ip: None,
+            protocol: PortProtocol::Tcp,
+        })
+    }
+
+    /// IP address of the container on `network`.
+    pub fn network(mut self, network: impl Into<String>, ip_address: impl Into<String>) -> Self {
+        self.networks.insert(network.into(), ip_address.into());
+        self
+    }
+
+    /// Network to reach the container on, as set by the `traefik.docker.network` label.
+    pub fn docker_network(mut self, network: impl Into<String>) -> Self {
+        self.network = Some(network.into());
+        self
+    }
+
+    /// Base URL to reach the container through, as set by the `traefik-provider.base-url` label.
+    pub fn base_url(mut self, base_url: Url) -> Self {
+        self.base_url = Some(base_url);
+        self
+    }
+
+    pub fn router(self, name: impl Into<String>, rule: impl Into<String>) -> Self {
+        self.router_config(TraefikedContainerSinglePortConfig {
+            router_name: name.into(),
+            rule: rule.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Router with all its options, including the load balancer options of its service.
+    pub fn router_config(mut self, config: TraefikedContainerSinglePortConfig) -> Self {
+        self.routers.push(config);
+        self
+    }
+
+    /// Targets `service_name` from `router_name`, as the `service` router label does.
+    pub fn router_service(
+        mut self,
+        router_name: impl Into<String>,
+        service_name: impl Into<String>,
+    ) -> Self {
+        self.router_services
+            .insert(router_name.into(), service_name.into());
+        self
+    }
+
+    /// Service targeting the container-side `port`, in addition to the ports previously given
+    /// for the same service.
+    pub fn service(mut self, name: impl Into<String>, port: u16) -> Self {
+        self.services.entry(name.into()).or_default().push(port);
+        self
+    }
+
+    pub fn middleware(
+        mut self,
+        name: impl Into<String>,
+        middleware: impl Into<MiddlewareConfiguration>,
+    ) -> Self {
+        self.middlewares.insert(name.into(), middleware.into());
+        self
+    }
+
+    pub fn servers_transport(
+        mut self,
+        name: impl Into<String>,
+        servers_transport: ServersTransportConfiguration,
+    ) -> Self {
+        self.servers_transports
+            .insert(name.into(), servers_transport);
+        self
+    }
+
+    pub fn weighted_service(
+        mut self,
+        name: impl Into<String>,
+        services: Vec<TraefikedWeightedService>,
+    ) -> Self {
+        self.weighted_services.insert(name.into(), services);
+        self
+    }
+
+    pub fn tcp(mut self, config: TraefikedContainerTcpConfig) -> Self {
+        self.tcp.push(config);
+        self
+    }
+
+    pub fn udp(mut self, config: TraefikedContainerUdpConfig) -> Self {
+        self.udp.push(config);
+        self
+    }
+
+    /// Builds the container, pairing routers with services like [`parse_traefik_labels`] does:
+    /// a single router targets its explicit or only service, several routers need as many
+    /// services as routers without an explicit one.
+    pub fn build(self) -> Result<TraefikedContainer, ContainerConversionError> {
+        let service_name = sanitize_name(&self.name)?;
+        let mut routers = self.routers;
+        routers.sort_by(|a, b| a.router_name.cmp(&b.router_name));
+
+        let config = match routers.len() {
+            0 if self.tcp.is_empty()
+                && self.udp.is_empty()
+                && self.weighted_services.is_empty() =>
+            {
+                return Err(ContainerConversionError::NoTraefikLabels)
+            }
+            0 => TraefikedContainerConfig::MultiplePorts(vec![]),
+            1 if self.services.len() <= 1
+                && !self
+                    .router_services
+                    .get(&routers[0].router_name)
+                    .is_some_and(|service_name| is_provider_qualified(service_name)) =>
+            {
+                let mut config = routers.remove(0);
+                let router_service = self
+                    .router_services
+                    .get(&config.router_name)
+                    .or(self.services.keys().next());
+                if config.target_ports.is_empty() {
+                    if let Some(ports) = router_service.and_then(|name| self.services.get(name)) {
+                        config.target_ports = ports.clone();
+                    }
+                }
+                TraefikedContainerConfig::SinglePort(config)
+            }
+            _ => {
+                let router_names: Vec<String> = routers
+                    .iter()
+                    .map(|config| config.router_name.clone())
+                    .collect();
+                let explicit_services: HashMap<String, &String> = self
+                    .router_services
+                    .iter()
+                    .map(|(router_name, service_name)| (router_name.clone(), service_name))
+                    .collect();
+                let service_names: Vec<String> = self.services.keys().cloned().collect();
+                let paired_services =
+                    pair_routers_with_services(&router_names, &explicit_services, &service_names)?;
+
+                TraefikedContainerConfig::MultiplePorts(
+                    routers
+                        .into_iter()
+                        .zip(paired_services)
+                        .map(|(config, service_name)| TraefikedContainerMultiPortConfig {
+                            target_ports: self
+                                .services
+                                .get(&service_name)
+                                .cloned()
+                                .unwrap_or_default(),
+                            config,
+                            service_name,
+                        })
+                        .collect(),
+                )
+            }
+        };
+
+        Ok(TraefikedContainer {
+            name: self.name.clone(),
+            aliases: vec![self.name],
+            service_name,
+            compose_project: None,
+            compose_service: None,
+            ports: self.ports,
+            networks: self.networks,
+            network: self.network,
+            base_url: self.base_url,
+            config,
+            middlewares: self.middlewares,
+            servers_transports: self.servers_transports,
+            weighted_services: self.weighted_services,
+            tcp: self.tcp,
+            udp: self.udp,
+        })
+    }
+}
+
+/// Router generated for a container without a rule label, along with what it was generated from.
+///
+/// The `traefik-provider.hostname` label takes precedence over the `default_rule` template.
+pub(crate) fn generated_router(
+    container: &ContainerSummary,
+    options: &DockerOptions,
+    regexes: &LabelRegexes,
+) -> Option<(&'static str, (String, String))> {
+    hostname_router(container)
+        .map(|router| (HOSTNAME_LABEL, router))
+        .or_else(|| {
+            options
+                .default_rule
+                .as_deref()
+                .and_then(|template| default_router(container, regexes, template))
+                .map(|router| ("default rule", router))
+        })
+}
+
+/// Router named after the (sanitized) container with a ``Host(`<hostname>.{{ domain }}`)`` rule,
+/// `<hostname>` being the value of its `traefik-provider.hostname` label.
+fn hostname_router(container: &ContainerSummary) -> Option<(String, String)> {
+    let hostname = container
+        .labels
+        .as_ref()?
+        .iter()
+        .find(|(label_key, _)| label_key.eq_ignore_ascii_case(HOSTNAME_LABEL))
+        .map(|(_, value)| value.trim())
+        .filter(|hostname| !hostname.is_empty())?;
+    let (name, _) = container_names(container.names.as_deref()).ok()?;
+
+    Some((
+        sanitize_name(&name).ok()?,
+        format!("Host(`{}.{{{{ domain }}}}`)", hostname),
+    ))
+}
+
+/// Router named after the (sanitized) container with a rule rendered from `template`, where `{name}`
+/// is replaced by the container name.
+///
+/// Only containers with a public port and without TCP/UDP routers get a default router.
+pub(crate) fn default_router(
+    container: &ContainerSummary,
+    regexes: &LabelRegexes,
+    template: &str,
+) -> Option<(String, String)> {
+    let (name, _) = container_names(container.names.as_deref()).ok()?;
+    let has_public_port = container
+        .ports
+        .as_ref()
+        .is_some_and(|ports| ports.iter().any(|p| p.public_port.is_some()));
+    let has_other_routers = container
+        .labels
+        .as_ref()
+        .is_some_and(|labels| has_tcp_routers(labels, regexes) || has_udp_routers(labels, regexes));
+
+    if !has_public_port || has_other_routers {
         return None;
     }
 
-    if routers.len() == 1 {
-        let (router_name, rule) = routers.first().cloned().expect("Should have an router");
+    Some((
+        sanitize_name(&name).ok()?,
+        template.replace("{name}", &name),
+    ))
+}
 
-        return Some(TraefikedContainerConfig::SinglePort(
-            TraefikedContainerSinglePortConfig { router_name, rule },
-        ));
+/// Whether a container should be exposed, based on its `<prefix>.enable` label.
+///
+/// Containers without the label (or with an unparseable value) fall back to `exposed_by_default`.
+pub(crate) fn is_traefik_enabled(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    exposed_by_default: bool,
+) -> bool {
+    let enable_label = regexes.label(ENABLE_LABEL);
+
+    labels
+        .iter()
+        .find(|(label_key, _)| label_key.eq_ignore_ascii_case(&enable_label))
+        .and_then(|(_, value)| parse_bool(value))
+        .unwrap_or(exposed_by_default)
+}
+
+/// Base URL declared by the `traefik-provider.base-url` label, which must be a URL with a host.
+fn base_url_override(
+    labels: Option<&HashMap<String, String>>,
+) -> Result<Option<Url>, ContainerConversionError> {
+    let Some(value) = labels
+        .into_iter()
+        .flatten()
+        .find(|(label_key, _)| label_key.eq_ignore_ascii_case(BASE_URL_LABEL))
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    let invalid = |reason: String| ContainerConversionError::InvalidBaseUrl {
+        value: value.to_owned(),
+        reason,
+    };
+    let url = Url::parse(value).map_err(|e| invalid(e.to_string()))?;
+    if url.host_str().is_none() {
+        return Err(invalid("no host".to_owned()));
     }
 
-    let services: Vec<(String, u16)> = labels
+    Ok(Some(url))
+}
+
+/// Network declared by the `<prefix>.docker.network` label.
+fn docker_network(labels: &HashMap<String, String>, regexes: &LabelRegexes) -> Option<String> {
+    let network_label = regexes.label(DOCKER_NETWORK_LABEL);
+
+    labels
+        .iter()
+        .find(|(label_key, _)| label_key.eq_ignore_ascii_case(&network_label))
+        .map(|(_, value)| value.trim().to_owned())
+        .filter(|network| !network.is_empty())
+}
+
+/// Port declared by the legacy `<prefix>.port` label, logging a deprecation warning when used.
+fn legacy_port(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> Option<u16> {
+    let legacy_port_label = regexes.label(LEGACY_PORT_LABEL);
+    let (label_key, value) = labels
         .iter()
+        .find(|(label_key, _)| label_key.eq_ignore_ascii_case(&legacy_port_label))?;
+
+    match parse_port(label_key, value) {
+        Ok(port) => {
+            tracing::warn!(
+                "Label '{}' is deprecated, use 'traefik.http.services.<name>.loadbalancer.server.port' instead",
+                label_key
+            );
+            Some(port)
+        }
+        Err(error) => {
+            diagnostics.report(label_key, error);
+            None
+        }
+    }
+}
+
+/// Parses the comma-separated list of container ports of `label`, e.g. `8080,8081`.
+fn parse_ports(label: &str, value: &str) -> Result<Vec<u16>, ContainerConversionError> {
+    value
+        .split(',')
+        .map(|port| parse_port(label, port))
+        .collect()
+}
+
+/// Parses the container port of `label`, port 0 being rejected as nothing can listen on it.
+pub(crate) fn parse_port(label: &str, value: &str) -> Result<u16, ContainerConversionError> {
+    match value.trim().parse::<u16>() {
+        Ok(port) if port != 0 => Ok(port),
+        _ => Err(ContainerConversionError::InvalidPortValue {
+            label: label.to_owned(),
+            value: value.to_owned(),
+        }),
+    }
+}
+
+/// Captures the name matched by `regex` in each label key, along with the label value.
+pub(crate) fn capture_labels<'a>(
+    labels: impl IntoIterator<Item = (&'a String, &'a String)>,
+    regex: &Regex,
+) -> HashMap<String, &'a String> {
+    labels
+        .into_iter()
         .filter_map(|(label_key, label_value)| {
-            SERVICE_LABEL_REGEX
+            regex
                 .captures(label_key)
                 .and_then(|captures| captures.get(1))
-                .map(|service_name| service_name.as_str().to_owned())
-                .and_then(|service_name| {
-                    label_value
-                        .parse::<u16>()
-                        .ok()
-                        .map(|port| (service_name, port))
-                })
+                .map(|name| (name.as_str().to_owned(), label_value))
         })
-        .sorted_by(|a, b| Ord::cmp(&a.0, &b.0))
-        .collect();
+        .collect()
+}
 
-    if services.len() != routers.len() {
-        return None;
+/// Captures the name (group 1) and lowercased option (group 2) matched by `regex` in each label key,
+/// grouping the label values by name. A missing option group is captured as an empty option.
+fn capture_grouped_labels<'a>(
+    labels: impl IntoIterator<Item = (&'a String, &'a String)>,
+    regex: &Regex,
+) -> HashMap<String, BTreeMap<String, &'a String>> {
+    let mut grouped: HashMap<String, BTreeMap<String, &'a String>> = HashMap::new();
+
+    for (label_key, label_value) in labels {
+        if let Some(captures) = regex.captures(label_key) {
+            grouped.entry(captures[1].to_owned()).or_default().insert(
+                captures
+                    .get(2)
+                    .map_or(String::new(), |option| option.as_str().to_ascii_lowercase()),
+                label_value,
+            );
+        }
     }
 
-    let multiport_configs = routers
-        .into_iter()
-        .zip(services)
-        .map(|((router_name, rule), (service_name, target_port))| {
-            TraefikedContainerMultiPortConfig {
-                config: TraefikedContainerSinglePortConfig { router_name, rule },
-                service_name,
-                target_port,
+    grouped
+}
+
+/// Splits a comma-separated label value, preserving order and ignoring empty entries.
+pub(crate) fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    if value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn parse_scheme(label: &str, value: &str, diagnostics: &Diagnostics) -> Option<String> {
+    let scheme = value.trim().to_ascii_lowercase();
+
+    match scheme.as_str() {
+        "http" | "https" | "h2c" => Some(scheme),
+        _ => {
+            diagnostics.report(
+                label,
+                format!("expected one of http, https or h2c, got '{}'", value),
+            );
+            None
+        }
+    }
+}
+
+fn parse_bool_option(label: &str, value: &str, diagnostics: &Diagnostics) -> Option<bool> {
+    let parsed = parse_bool(value.trim());
+
+    if parsed.is_none() {
+        diagnostics.report(label, format!("expected true or false, got '{}'", value));
+    }
+
+    parsed
+}
+
+fn parse_health_check(
+    service_name: &str,
+    options: BTreeMap<String, &String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> anyhow::Result<TraefikedHealthCheckConfig> {
+    let mut health_check = TraefikedHealthCheckConfig::default();
+    let mut path = None;
+
+    for (option, value) in options {
+        match option.as_str() {
+            "path" => path = Some(value.clone()),
+            "interval" => health_check.interval = Some(value.clone()),
+            "timeout" => health_check.timeout = Some(value.clone()),
+            "port" => {
+                health_check.port = Some(parse_port(
+                    &regexes.service_label(service_name, "healthcheck.port"),
+                    value,
+                )?)
             }
-        })
-        .collect();
+            "scheme" => health_check.scheme = Some(value.clone()),
+            _ => diagnostics.report(
+                regexes.service_label(service_name, &format!("healthcheck.{}", option)),
+                "unsupported health check option",
+            ),
+        }
+    }
+
+    health_check.path = path.ok_or(anyhow!(
+        "Health check of service '{}' requires a 'loadbalancer.healthcheck.path' label",
+        service_name
+    ))?;
+
+    Ok(health_check)
+}
+
+/// Sticky sessions are enabled by `sticky.cookie=true` or implied by any cookie option.
+fn parse_sticky(
+    service_name: &str,
+    options: BTreeMap<String, &String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> Option<TraefikedStickyConfig> {
+    let mut cookie = TraefikedStickyCookieConfig::default();
+    let mut enabled = None;
+
+    for (option, value) in options {
+        let label = match option.as_str() {
+            "" => regexes.service_label(service_name, "sticky.cookie"),
+            option => regexes.service_label(service_name, &format!("sticky.cookie.{}", option)),
+        };
+
+        match option.as_str() {
+            "" => enabled = parse_bool_option(&label, value, diagnostics),
+            "name" => cookie.name = Some(value.clone()),
+            "secure" => cookie.secure = parse_bool_option(&label, value, diagnostics),
+            "httponly" => cookie.http_only = parse_bool_option(&label, value, diagnostics),
+            "samesite" => cookie.same_site = Some(value.clone()),
+            _ => diagnostics.report(label, "unsupported sticky cookie option"),
+        }
+    }
+
+    let has_options = cookie != TraefikedStickyCookieConfig::default();
+
+    enabled
+        .unwrap_or(has_options)
+        .then_some(TraefikedStickyConfig { cookie })
+}
+
+fn parse_priority(label: &str, value: &str, diagnostics: &Diagnostics) -> Option<i64> {
+    match value.trim().parse::<i64>() {
+        Ok(priority) => Some(priority),
+        Err(e) => {
+            diagnostics.report(label, format!("invalid priority '{}': {}", value, e));
+            None
+        }
+    }
+}
+
+fn parse_observability(
+    router_name: &str,
+    options: &BTreeMap<String, &String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> Option<TraefikedObservabilityConfig> {
+    let mut observability = TraefikedObservabilityConfig::default();
+
+    for (option, value) in options {
+        let label = regexes.router_label(router_name, &format!("observability.{}", option));
 
-    Some(TraefikedContainerConfig::MultiplePorts(multiport_configs))
+        match option.as_str() {
+            "accesslogs" => {
+                observability.access_logs = parse_bool_option(&label, value, diagnostics)
+            }
+            "metrics" => observability.metrics = parse_bool_option(&label, value, diagnostics),
+            "tracing" => observability.tracing = parse_bool_option(&label, value, diagnostics),
+            _ => diagnostics.report(label, "unsupported observability option"),
+        }
+    }
+
+    (observability != TraefikedObservabilityConfig::default()).then_some(observability)
+}
+
+/// Rule syntaxes accepted by Traefik v3.
+const RULE_SYNTAXES: &[&str] = &["v2", "v3"];
+
+fn parse_rule_syntax(label: &str, value: &str, diagnostics: &Diagnostics) -> Option<String> {
+    let rule_syntax = value.trim().to_ascii_lowercase();
+
+    if !RULE_SYNTAXES.contains(&rule_syntax.as_str()) {
+        diagnostics.report(
+            label,
+            format!(
+                "unsupported rule syntax '{}', expected one of {}",
+                value,
+                RULE_SYNTAXES.join(", ")
+            ),
+        );
+        return None;
+    }
+
+    Some(rule_syntax)
+}
+
+/// TLS is enabled by `tls=true` or implied by any TLS option, unless explicitly set to `tls=false`.
+fn router_tls_config(
+    tls: Option<bool>,
+    cert_resolver: Option<&str>,
+    options: Option<&str>,
+    domains: Vec<TraefikedTlsDomain>,
+) -> Option<TraefikedRouterTlsConfig> {
+    let enabled = match tls {
+        Some(enabled) => enabled,
+        None => cert_resolver.is_some() || options.is_some() || !domains.is_empty(),
+    };
+
+    enabled.then(|| TraefikedRouterTlsConfig {
+        cert_resolver: cert_resolver.map(str::to_owned),
+        options: options.map(str::to_owned),
+        domains,
+    })
+}
+
+/// Groups `tls.domains[n].main` / `tls.domains[n].sans` labels by router, ordered by index.
+///
+/// Labels with a malformed index or an unknown option are skipped and reported.
+fn extract_tls_domains<'a>(
+    labels: impl IntoIterator<Item = (&'a String, &'a String)>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> HashMap<String, Vec<TraefikedTlsDomain>> {
+    #[derive(Default)]
+    struct PartialTlsDomain {
+        main: Option<String>,
+        sans: Vec<String>,
+    }
+
+    let mut domains: HashMap<String, BTreeMap<usize, PartialTlsDomain>> = HashMap::new();
+
+    for (label_key, label_value) in labels {
+        let Some(captures) = regexes.router_tls_domain.captures(label_key) else {
+            continue;
+        };
+
+        let Ok(index) = captures[2].parse::<usize>() else {
+            diagnostics.report(
+                label_key,
+                format!("'{}' is not a valid domain index", &captures[2]),
+            );
+            continue;
+        };
+
+        let domain = domains
+            .entry(captures[1].to_owned())
+            .or_default()
+            .entry(index)
+            .or_default();
+
+        match captures[3].to_ascii_lowercase().as_str() {
+            "main" => domain.main = Some(label_value.clone()),
+            "sans" => domain.sans = parse_list(label_value),
+            option => diagnostics.report(label_key, format!("unknown domain option '{}'", option)),
+        }
+    }
+
+    domains
+        .into_iter()
+        .map(|(router_name, domains)| {
+            let domains = domains
+                .into_iter()
+                .filter_map(|(index, domain)| match domain.main {
+                    Some(main) => Some(TraefikedTlsDomain {
+                        main,
+                        sans: domain.sans,
+                    }),
+                    None => {
+                        diagnostics.report(
+                            regexes.router_label(&router_name, &format!("tls.domains[{}]", index)),
+                            "missing 'main' domain",
+                        );
+                        None
+                    }
+                })
+                .collect();
+
+            (router_name, domains)
+        })
+        .collect()
+}
+
+/// Whether `name` references a router, service or middleware of another provider, e.g. `auth@file`.
+pub(crate) fn is_provider_qualified(name: &str) -> bool {
+    name.contains('@')
+}
+
+/// Pairs each router (in order) with a service name.
+///
+/// Routers with an explicit `service` label are paired with it, provider-qualified services
+/// (e.g. `auth@file`) being defined elsewhere. The others target the only service when there is
+/// a single one, or are paired in alphabetical order with the services no router explicitly
+/// references.
+pub(crate) fn pair_routers_with_services(
+    router_names: &[String],
+    explicit_services: &HashMap<String, &String>,
+    service_names: &[String],
+) -> anyhow::Result<Vec<String>> {
+    let implicit_routers_count = router_names
+        .iter()
+        .filter(|router_name| !explicit_services.contains_key(*router_name))
+        .count();
+    let mut unreferenced_services = service_names
+        .iter()
+        .filter(|service_name| !explicit_services.values().any(|s| s == service_name));
+
+    let sole_service = match service_names {
+        [service_name] => Some(service_name),
+        _ => None,
+    };
+
+    let unreferenced_services_count = unreferenced_services.clone().count();
+    if sole_service.is_none()
+        && implicit_routers_count > 0
+        && unreferenced_services_count != implicit_routers_count
+    {
+        return Err(anyhow!(
+            "{} router(s) without a 'service' label but {} unreferenced service(s)",
+            implicit_routers_count,
+            unreferenced_services_count
+        ));
+    }
+
+    router_names
+        .iter()
+        .map(|router_name| match explicit_services.get(router_name) {
+            Some(service_name)
+                if service_names.contains(service_name) || is_provider_qualified(service_name) =>
+            {
+                Ok(service_name.to_string())
+            }
+            Some(service_name) => Err(anyhow!(
+                "Router '{}' references service '{}' which has no 'loadbalancer.server.port' label",
+                router_name,
+                service_name
+            )),
+            None => Ok(sole_service
+                .or_else(|| unreferenced_services.next())
+                .expect("Should have as many unreferenced services as implicit routers")
+                .clone()),
+        })
+        .collect()
+}
+
+/// Result of [`parse_traefik_labels`].
+#[derive(Clone, Debug)]
+pub struct ParseOutcome {
+    /// HTTP routers and services declared by the labels, `None` when there is no router.
+    pub config: Option<TraefikedContainerConfig>,
+    /// Labels that were ignored because they could not be used.
+    pub warnings: Vec<LabelParseError>,
+}
+
+/// Parses the `traefik.http.*` routers and services from a container's labels, wherever
+/// they come from (Docker API, compose file...).
+///
+/// Labels that cannot be used are skipped and reported in [`ParseOutcome::warnings`], while
+/// labels that cannot be combined (e.g. routers that cannot be paired with services) fail.
+///
+/// A single router makes a [`TraefikedContainerConfig::SinglePort`] config:
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use traefik_docker_http_provider_server::{parse_traefik_labels, TraefikedContainerConfig};
+/// let labels = HashMap::from([
+///     ("traefik.http.routers.app.rule".to_owned(), "Host(`app.local`)".to_owned()),
+///     ("traefik.http.routers.app.priority".to_owned(), "high".to_owned()),
+/// ]);
+///
+/// let outcome = parse_traefik_labels(&labels)?;
+///
+/// let Some(TraefikedContainerConfig::SinglePort(config)) = outcome.config else {
+///     panic!("Should be a single port config");
+/// };
+/// assert_eq!(config.rule, "Host(`app.local`)");
+/// assert_eq!(outcome.warnings[0].label, "traefik.http.routers.app.priority");
+/// # Ok::<(), traefik_docker_http_provider_server::error::ContainerConversionError>(())
+/// ```
+///
+/// Several routers make a [`TraefikedContainerConfig::MultiplePorts`] config, each router
+/// targeting a service with a `loadbalancer.server.port` label:
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use traefik_docker_http_provider_server::{parse_traefik_labels, TraefikedContainerConfig};
+/// let labels: HashMap<String, String> = [
+///     ("traefik.http.routers.app.rule", "Host(`app.local`)"),
+///     ("traefik.http.routers.app.service", "app"),
+///     ("traefik.http.services.app.loadbalancer.server.port", "8080"),
+///     ("traefik.http.routers.metrics.rule", "Host(`metrics.local`)"),
+///     ("traefik.http.routers.metrics.service", "metrics"),
+///     ("traefik.http.services.metrics.loadbalancer.server.port", "9100"),
+/// ]
+/// .into_iter()
+/// .map(|(key, value)| (key.to_owned(), value.to_owned()))
+/// .collect();
+///
+/// let outcome = parse_traefik_labels(&labels)?;
+///
+/// let Some(TraefikedContainerConfig::MultiplePorts(configs)) = outcome.config else {
+///     panic!("Should be a multiple ports config");
+/// };
+/// assert_eq!(configs.len(), 2);
+/// assert_eq!(configs[0].service_name, "app");
+/// assert_eq!(configs[0].target_ports, vec![8080]);
+/// assert!(outcome.warnings.is_empty());
+/// # Ok::<(), traefik_docker_http_provider_server::error::ContainerConversionError>(())
+/// ```
+pub fn parse_traefik_labels(
+    labels: &HashMap<String, String>,
+) -> Result<ParseOutcome, ContainerConversionError> {
+    let diagnostics = Diagnostics::default();
+    let regexes = LabelRegexes::default();
+    let labels = regexes.own_labels(labels.clone());
+    report_unrecognized_labels(&labels, &regexes, &diagnostics);
+    let config = extract_traefik_config(&labels, &regexes, true, None, &diagnostics)?;
+
+    Ok(ParseOutcome {
+        config,
+        warnings: diagnostics.into_errors(),
+    })
+}
+
+/// Reports the router and service labels that no supported option matches, e.g. a misspelled
+/// option or an empty router name, which would otherwise be silently ignored.
+fn report_unrecognized_labels(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) {
+    for label_key in labels.keys().sorted() {
+        let Some(captures) = regexes.router_or_service.captures(label_key) else {
+            continue;
+        };
+
+        let supported = match captures[1].to_ascii_lowercase().as_str() {
+            "http" => regexes.is_supported_http(label_key),
+            "tcp" => regexes.tcp.is_supported(label_key),
+            _ => regexes.udp.is_supported(label_key),
+        };
+        if supported {
+            continue;
+        }
+
+        let kind = match captures[2].to_ascii_lowercase().as_str() {
+            "routers" => "router",
+            _ => "service",
+        };
+        if captures[3].is_empty() {
+            diagnostics.report(label_key, format!("empty {} name", kind));
+        } else {
+            diagnostics.report(label_key, format!("unsupported {} option", kind));
+        }
+    }
+}
+
+/// Reports the declared HTTP services that no router targets, their options being ignored.
+fn report_unused_services(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    declared_services: &[String],
+    used_services: &[&str],
+    diagnostics: &Diagnostics,
+) {
+    for service_name in declared_services {
+        if !used_services.contains(&service_name.as_str()) {
+            diagnostics.report(
+                labels
+                    .keys()
+                    .filter(|label_key| {
+                        regexes
+                            .service_name
+                            .captures(label_key)
+                            .is_some_and(|captures| &captures[1] == service_name)
+                    })
+                    .min()
+                    .cloned()
+                    .unwrap_or_else(|| regexes.label(&format!("http.services.{}", service_name))),
+                "service is not used by any router",
+            );
+        }
+    }
+}
+
+/// Whether all the labels of `service_name` declare a weighted service.
+fn is_weighted_service(http_labels: &HttpLabels, service_name: &str) -> bool {
+    let regexes = http_labels.regexes;
+
+    http_labels
+        .capture_grouped(HttpOption::Weighted)
+        .contains_key(service_name)
+        && !http_labels
+            .matching(HttpOption::ServiceName)
+            .any(|(label_key, _)| {
+                regexes
+                    .service_name
+                    .captures(label_key)
+                    .is_some_and(|captures| &captures[1] == service_name)
+                    && !regexes.weighted_services.is_match(label_key)
+            })
+}
+
+pub(crate) fn extract_traefik_config(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    exposed_by_default: bool,
+    default_router: Option<(String, String)>,
+    diagnostics: &Diagnostics,
+) -> anyhow::Result<Option<TraefikedContainerConfig>> {
+    if !is_traefik_enabled(labels, regexes, exposed_by_default) {
+        return Ok(None);
+    }
+    let http_labels = HttpLabels::new(labels, regexes);
+
+    let mut routers: Vec<(String, String)> = http_labels
+        .matching(HttpOption::Rule)
+        .filter_map(|(label_key, label_value)| {
+            regexes
+                .routers
+                .captures(label_key)
+                .and_then(|captures| captures.get(1))
+                .map(|router_name| (router_name.as_str().to_owned(), label_value.clone()))
+        })
+        .sorted_by(|a, b| Ord::cmp(&a.0, &b.0))
+        .collect();
+
+    if routers.is_empty() {
+        match default_router {
+            Some(default_router) => routers.push(default_router),
+            None => return Ok(None),
+        }
+    }
+
+    // A rule Traefik cannot parse would make it reject the whole dynamic configuration
+    routers.retain(|(router_name, rule)| match validate_rule(rule) {
+        Ok(()) => true,
+        Err(e) => {
+            diagnostics.report(
+                regexes.router_label(router_name, "rule"),
+                format!("invalid rule '{}': {}", rule, e),
+            );
+            false
+        }
+    });
+
+    if routers.is_empty() {
+        return Err(anyhow!("No router has a valid rule"));
+    }
+
+    let entrypoints = http_labels.capture(HttpOption::Entrypoints);
+    let middlewares = http_labels.capture(HttpOption::Middlewares);
+    let priorities = http_labels.capture(HttpOption::Priority);
+    let rule_syntaxes = http_labels.capture(HttpOption::RuleSyntax);
+    let observabilities = http_labels.capture_grouped(HttpOption::Observability);
+    let tls = http_labels.capture(HttpOption::Tls);
+    let cert_resolvers = http_labels.capture(HttpOption::TlsCertResolver);
+    let tls_options = http_labels.capture(HttpOption::TlsOptions);
+    let mut tls_domains = extract_tls_domains(
+        http_labels.matching(HttpOption::TlsDomain),
+        regexes,
+        diagnostics,
+    );
+    let schemes = http_labels.capture(HttpOption::Scheme);
+    let server_urls: HashMap<String, Url> = http_labels
+        .capture(HttpOption::Url)
+        .into_iter()
+        .map(|(service_name, value)| {
+            value
+                .parse::<Url>()
+                .map(|url| (service_name.clone(), url))
+                .map_err(|e| {
+                    anyhow!(
+                        "Invalid server URL '{}' for service '{}': {}",
+                        value,
+                        service_name,
+                        e
+                    )
+                })
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let pass_host_headers = http_labels.capture(HttpOption::PassHostHeader);
+    let health_checks: HashMap<String, TraefikedHealthCheckConfig> = http_labels
+        .capture_grouped(HttpOption::HealthCheck)
+        .into_iter()
+        .map(|(service_name, options)| {
+            parse_health_check(&service_name, options, regexes, diagnostics)
+                .map(|health_check| (service_name, health_check))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let sticky_cookies = http_labels.capture_grouped(HttpOption::StickyCookie);
+    let servers_transports = http_labels.capture(HttpOption::ServersTransport);
+    let flush_intervals = http_labels.capture(HttpOption::FlushInterval);
+    let load_balancer_config = |service_name: &str| TraefikedLoadBalancerConfig {
+        scheme: schemes.get(service_name).and_then(|value| {
+            parse_scheme(
+                &regexes.service_label(service_name, "server.scheme"),
+                value,
+                diagnostics,
+            )
+        }),
+        url: server_urls.get(service_name).cloned(),
+        pass_host_header: pass_host_headers.get(service_name).and_then(|value| {
+            parse_bool_option(
+                &regexes.service_label(service_name, "passhostheader"),
+                value,
+                diagnostics,
+            )
+        }),
+        health_check: health_checks.get(service_name).cloned(),
+        sticky: sticky_cookies
+            .get(service_name)
+            .and_then(|options| parse_sticky(service_name, options.clone(), regexes, diagnostics)),
+        servers_transport: servers_transports
+            .get(service_name)
+            .map(|value| value.trim().to_owned()),
+        flush_interval: flush_intervals
+            .get(service_name)
+            .map(|value| value.trim().to_owned()),
+    };
+    let mut router_config = |router_name: String, rule: String, service_name: Option<&str>| {
+        let tls_enabled = tls.get(&router_name).and_then(|value| {
+            parse_bool_option(
+                &regexes.router_label(&router_name, "tls"),
+                value,
+                diagnostics,
+            )
+        });
+        TraefikedContainerSinglePortConfig {
+            entrypoints: entrypoints
+                .get(&router_name)
+                .map(|value| parse_list(value))
+                .unwrap_or_default(),
+            middlewares: middlewares.get(&router_name).map(|value| parse_list(value)),
+            priority: priorities.get(&router_name).and_then(|value| {
+                parse_priority(
+                    &regexes.router_label(&router_name, "priority"),
+                    value,
+                    diagnostics,
+                )
+            }),
+            rule_syntax: rule_syntaxes.get(&router_name).and_then(|value| {
+                parse_rule_syntax(
+                    &regexes.router_label(&router_name, "rulesyntax"),
+                    value,
+                    diagnostics,
+                )
+            }),
+            observability: observabilities.get(&router_name).and_then(|options| {
+                parse_observability(&router_name, options, regexes, diagnostics)
+            }),
+            tls: router_tls_config(
+                tls_enabled,
+                cert_resolvers.get(&router_name).map(|value| value.as_str()),
+                tls_options.get(&router_name).map(|value| value.as_str()),
+                tls_domains.remove(&router_name).unwrap_or_default(),
+            ),
+            tls_disabled: tls_enabled == Some(false),
+            load_balancer: service_name.map(load_balancer_config).unwrap_or_default(),
+            target_ports: vec![],
+            router_name,
+            rule,
+        }
+    };
+
+    let explicit_services = http_labels.capture(HttpOption::Service);
+
+    let services: BTreeMap<String, Vec<u16>> = http_labels
+        .matching(HttpOption::Port)
+        .filter_map(|(label_key, label_value)| {
+            regexes
+                .service_port
+                .captures(label_key)
+                .and_then(|captures| captures.get(1))
+                .map(|service_name| service_name.as_str().to_owned())
+                .and_then(|service_name| match parse_ports(label_key, label_value) {
+                    Ok(ports) => Some((service_name, ports)),
+                    Err(error) => {
+                        diagnostics.report(label_key, error);
+                        None
+                    }
+                })
+        })
+        .collect();
+
+    let declared_services: Vec<String> = http_labels
+        .capture(HttpOption::ServiceName)
+        .into_keys()
+        .filter(|service_name| !is_weighted_service(&http_labels, service_name))
+        .sorted()
+        .collect();
+
+    // A router targeting a service of another provider needs its own service name
+    let targets_other_provider = |router_name: &String| {
+        explicit_services
+            .get(router_name)
+            .is_some_and(|service_name| is_provider_qualified(service_name))
+    };
+
+    if routers.len() == 1 && !targets_other_provider(&routers[0].0) {
+        let (router_name, rule) = routers.first().cloned().expect("Should have an router");
+
+        // The service options of a single router container come from its explicit service,
+        // or from the only service declared in the labels.
+        let service_name = match explicit_services.get(&router_name) {
+            Some(service_name) => Some(service_name.as_str()),
+            None if declared_services.len() == 1 => declared_services.first().map(String::as_str),
+            None => None,
+        };
+        report_unused_services(
+            labels,
+            regexes,
+            &declared_services,
+            service_name.as_slice(),
+            diagnostics,
+        );
+        // Services with a port or URL are declared ones, services of other providers having been
+        // handled above
+        if let Some(service_name) = explicit_services.get(&router_name).filter(|service_name| {
+            !declared_services.contains(service_name)
+                && !is_weighted_service(&http_labels, service_name)
+        }) {
+            diagnostics.report(
+                regexes.router_label(&router_name, "service"),
+                format!(
+                    "service '{}' is not declared by the labels of the container",
+                    service_name
+                ),
+            );
+        }
+
+        let target_ports = match service_name.and_then(|service_name| services.get(service_name)) {
+            Some(ports) => ports.clone(),
+            None => legacy_port(labels, regexes, diagnostics)
+                .into_iter()
+                .collect(),
+        };
+
+        return Ok(Some(TraefikedContainerConfig::SinglePort(
+            TraefikedContainerSinglePortConfig {
+                target_ports,
+                ..router_config(router_name, rule, service_name)
+            },
+        )));
+    }
+
+    let router_names: Vec<String> = routers.iter().map(|(name, _)| name.clone()).collect();
+    // Services with an explicit URL need no port
+    let service_names: Vec<String> = services
+        .keys()
+        .chain(server_urls.keys())
+        .cloned()
+        .sorted()
+        .dedup()
+        .collect();
+    let paired_services =
+        pair_routers_with_services(&router_names, &explicit_services, &service_names)?;
+    report_unused_services(
+        labels,
+        regexes,
+        &declared_services,
+        &paired_services.iter().map(String::as_str).collect_vec(),
+        diagnostics,
+    );
+
+    let multiport_configs = routers
+        .into_iter()
+        .zip(paired_services)
+        .map(
+            |((router_name, rule), service_name)| TraefikedContainerMultiPortConfig {
+                target_ports: services.get(&service_name).cloned().unwrap_or_default(),
+                config: router_config(router_name, rule, Some(&service_name)),
+                service_name,
+            },
+        )
+        .collect();
+
+    Ok(Some(TraefikedContainerConfig::MultiplePorts(
+        multiport_configs,
+    )))
+}
+
+/// Labels of a test container, e.g. `labels(&[("traefik.enable", "true")])`.
+#[cfg(test)]
+pub(crate) fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::models::{ContainerSummaryNetworkSettings, EndpointSettings, Port, PortTypeEnum};
+    use rstest::*;
+
+    use super::*;
+
+    #[rstest]
+    #[case(true, None, true)]
+    #[case(true, Some("true"), true)]
+    #[case(true, Some("false"), false)]
+    #[case(false, None, false)]
+    #[case(false, Some("true"), true)]
+    #[case(false, Some("false"), false)]
+    #[case(true, Some("FALSE"), false)]
+    fn test_extract_traefik_config_respects_enable_label(
+        #[case] exposed_by_default: bool,
+        #[case] enable_label: Option<&str>,
+        #[case] expected_exposed: bool,
+    ) {
+        let mut labels = labels(&[(
+            "traefik.http.routers.to-my-service.rule",
+            "Host(`my-service.my-domain.com`)",
+        )]);
+        if let Some(enable) = enable_label {
+            labels.insert("traefik.enable".to_owned(), enable.to_owned());
+        }
+
+        let config = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            exposed_by_default,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap();
+
+        assert_eq!(config.is_some(), expected_exposed);
+    }
+
+    #[test]
+    fn test_extract_traefik_config_entrypoints() {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.to-my-service.rule",
+                "Host(`my-service.my-domain.com`)",
+            ),
+            (
+                "traefik.http.routers.to-my-service.entrypoints",
+                "websecure, web,",
+            ),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.entrypoints, vec!["websecure", "web"]);
+    }
+
+    #[test]
+    fn test_extract_traefik_config_without_entrypoints() {
+        let labels = labels(&[(
+            "traefik.http.routers.to-my-service.rule",
+            "Host(`my-service.my-domain.com`)",
+        )]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert!(config.entrypoints.is_empty());
+    }
+
+    #[test]
+    fn test_extract_traefik_config_middlewares() {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.to-my-service.rule",
+                "Host(`my-service.my-domain.com`)",
+            ),
+            (
+                "traefik.http.routers.to-my-service.middlewares",
+                "auth@file,compress@file,,",
+            ),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(
+            config.middlewares,
+            Some(vec!["auth@file".to_owned(), "compress@file".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_extract_traefik_config_empty_middlewares() {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.to-my-service.rule",
+                "Host(`my-service.my-domain.com`)",
+            ),
+            ("traefik.http.routers.to-my-service.middlewares", ""),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.middlewares, Some(vec![]));
+    }
+
+    #[rstest]
+    #[case("100", Some(100))]
+    #[case("-5", Some(-5))]
+    #[case("high", None)]
+    #[case("99999999999999999999", None)]
+    fn test_extract_traefik_config_priority(#[case] priority: &str, #[case] expected: Option<i64>) {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.to-my-service.rule",
+                "Host(`my-service.my-domain.com`)",
+            ),
+            ("traefik.http.routers.to-my-service.priority", priority),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.priority, expected);
+    }
+
+    #[rstest]
+    #[case(&[], None, 0)]
+    #[case(
+        &[("traefik.http.routers.to-my-service.observability.accesslogs", "false")],
+        Some(TraefikedObservabilityConfig { access_logs: Some(false), ..Default::default() }),
+        0
+    )]
+    #[case(
+        &[
+            ("traefik.http.routers.to-my-service.observability.accessLogs", "false"),
+            ("traefik.http.routers.to-my-service.observability.metrics", " TRUE "),
+            ("traefik.http.routers.to-my-service.observability.tracing", "false"),
+        ],
+        Some(TraefikedObservabilityConfig {
+            access_logs: Some(false),
+            metrics: Some(true),
+            tracing: Some(false),
+        }),
+        0
+    )]
+    #[case(
+        &[
+            ("traefik.http.routers.to-my-service.observability.metrics", "nope"),
+            ("traefik.http.routers.to-my-service.observability.logs", "false"),
+        ],
+        None,
+        2
+    )]
+    fn test_extract_traefik_config_observability(
+        #[case] pairs: &[(&str, &str)],
+        #[case] expected: Option<TraefikedObservabilityConfig>,
+        #[case] expected_errors: usize,
+    ) {
+        let labels = labels(
+            &[
+                &[(
+                    "traefik.http.routers.to-my-service.rule",
+                    "Host(`my-service.my-domain.com`)",
+                )],
+                pairs,
+            ]
+            .concat(),
+        );
+        let diagnostics = Diagnostics::default();
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) =
+            extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)
+                .unwrap()
+        else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.observability, expected);
+        assert_eq!(diagnostics.into_errors().len(), expected_errors);
+    }
+
+    #[rstest]
+    #[case("v2", Some("v2"))]
+    #[case(" V3 ", Some("v3"))]
+    #[case("v1", None)]
+    fn test_extract_traefik_config_rule_syntax(
+        #[case] rule_syntax: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.to-my-service.rule",
+                "Host(`my-service.my-domain.com`)",
+            ),
+            ("traefik.http.routers.to-my-service.ruleSyntax", rule_syntax),
+        ]);
+        let diagnostics = Diagnostics::default();
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) =
+            extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)
+                .unwrap()
+        else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.rule_syntax.as_deref(), expected);
+        assert_eq!(diagnostics.is_empty(), expected.is_some());
+    }
+
+    #[rstest]
+    #[case(None, None, None, None)]
+    #[case(Some("true"), None, None, Some(TraefikedRouterTlsConfig::default()))]
+    #[case(Some("true"), Some("le"), None, Some(TraefikedRouterTlsConfig { cert_resolver: Some("le".to_owned()), ..Default::default() }))]
+    #[case(None, Some("le"), None, Some(TraefikedRouterTlsConfig { cert_resolver: Some("le".to_owned()), ..Default::default() }))]
+    #[case(Some("false"), Some("le"), None, None)]
+    #[case(None, None, Some("modern@file"), Some(TraefikedRouterTlsConfig { options: Some("modern@file".to_owned()), ..Default::default() }))]
+    #[case(Some("true"), Some("le"), Some("modern@file"), Some(TraefikedRouterTlsConfig { cert_resolver: Some("le".to_owned()), options: Some("modern@file".to_owned()), ..Default::default() }))]
+    #[case(Some("false"), None, Some("modern@file"), None)]
+    fn test_extract_traefik_config_tls(
+        #[case] tls_label: Option<&str>,
+        #[case] cert_resolver_label: Option<&str>,
+        #[case] options_label: Option<&str>,
+        #[case] expected: Option<TraefikedRouterTlsConfig>,
+    ) {
+        let mut labels = labels(&[(
+            "traefik.http.routers.to-my-service.rule",
+            "Host(`my-service.my-domain.com`)",
+        )]);
+        if let Some(tls) = tls_label {
+            labels.insert(
+                "traefik.http.routers.to-my-service.tls".to_owned(),
+                tls.to_owned(),
+            );
+        }
+        if let Some(cert_resolver) = cert_resolver_label {
+            labels.insert(
+                "traefik.http.routers.to-my-service.tls.certresolver".to_owned(),
+                cert_resolver.to_owned(),
+            );
+        }
+        if let Some(options) = options_label {
+            labels.insert(
+                "traefik.http.routers.to-my-service.tls.options".to_owned(),
+                options.to_owned(),
+            );
+        }
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.tls, expected);
+        assert_eq!(config.tls_disabled, tls_label == Some("false"));
+    }
+
+    #[rstest]
+    #[case(None, None)]
+    #[case(Some("le"), Some(TraefikedRouterTlsConfig { cert_resolver: Some("le".to_owned()), ..Default::default() }))]
+    fn test_extract_traefik_config_invalid_tls(
+        #[case] cert_resolver_label: Option<&str>,
+        #[case] expected: Option<TraefikedRouterTlsConfig>,
+    ) -> anyhow::Result<()> {
+        let mut labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.routers.app.tls", "yes"),
+        ]);
+        if let Some(cert_resolver) = cert_resolver_label {
+            labels.insert(
+                "traefik.http.routers.app.tls.certresolver".to_owned(),
+                cert_resolver.to_owned(),
+            );
+        }
+        let diagnostics = Diagnostics::default();
+
+        let config =
+            extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)?;
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = config else {
+            panic!("Should be a single port config");
+        };
+        assert_eq!(config.tls, expected);
+        assert!(!config.tls_disabled);
+        assert_eq!(
+            diagnostics.into_errors(),
+            vec![LabelParseError {
+                label: "traefik.http.routers.app.tls".to_owned(),
+                reason: "expected true or false, got 'yes'".to_owned(),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_traefik_config_tls_domains() {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.to-my-service.rule",
+                "Host(`my-service.my-domain.com`)",
+            ),
+            (
+                "traefik.http.routers.to-my-service.tls.domains[10].main",
+                "other.com",
+            ),
+            (
+                "traefik.http.routers.to-my-service.tls.domains[2].main",
+                "example.com",
+            ),
+            (
+                "traefik.http.routers.to-my-service.tls.domains[2].sans",
+                "*.example.com,www.example.org",
+            ),
+            (
+                "traefik.http.routers.to-my-service.tls.domains[x].main",
+                "malformed.com",
+            ),
+            (
+                "traefik.http.routers.to-my-service.tls.domains[3].sans",
+                "*.no-main.com",
+            ),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(
+            config.tls,
+            Some(TraefikedRouterTlsConfig {
+                cert_resolver: None,
+                options: None,
+                domains: vec![
+                    TraefikedTlsDomain {
+                        main: "example.com".to_owned(),
+                        sans: vec!["*.example.com".to_owned(), "www.example.org".to_owned()],
+                    },
+                    TraefikedTlsDomain {
+                        main: "other.com".to_owned(),
+                        sans: vec![],
+                    },
+                ],
+            })
+        );
+    }
+
+    fn multiport_pairs(config: TraefikedContainerConfig) -> Vec<(String, String, Vec<u16>)> {
+        let TraefikedContainerConfig::MultiplePorts(configs) = config else {
+            panic!("Should be a multi port config");
+        };
+
+        configs
+            .into_iter()
+            .map(|c| (c.config.router_name, c.service_name, c.target_ports))
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_traefik_config_explicit_router_service() {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.a-router.rule",
+                "Host(`a.my-domain.com`)",
+            ),
+            ("traefik.http.routers.a-router.service", "zeta"),
+            (
+                "traefik.http.routers.b-router.rule",
+                "Host(`b.my-domain.com`)",
+            ),
+            ("traefik.http.routers.b-router.service", "alpha"),
+            (
+                "traefik.http.services.alpha.loadbalancer.server.port",
+                "8081",
+            ),
+            (
+                "traefik.http.services.zeta.loadbalancer.server.port",
+                "8082",
+            ),
+        ]);
+
+        let config = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap()
+        .expect("Should have a config");
+
+        assert_eq!(
+            multiport_pairs(config),
+            vec![
+                ("a-router".to_owned(), "zeta".to_owned(), vec![8082]),
+                ("b-router".to_owned(), "alpha".to_owned(), vec![8081]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_traefik_config_partially_explicit_router_service() {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.a-router.rule",
+                "Host(`a.my-domain.com`)",
+            ),
+            (
+                "traefik.http.routers.b-router.rule",
+                "Host(`b.my-domain.com`)",
+            ),
+            ("traefik.http.routers.b-router.service", "alpha"),
+            (
+                "traefik.http.services.alpha.loadbalancer.server.port",
+                "8081",
+            ),
+            (
+                "traefik.http.services.zeta.loadbalancer.server.port",
+                "8082",
+            ),
+        ]);
+
+        let config = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap()
+        .expect("Should have a config");
+
+        assert_eq!(
+            multiport_pairs(config),
+            vec![
+                ("a-router".to_owned(), "zeta".to_owned(), vec![8082]),
+                ("b-router".to_owned(), "alpha".to_owned(), vec![8081]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_traefik_config_routers_sharing_single_service() {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.internal.rule",
+                "Host(`my-service.local`)",
+            ),
+            (
+                "traefik.http.routers.public.rule",
+                "Host(`my-service.my-domain.com`)",
+            ),
+            (
+                "traefik.http.services.my-service.loadbalancer.server.port",
+                "8080",
+            ),
+        ]);
+
+        let config = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap()
+        .expect("Should have a config");
+
+        assert_eq!(
+            multiport_pairs(config),
+            vec![
+                ("internal".to_owned(), "my-service".to_owned(), vec![8080]),
+                ("public".to_owned(), "my-service".to_owned(), vec![8080]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_traefik_config_routers_sharing_explicit_service() {
+        let labels = labels(&[
+            ("traefik.http.routers.a-router.rule", "Host(`a.local`)"),
+            ("traefik.http.routers.a-router.service", "alpha"),
+            ("traefik.http.routers.b-router.rule", "Host(`b.local`)"),
+            ("traefik.http.routers.b-router.service", "alpha"),
+            ("traefik.http.routers.c-router.rule", "Host(`c.local`)"),
+            ("traefik.http.routers.c-router.service", "zeta"),
+            (
+                "traefik.http.services.alpha.loadbalancer.server.port",
+                "8081",
+            ),
+            (
+                "traefik.http.services.zeta.loadbalancer.server.port",
+                "8082",
+            ),
+        ]);
+
+        let config = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap()
+        .expect("Should have a config");
+
+        assert_eq!(
+            multiport_pairs(config),
+            vec![
+                ("a-router".to_owned(), "alpha".to_owned(), vec![8081]),
+                ("b-router".to_owned(), "alpha".to_owned(), vec![8081]),
+                ("c-router".to_owned(), "zeta".to_owned(), vec![8082]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_traefik_config_unpaired_implicit_routers() {
+        let labels = labels(&[
+            ("traefik.http.routers.a-router.rule", "Host(`a.local`)"),
+            ("traefik.http.routers.b-router.rule", "Host(`b.local`)"),
+            ("traefik.http.routers.c-router.rule", "Host(`c.local`)"),
+            (
+                "traefik.http.services.alpha.loadbalancer.server.port",
+                "8081",
+            ),
+            (
+                "traefik.http.services.zeta.loadbalancer.server.port",
+                "8082",
+            ),
+        ]);
+
+        assert!(extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_extract_traefik_config_unknown_router_service() {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.a-router.rule",
+                "Host(`a.my-domain.com`)",
+            ),
+            ("traefik.http.routers.a-router.service", "missing"),
+            (
+                "traefik.http.routers.b-router.rule",
+                "Host(`b.my-domain.com`)",
+            ),
+            (
+                "traefik.http.services.alpha.loadbalancer.server.port",
+                "8081",
+            ),
+        ]);
+
+        assert!(extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default()
+        )
+        .is_err());
+    }
+
+    #[rstest]
+    #[case(&[], &[("auth", "central-auth@file", vec![])])]
+    #[case(
+        &[
+            ("traefik.http.routers.app.rule", "Host(`app.my-domain.com`)"),
+            ("traefik.http.services.app.loadbalancer.server.port", "8080"),
+        ],
+        &[("app", "app", vec![8080]), ("auth", "central-auth@file", vec![])]
+    )]
+    fn test_extract_traefik_config_provider_qualified_router_service(
+        #[case] other_labels: &[(&str, &str)],
+        #[case] expected: &[(&str, &str, Vec<u16>)],
+    ) -> anyhow::Result<()> {
+        let mut labels = labels(other_labels);
+        labels.extend(self::labels(&[
+            (
+                "traefik.http.routers.auth.rule",
+                "Host(`auth.my-domain.com`)",
+            ),
+            ("traefik.http.routers.auth.service", "central-auth@file"),
+        ]));
+
+        let Some(TraefikedContainerConfig::MultiplePorts(configs)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )?
+        else {
+            panic!("Should be a multiple ports config");
+        };
+
+        assert_eq!(
+            configs
+                .iter()
+                .map(|c| (
+                    c.config.router_name.as_str(),
+                    c.service_name.as_str(),
+                    c.target_ports.clone()
+                ))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("8080", Some(8080))]
+    #[case(" 80 ", Some(80))]
+    #[case("0", None)]
+    #[case("65536", None)]
+    #[case("http", None)]
+    fn test_parse_port(#[case] value: &str, #[case] expected: Option<u16>) {
+        let label = "traefik.http.services.app.loadbalancer.server.port";
+
+        match parse_port(label, value) {
+            Ok(port) => assert_eq!(Some(port), expected),
+            Err(error) => {
+                assert_eq!(expected, None);
+                assert!(matches!(
+                    error,
+                    ContainerConversionError::InvalidPortValue { label: l, value: v }
+                        if l == label && v == value
+                ));
+            }
+        }
+    }
+
+    #[rstest]
+    #[case("8080", Some(vec![8080]))]
+    #[case("8080, 8081,8082", Some(vec![8080, 8081, 8082]))]
+    #[case("8080,http", None)]
+    #[case("8080,0", None)]
+    fn test_extract_traefik_config_port_list(
+        #[case] port: &str,
+        #[case] expected: Option<Vec<u16>>,
+    ) -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.services.app.loadbalancer.server.port", port),
+        ]);
+        let diagnostics = Diagnostics::default();
+
+        let config =
+            extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)?;
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = config else {
+            panic!("Should be a single port config");
+        };
+        assert_eq!(diagnostics.is_empty(), expected.is_some());
+        assert_eq!(config.target_ports, expected.unwrap_or_default());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::undeclared("traefik.http.routers.app.service", "api", true)]
+    #[case::port("traefik.http.services.app.loadbalancer.server.port", "8080", false)]
+    #[case::url(
+        "traefik.http.services.app.loadbalancer.server.url",
+        "http://10.0.0.5/",
+        false
+    )]
+    #[case::weighted(
+        "traefik.http.services.app.weighted.services[0].name",
+        "api@file",
+        false
+    )]
+    fn test_extract_traefik_config_undeclared_service(
+        #[case] label: &str,
+        #[case] value: &str,
+        #[case] expected_error: bool,
+    ) -> anyhow::Result<()> {
+        let mut labels = labels(&[("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)")]);
+        labels
+            .entry("traefik.http.routers.app.service".to_owned())
+            .or_insert_with(|| "app".to_owned());
+        labels.insert(label.to_owned(), value.to_owned());
+        let diagnostics = Diagnostics::default();
+
+        extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)?;
+
+        let expected = expected_error.then(|| LabelParseError {
+            label: "traefik.http.routers.app.service".to_owned(),
+            reason: format!(
+                "service '{}' is not declared by the labels of the container",
+                value
+            ),
+        });
+        assert_eq!(diagnostics.into_errors(), Vec::from_iter(expected));
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_traefik_config_rejects_port_zero() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.services.app.loadbalancer.server.port", "0"),
+        ]);
+        let diagnostics = Diagnostics::default();
+
+        let config =
+            extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)?;
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = config else {
+            panic!("Should be a single port config");
+        };
+        assert!(config.target_ports.is_empty());
+        assert_eq!(
+            diagnostics.into_errors(),
+            vec![LabelParseError {
+                label: "traefik.http.services.app.loadbalancer.server.port".to_owned(),
+                reason:
+                    "Invalid port '0' in label 'traefik.http.services.app.loadbalancer.server.port'"
+                        .to_owned(),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_traefik_config_reports_label_errors() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.routers.app.priority", "high"),
+            ("traefik.http.services.app.loadbalancer.server.port", "80a"),
+            (
+                "traefik.http.services.app.loadbalancer.server.scheme",
+                "ftp",
+            ),
+        ]);
+        let diagnostics = Diagnostics::default();
+
+        let config =
+            extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)?;
+
+        assert!(config.is_some());
+        let mut labels_in_error: Vec<String> = diagnostics
+            .into_errors()
+            .into_iter()
+            .map(|error| error.label)
+            .collect();
+        labels_in_error.sort();
+        assert_eq!(
+            labels_in_error,
+            vec![
+                "traefik.http.routers.app.priority",
+                "traefik.http.services.app.loadbalancer.server.port",
+                "traefik.http.services.app.loadbalancer.server.scheme",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_traefik_config_skips_invalid_rules() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.http.routers.broken.rule", "Host(`a.my-domain.com)"),
+            ("traefik.http.routers.valid.rule", "Host(`b.my-domain.com`)"),
+        ]);
+        let diagnostics = Diagnostics::default();
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) =
+            extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)?
+        else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.router_name, "valid");
+        assert_eq!(
+            diagnostics
+                .into_errors()
+                .into_iter()
+                .map(|error| error.label)
+                .collect::<Vec<_>>(),
+            vec!["traefik.http.routers.broken.rule"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_traefik_config_only_invalid_rules() {
+        let labels = labels(&[("traefik.http.routers.broken.rule", "Host(a.my-domain.com)")]);
+
+        assert!(extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_extract_traefik_config_valid_labels_report_nothing() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.routers.app.priority", "10"),
+            ("traefik.http.services.app.loadbalancer.server.port", "8080"),
+        ]);
+        let diagnostics = Diagnostics::default();
+
+        extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)?;
+
+        assert!(diagnostics.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_traefik_config_reports_unused_services() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.routers.app.service", "app"),
+            ("traefik.http.services.app.loadbalancer.server.port", "8080"),
+            (
+                "traefik.http.services.admin.loadbalancer.server.port",
+                "9090",
+            ),
+        ]);
+        let diagnostics = Diagnostics::default();
+
+        extract_traefik_config(&labels, &LabelRegexes::default(), true, None, &diagnostics)?;
+
+        assert_eq!(
+            diagnostics.into_errors(),
+            vec![LabelParseError {
+                label: "traefik.http.services.admin.loadbalancer.server.port".to_owned(),
+                reason: "service is not used by any router".to_owned(),
+            }]
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("traefik.http.routers..rule", "empty router name")]
+    #[case("traefik.http.routers.app.rulee", "unsupported router option")]
+    #[case(
+        "traefik.http.services.app.loadbalancer.server.prot",
+        "unsupported service option"
+    )]
+    #[case("traefik.tcp.routers.db.entrypoint", "unsupported router option")]
+    #[case("traefik.udp.services..loadbalancer.server.port", "empty service name")]
+    fn test_parse_traefik_labels_reports_unrecognized_labels(
+        #[case] label: &str,
+        #[case] reason: &str,
+    ) -> Result<(), ContainerConversionError> {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.services.app.loadbalancer.server.port", "8080"),
+            (label, "value"),
+        ]);
+
+        let outcome = parse_traefik_labels(&labels)?;
+
+        assert_eq!(
+            outcome.warnings,
+            vec![LabelParseError {
+                label: label.to_owned(),
+                reason: reason.to_owned(),
+            }]
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("traefik.http.routers.my-router.rule", Some("my-router"))]
+    #[case("traefik.http.routers.my_router-2.rule", Some("my_router-2"))]
+    #[case("traefik.http.routersXfoo.rule", None)]
+    #[case("traefik.http.routers.a.rule.extra", None)]
+    #[case("not-traefik.http.routers.a.rule", None)]
+    #[case("not-traefik.http.routers.a.rule.extra", None)]
+    #[case("traefik.http.routers.a.b.rule", None)]
+    #[case("traefik.http.routers..rule", None)]
+    #[case("traefik.http.routers.rule", None)]
+    #[case("traefikXhttpXroutersXaXrule", None)]
+    #[case("traefik.http.routers.a.rulesyntax", None)]
+    #[case("traefik.HTTP.Routers.My-Router.Rule", Some("My-Router"))]
+    #[case("TRAEFIK.HTTP.ROUTERS.MY-ROUTER.RULE", Some("MY-ROUTER"))]
+    fn test_routers_label_regex(#[case] label_key: &str, #[case] expected: Option<&str>) {
+        let router_name = LabelRegexes::default()
+            .routers
+            .captures(label_key)
+            .and_then(|captures| captures.get(1))
+            .map(|router_name| router_name.as_str());
+
+        assert_eq!(router_name, expected);
+    }
+
+    #[rstest]
+    #[case(
+        "traefik.http.services.my-service.loadbalancer.server.port",
+        Some("my-service")
+    )]
+    #[case(
+        "traefik.http.services.My-Service.LoadBalancer.Server.Port",
+        Some("My-Service")
+    )]
+    #[case("traefik.http.servicesNAME.loadbalancer.server.port", None)]
+    #[case("traefik.http.services.a.loadbalancerXserverXport", None)]
+    #[case("traefik.http.services.a.b.loadbalancer.server.port", None)]
+    #[case("traefik.http.services.a.loadbalancer.server.port.extra", None)]
+    #[case("my.traefik.http.services.a.loadbalancer.server.port", None)]
+    #[case("traefik.http.services..loadbalancer.server.port", None)]
+    fn test_service_label_regex(#[case] label_key: &str, #[case] expected: Option<&str>) {
+        let service_name = LabelRegexes::default()
+            .service_port
+            .captures(label_key)
+            .and_then(|captures| captures.get(1))
+            .map(|service_name| service_name.as_str());
+
+        assert_eq!(service_name, expected);
+    }
+
+    #[rstest]
+    #[case("traefik", "traefik.http.routers.app.rule", true)]
+    #[case("traefik", "Traefik.enable", true)]
+    #[case("traefik", "traefik", false)]
+    #[case("traefik", "traefik-internal.enable", false)]
+    #[case("traefik", "com.docker.compose.project", false)]
+    #[case("traefik", "trä", false)]
+    #[case("my.traefik", "my.traefik.enable", true)]
+    fn test_is_own_label(#[case] prefix: &str, #[case] label_key: &str, #[case] expected: bool) {
+        assert_eq!(LabelRegexes::new(prefix).is_own_label(label_key), expected);
+    }
+
+    #[rstest]
+    #[case("traefik-internal", "traefik-internal.http.routers.app.rule", true)]
+    #[case("traefik-internal", "traefik.http.routers.app.rule", false)]
+    #[case("traefik", "traefik-internal.http.routers.app.rule", false)]
+    #[case("my.traefik", "my.traefik.http.routers.app.rule", true)]
+    #[case("my.traefik", "myXtraefik.http.routers.app.rule", false)]
+    fn test_extract_traefik_config_label_prefix(
+        #[case] prefix: &str,
+        #[case] rule_label: &str,
+        #[case] expected_found: bool,
+    ) {
+        let labels = labels(&[(rule_label, "Host(`app.my-domain.com`)")]);
+
+        let config = extract_traefik_config(
+            &labels,
+            &LabelRegexes::new(prefix),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap();
+
+        assert_eq!(config.is_some(), expected_found);
+    }
+
+    #[test]
+    fn test_extract_traefik_config_label_prefix_enable_label() {
+        let labels = labels(&[
+            (
+                "traefik-internal.http.routers.app.rule",
+                "Host(`app.my-domain.com`)",
+            ),
+            ("traefik-internal.enable", "false"),
+            ("traefik.enable", "true"),
+        ]);
+
+        let config = extract_traefik_config(
+            &labels,
+            &LabelRegexes::new("traefik-internal"),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap();
+
+        assert!(config.is_none());
+    }
+
+    #[test]
+    fn test_extract_traefik_config_ignores_adversarial_labels() {
+        let labels = labels(&[
+            ("traefik.http.routers.a.rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.routers.a.b.rule", "Host(`ab.my-domain.com`)"),
+            ("traefik.http.routersXfoo.rule", "Host(`foo.my-domain.com`)"),
+            ("not-traefik.http.routers.c.rule", "Host(`c.my-domain.com`)"),
+            (
+                "traefik.http.routers.d.rule.extra",
+                "Host(`d.my-domain.com`)",
+            ),
+            ("traefik.http.routers.a.entrypoints.extra", "web"),
+            ("traefik.http.servicesX.loadbalancer.server.port", "8080"),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.router_name, "a");
+        assert!(config.entrypoints.is_empty());
+    }
+
+    #[test]
+    fn test_extract_traefik_config_mixed_case_labels() {
+        let labels = labels(&[
+            ("Traefik.Enable", "True"),
+            ("traefik.HTTP.Routers.App.Rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.routers.App.EntryPoints", "websecure"),
+            ("traefik.http.routers.App.TLS.CertResolver", "le"),
+            (
+                "traefik.http.routers.App.tls.Domains[0].Main",
+                "my-domain.com",
+            ),
+            ("traefik.http.routers.Admin.rule", "Host(`b.my-domain.com`)"),
+            ("traefik.http.routers.Admin.Service", "Admin-Service"),
+            (
+                "traefik.http.services.Admin-Service.LoadBalancer.Server.Port",
+                "8081",
+            ),
+            (
+                "traefik.http.services.App-Service.loadBalancer.server.port",
+                "8082",
+            ),
+        ]);
+
+        let config = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            false,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap()
+        .expect("Should have a config");
+        let TraefikedContainerConfig::MultiplePorts(configs) = config else {
+            panic!("Should be a multi port config");
+        };
+
+        assert_eq!(configs[0].config.router_name, "Admin");
+        assert_eq!(configs[0].service_name, "Admin-Service");
+        assert_eq!(configs[1].config.router_name, "App");
+        assert_eq!(configs[1].service_name, "App-Service");
+        assert_eq!(configs[1].config.entrypoints, vec!["websecure"]);
+        assert_eq!(
+            configs[1].config.tls,
+            Some(TraefikedRouterTlsConfig {
+                cert_resolver: Some("le".to_owned()),
+                options: None,
+                domains: vec![TraefikedTlsDomain {
+                    main: "my-domain.com".to_owned(),
+                    sans: vec![],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_extract_traefik_config_single_port_target_port() {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            ("traefik.http.services.app.loadbalancer.server.port", "8080"),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.target_ports, vec![8080]);
+    }
+
+    fn container_summary(
+        labels: HashMap<String, String>,
+        public_port: Option<u16>,
+    ) -> ContainerSummary {
+        ContainerSummary {
+            names: Some(vec!["/my-app".to_owned()]),
+            ports: Some(vec![Port {
+                private_port: 80,
+                public_port,
+                ..Default::default()
+            }]),
+            labels: Some(labels),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case(Some(vec!["/my-app"]), Some("my-app"))]
+    #[case(Some(vec!["my-app"]), Some("my-app"))]
+    #[case(Some(vec![]), None)]
+    #[case(Some(vec![""]), None)]
+    #[case(Some(vec!["/"]), None)]
+    #[case(None, None)]
+    #[case(Some(vec!["/my-app", "/proxy/my-app-alias"]), Some("my-app"))]
+    #[case(Some(vec!["/proxy/my-app-alias", "/my-app"]), Some("my-app"))]
+    #[case(Some(vec!["/my-application", "/app", "/my-app"]), Some("app"))]
+    #[case(Some(vec!["/my-app", "/app", "/my-application"]), Some("app"))]
+    #[case(Some(vec!["/bbb", "/aaa"]), Some("aaa"))]
+    #[case(Some(vec!["/aaa", "/bbb"]), Some("aaa"))]
+    fn test_from_summary_name(#[case] names: Option<Vec<&str>>, #[case] expected: Option<&str>) {
+        let mut summary = container_summary(
+            labels(&[("traefik.http.routers.app.rule", "Host(`app.local`)")]),
+            Some(7878),
+        );
+        summary.names = names.map(|names| names.into_iter().map(str::to_owned).collect());
+
+        let container = TraefikedContainer::from_summary(
+            summary,
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        );
+
+        assert_eq!(container.ok().map(|c| c.name).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case("my-app", "my-app")]
+    #[case("my_app", "my_app")]
+    #[case("mystack_web.1.abc123", "mystack_web-1-abc123")]
+    #[case("proxy/my-app", "proxy-my-app")]
+    #[case("my..app--", "my-app")]
+    #[case(".my app!", "my-app")]
+    #[case(&"a".repeat(100), &"a".repeat(MAX_NAME_LENGTH))]
+    fn test_sanitize_name(#[case] name: &str, #[case] expected: &str) -> anyhow::Result<()> {
+        assert_eq!(sanitize_name(name)?, expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("...")]
+    #[case("@/.")]
+    fn test_sanitize_name_invalid(#[case] name: &str) {
+        assert!(sanitize_name(name).is_err());
+    }
+
+    #[test]
+    fn test_from_summary_service_name() -> anyhow::Result<()> {
+        let mut summary = container_summary(
+            labels(&[("traefik.http.routers.app.rule", "Host(`app.local`)")]),
+            Some(7878),
+        );
+        summary.names = Some(vec!["/mystack_web.1.abc123".to_owned()]);
+
+        let container = TraefikedContainer::from_summary(
+            summary,
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        assert_eq!(container.name, "mystack_web.1.abc123");
+        assert_eq!(container.service_name, "mystack_web-1-abc123");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(true, None, true)]
+    #[case(false, None, false)]
+    #[case(false, Some("true"), true)]
+    #[case(true, Some("false"), false)]
+    fn test_from_summary_exposed_by_default(
+        #[case] exposed_by_default: bool,
+        #[case] enable: Option<&str>,
+        #[case] expected: bool,
+    ) {
+        let mut pairs = vec![("traefik.tcp.routers.db.rule", "HostSNI(`*`)")];
+        pairs.extend(enable.map(|enable| ("traefik.enable", enable)));
+        let options = DockerOptions {
+            exposed_by_default,
+            ..Default::default()
+        };
+
+        let result = TraefikedContainer::from_summary(
+            container_summary(labels(&pairs), Some(5432)),
+            &options,
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        );
+
+        assert_eq!(result.is_ok(), expected);
+        if !expected {
+            assert!(matches!(
+                result,
+                Err(ContainerConversionError::TraefikDisabled)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_try_from_errors() {
+        let summary = container_summary(
+            labels(&[("traefik.http.routers.app.rule", "Host(`app.local`)")]),
+            Some(7878),
+        );
+
+        let missing_name = ContainerSummary {
+            names: Some(vec![]),
+            ..summary.clone()
+        };
+        let no_ports = ContainerSummary {
+            ports: None,
+            ..summary.clone()
+        };
+        let no_traefik_labels = ContainerSummary {
+            labels: None,
+            ..summary.clone()
+        };
+        // TCP routers included, which are not read by the HTTP router extraction
+        let disabled = ContainerSummary {
+            labels: Some(labels(&[
+                ("traefik.enable", "false"),
+                ("traefik.tcp.routers.db.rule", "HostSNI(`*`)"),
+                ("traefik.udp.routers.dns.entrypoints", "dns"),
+            ])),
+            ..summary.clone()
+        };
+        let zero_port = ContainerSummary {
+            ports: Some(vec![Port {
+                private_port: 80,
+                public_port: Some(0),
+                ..Default::default()
+            }]),
+            ..summary.clone()
+        };
+        let invalid_health_check_port = ContainerSummary {
+            labels: Some(labels(&[
+                ("traefik.http.routers.app.rule", "Host(`app.local`)"),
+                (
+                    "traefik.http.services.app.loadbalancer.healthcheck.path",
+                    "/health",
+                ),
+                (
+                    "traefik.http.services.app.loadbalancer.healthcheck.port",
+                    "eighty",
+                ),
+            ])),
+            ..summary.clone()
+        };
+        let invalid_name = ContainerSummary {
+            names: Some(vec!["/...".to_owned()]),
+            ..summary
+        };
+
+        assert!(matches!(
+            TraefikedContainer::try_from(missing_name),
+            Err(ContainerConversionError::MissingName)
+        ));
+        assert!(matches!(
+            TraefikedContainer::try_from(no_ports),
+            Err(ContainerConversionError::NoPorts)
+        ));
+        assert!(matches!(
+            TraefikedContainer::try_from(no_traefik_labels),
+            Err(ContainerConversionError::NoTraefikLabels)
+        ));
+        assert!(matches!(
+            TraefikedContainer::try_from(disabled),
+            Err(ContainerConversionError::TraefikDisabled)
+        ));
+        assert!(matches!(
+            TraefikedContainer::try_from(zero_port),
+            Err(ContainerConversionError::ZeroPort)
+        ));
+        assert!(matches!(
+            TraefikedContainer::try_from(invalid_health_check_port),
+            Err(ContainerConversionError::InvalidPortValue { label, value })
+                if label == "traefik.http.services.app.loadbalancer.healthcheck.port"
+                    && value == "eighty"
+        ));
+        assert!(matches!(
+            TraefikedContainer::try_from(invalid_name),
+            Err(ContainerConversionError::InvalidName { name }) if name == "..."
+        ));
+    }
+
+    #[test]
+    fn test_hostnames() -> anyhow::Result<()> {
+        let container = TraefikedContainer::builder("my-service")
+            .public_port(7878)
+            .router("api", "Host(`api.example.com`) && PathPrefix(`/v1`)")
+            .router("app", "Host(`app.example.com`) || Host(`api.example.com`)")
+            .service("my-service", 7878)
+            .router_service("api", "my-service")
+            .router_service("app", "my-service")
+            .build()?;
+
+        assert_eq!(
+            container.hostnames(),
+            vec!["api.example.com", "app.example.com"]
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(Some(PortTypeEnum::TCP), PortProtocol::Tcp)]
+    #[case(Some(PortTypeEnum::UDP), PortProtocol::Udp)]
+    #[case(Some(PortTypeEnum::SCTP), PortProtocol::Sctp)]
+    #[case(None, PortProtocol::Tcp)]
+    fn test_port_mapping_from_port(
+        #[case] typ: Option<PortTypeEnum>,
+        #[case] expected_protocol: PortProtocol,
+    ) {
+        let port = Port {
+            ip: Some("127.0.0.1".to_owned()),
+            private_port: 53,
+            public_port: Some(5353),
+            typ,
+        };
+
+        assert_eq!(
+            PortMapping::from(&port),
+            PortMapping {
+                private_port: 53,
+                public_port: Some(5353),
+                host_ip: Some(IpAddr::from([127, 0, 0, 1])),
+                protocol: expected_protocol,
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_summary_port_published_over_tcp_and_udp() -> anyhow::Result<()> {
+        let port = |typ| Port {
+            private_port: 53,
+            public_port: Some(5353),
+            typ: Some(typ),
+            ..Default::default()
+        };
+        let summary = ContainerSummary {
+            ports: Some(vec![
+                port(PortTypeEnum::TCP),
+                port(PortTypeEnum::UDP),
+                Port {
+                    private_port: 80,
+                    public_port: Some(8080),
+                    typ: Some(PortTypeEnum::TCP),
+                    ..Default::default()
+                },
+            ]),
+            ..container_summary(
+                labels(&[("traefik.http.routers.dns.rule", "Host(`dns.local`)")]),
+                None,
+            )
+        };
+
+        let container = TraefikedContainer::try_from(summary)?;
+
+        assert_eq!(container.public_ports(), vec![5353, 8080]);
+        assert_eq!(container.private_ports(), vec![53, 80]);
+        assert_eq!(container.published_port(53), Some(5353));
+        assert_eq!(
+            container
+                .ports
+                .iter()
+                .map(|port| port.protocol)
+                .collect::<Vec<_>>(),
+            vec![PortProtocol::Tcp, PortProtocol::Udp, PortProtocol::Tcp]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_summary_weighted_services() -> anyhow::Result<()> {
+        let weighted_labels = [
+            (
+                "traefik.http.services.canary.weighted.services[0].name",
+                "app-v1",
+            ),
+            (
+                "traefik.http.services.canary.weighted.services[1].name",
+                "app-v2",
+            ),
+        ];
+
+        // Weighted services do not count as the sole service of a single router
+        let container = TraefikedContainer::from_summary(
+            container_summary(
+                labels(
+                    &[
+                        &weighted_labels[..],
+                        &[
+                            ("traefik.http.routers.app.rule", "Host(`app.local`)"),
+                            ("traefik.http.services.app.loadbalancer.server.port", "8080"),
+                        ],
+                    ]
+                    .concat(),
+                ),
+                Some(7878),
+            ),
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        let TraefikedContainerConfig::SinglePort(config) = container.config else {
+            panic!("Should be a single port config");
+        };
+        assert_eq!(config.target_ports, vec![8080]);
+        assert_eq!(container.weighted_services["canary"].len(), 2);
+
+        // A container declaring only weighted services has no routers
+        let container = TraefikedContainer::from_summary(
+            container_summary(labels(&weighted_labels), None),
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        assert!(matches!(
+            container.config,
+            TraefikedContainerConfig::MultiplePorts(configs) if configs.is_empty()
+        ));
+        assert_eq!(container.weighted_services["canary"].len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_single_router() -> Result<(), ContainerConversionError> {
+        let container = TraefikedContainer::builder("my.app")
+            .published_port(8080, 32771)
+            .router("app", "Host(`app.local`)")
+            .service("app", 8080)
+            .build()?;
+
+        assert_eq!(container.service_name, "my-app");
+        let TraefikedContainerConfig::SinglePort(config) = container.config else {
+            panic!("Should be a single port config");
+        };
+        assert_eq!(config.target_ports, vec![8080]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_multiple_routers() -> Result<(), ContainerConversionError> {
+        let container = TraefikedContainer::builder("my-app")
+            .router("metrics", "Host(`metrics.local`)")
+            .router("app", "Host(`app.local`)")
+            .router_service("metrics", "metrics")
+            .service("metrics", 9100)
+            .service("web", 8080)
+            .build()?;
+
+        let TraefikedContainerConfig::MultiplePorts(configs) = container.config else {
+            panic!("Should be a multiple ports config");
+        };
+        assert_eq!(
+            configs
+                .iter()
+                .map(|c| (
+                    c.config.router_name.as_str(),
+                    c.service_name.as_str(),
+                    c.target_ports.clone()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("app", "web", vec![8080]),
+                ("metrics", "metrics", vec![9100])
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_invalid() {
+        assert!(matches!(
+            TraefikedContainer::builder("my-app")
+                .public_port(80)
+                .build(),
+            Err(ContainerConversionError::NoTraefikLabels)
+        ));
+        assert!(matches!(
+            TraefikedContainer::builder("my-app")
+                .router("app", "Host(`app.local`)")
+                .router("admin", "Host(`admin.local`)")
+                .build(),
+            Err(ContainerConversionError::InvalidLabels(_))
+        ));
+    }
+
+    #[test]
+    fn test_serde_round_trip() -> anyhow::Result<()> {
+        let container = TraefikedContainer::from_summary(
+            container_summary(
+                labels(&[
+                    ("traefik.http.routers.app.rule", "Host(`app.local`)"),
+                    ("traefik.http.routers.app.service", "app"),
+                    ("traefik.http.routers.app.middlewares", "strip,auth"),
+                    ("traefik.http.routers.app.tls.domains[0].main", "app.local"),
+                    ("traefik.http.services.app.loadbalancer.server.port", "80"),
+                    (
+                        "traefik.http.services.app.loadbalancer.healthcheck.path",
+                        "/health",
+                    ),
+                    ("traefik.http.routers.api.rule", "Host(`api.local`)"),
+                    ("traefik.http.routers.api.service", "api"),
+                    (
+                        "traefik.http.services.api.loadbalancer.server.url",
+                        "https://api.local:8443",
+                    ),
+                    ("traefik.http.services.api.loadbalancer.server.port", "80"),
+                    (
+                        "traefik.http.middlewares.strip.stripprefix.prefixes",
+                        "/app",
+                    ),
+                    ("traefik.http.middlewares.auth.basicauth.users", "user:hash"),
+                    ("traefik.http.middlewares.limit.ratelimit.average", "100"),
+                    (
+                        "traefik.http.middlewares.headers.headers.customrequestheaders.X-App",
+                        "app",
+                    ),
+                    (
+                        "traefik.http.serverstransports.internal.insecureskipverify",
+                        "true",
+                    ),
+                    ("traefik.tcp.routers.db.rule", "HostSNI(`*`)"),
+                    ("traefik.udp.routers.dns.entrypoints", "dns"),
+                    (
+                        "traefik.http.services.canary.weighted.services[0].name",
+                        "app",
+                    ),
+                ]),
+                Some(7878),
+            ),
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        assert_eq!(container.middlewares.len(), 4);
+        assert_eq!((container.tcp.len(), container.udp.len()), (1, 1));
+
+        let json = serde_json::to_string(&container)?;
+
+        assert_eq!(
+            serde_json::from_str::<TraefikedContainer>(&json)?,
+            container
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(false, "project-web-1", "project-web-1", None)]
+    #[case(true, "web", "web", Some("project"))]
+    fn test_from_summary_compose_service_name(
+        #[case] use_compose_service_name: bool,
+        #[case] expected_service_name: &str,
+        #[case] expected_router_name: &str,
+        #[case] expected_compose_project: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut summary = container_summary(
+            labels(&[
+                ("com.docker.compose.service", "web"),
+                ("com.docker.compose.project", "project"),
+            ]),
+            Some(7878),
+        );
+        summary.names = Some(vec!["/project-web-1".to_owned()]);
+
+        let container = TraefikedContainer::from_summary(
+            summary,
+            &DockerOptions {
+                default_rule: Some("Host(`{name}.local`)".to_owned()),
+                use_compose_service_name,
+                ..Default::default()
+            },
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        let TraefikedContainerConfig::SinglePort(config) = &container.config else {
+            panic!("Should be a single port config");
+        };
+        assert_eq!(container.service_name, expected_service_name);
+        assert_eq!(config.router_name, expected_router_name);
+        assert_eq!(config.rule, "Host(`project-web-1.local`)");
+        assert_eq!(
+            container.compose_project.as_deref(),
+            expected_compose_project
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_summary_aliases() -> anyhow::Result<()> {
+        let mut summary = container_summary(
+            labels(&[("traefik.http.routers.app.rule", "Host(`app.local`)")]),
+            Some(7878),
+        );
+        summary.names = Some(vec!["/proxy/my-app-alias".to_owned(), "/my-app".to_owned()]);
+
+        let container = TraefikedContainer::from_summary(
+            summary,
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        assert_eq!(container.name, "my-app");
+        assert_eq!(container.aliases, vec!["my-app", "proxy/my-app-alias"]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(
+        &[("traefik.http.services.nas.loadbalancer.server.url", "http://192.168.1.50:5000")],
+        false,
+        true
+    )]
+    #[case(&[], false, false)]
+    #[case(&[], true, true)]
+    #[case(
+        &[
+            ("traefik.http.services.nas.loadbalancer.server.url", "http://192.168.1.50:5000"),
+            ("traefik.http.routers.auth.rule", "Host(`auth.local`)"),
+            ("traefik.http.routers.auth.service", "central-auth@file"),
+        ],
+        false,
+        true
+    )]
+    #[case(
+        &[
+            ("traefik.http.services.nas.loadbalancer.server.url", "http://192.168.1.50:5000"),
+            ("traefik.tcp.routers.db.rule", "HostSNI(`*`)"),
+        ],
+        false,
+        false
+    )]
+    fn test_from_summary_without_ports(
+        #[case] pairs: &[(&str, &str)],
+        #[case] allow_empty_services: bool,
+        #[case] expected_ok: bool,
+    ) {
+        let mut labels = labels(pairs);
+        labels.insert(
+            "traefik.http.routers.nas.rule".to_owned(),
+            "Host(`nas.local`)".to_owned(),
+        );
+        let summary = ContainerSummary {
+            ports: None,
+            ..container_summary(labels, None)
+        };
+
+        let container = TraefikedContainer::from_summary(
+            summary,
+            &DockerOptions {
+                allow_empty_services,
+                ..Default::default()
+            },
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        );
+
+        match container {
+            Ok(container) => {
+                assert!(expected_ok);
+                assert!(container.ports.is_empty());
+            }
+            Err(e) => {
+                assert!(!expected_ok);
+                assert!(matches!(e, ContainerConversionError::NoPorts));
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_summary_default_rule() -> anyhow::Result<()> {
+        let container = TraefikedContainer::from_summary(
+            container_summary(
+                labels(&[(
+                    "traefik.http.services.my-app.loadbalancer.server.port",
+                    "8080",
+                )]),
+                Some(7878),
+            ),
+            &DockerOptions {
+                default_rule: Some("Host(`{name}.my-domain.com`)".to_owned()),
+                ..Default::default()
+            },
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        let TraefikedContainerConfig::SinglePort(config) = container.config else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.router_name, "my-app");
+        assert_eq!(config.rule, "Host(`my-app.my-domain.com`)");
+        assert_eq!(config.target_ports, vec![8080]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_summary_default_rule_keeps_rule_label() -> anyhow::Result<()> {
+        let container = TraefikedContainer::from_summary(
+            container_summary(
+                labels(&[("traefik.http.routers.app.rule", "Host(`app.local`)")]),
+                Some(7878),
+            ),
+            &DockerOptions {
+                default_rule: Some("Host(`{name}.my-domain.com`)".to_owned()),
+                ..Default::default()
+            },
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        let TraefikedContainerConfig::SinglePort(config) = container.config else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.router_name, "app");
+        assert_eq!(config.rule, "Host(`app.local`)");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(&[], "my-app", "Host(`grafana.my-domain.com`)")]
+    #[case(&[("traefik.http.routers.app.rule", "Host(`app.local`)")], "app", "Host(`app.local`)")]
+    fn test_from_summary_hostname(
+        #[case] pairs: &[(&str, &str)],
+        #[case] expected_router: &str,
+        #[case] expected_rule: &str,
+    ) -> anyhow::Result<()> {
+        let mut labels = labels(pairs);
+        labels.insert("traefik-provider.hostname".to_owned(), "grafana".to_owned());
+
+        let container = TraefikedContainer::from_summary(
+            container_summary(labels, Some(7878)),
+            &DockerOptions {
+                default_rule: Some("Host(`{name}.local`)".to_owned()),
+                domain: Some("my-domain.com".to_owned()),
+                ..Default::default()
+            },
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        let TraefikedContainerConfig::SinglePort(config) = container.config else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.router_name, expected_router);
+        assert_eq!(config.rule, expected_rule);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_summary_hostname_without_domain() {
+        let container = TraefikedContainer::from_summary(
+            container_summary(
+                labels(&[("traefik-provider.hostname", "grafana")]),
+                Some(7878),
+            ),
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        );
+
+        assert!(matches!(
+            container,
+            Err(ContainerConversionError::InvalidPlaceholder { label, .. })
+                if label == "traefik-provider.hostname"
+        ));
+    }
+
+    #[test]
+    fn test_from_summary_base_url() -> anyhow::Result<()> {
+        let container = TraefikedContainer::from_summary(
+            container_summary(
+                labels(&[
+                    ("traefik.http.routers.app.rule", "Host(`app.local`)"),
+                    ("traefik-provider.base-url", " http://10.0.0.7 "),
+                ]),
+                Some(7878),
+            ),
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        assert_eq!(container.base_url, Some(Url::parse("http://10.0.0.7")?));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("10.0.0.7")]
+    #[case("http://")]
+    #[case("unix:/var/run/app.sock")]
+    fn test_from_summary_invalid_base_url(#[case] base_url: &str) {
+        let container = TraefikedContainer::from_summary(
+            container_summary(
+                labels(&[
+                    ("traefik.http.routers.app.rule", "Host(`app.local`)"),
+                    ("traefik-provider.base-url", base_url),
+                ]),
+                Some(7878),
+            ),
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        );
+
+        assert!(matches!(
+            container,
+            Err(ContainerConversionError::InvalidBaseUrl { value, .. }) if value == base_url
+        ));
+    }
+
+    #[test]
+    fn test_from_summary_placeholders() -> anyhow::Result<()> {
+        let container = TraefikedContainer::from_summary(
+            container_summary(
+                labels(&[(
+                    "traefik.http.routers.app.rule",
+                    "Host(`{{ container_name }}.{{ domain }}`)",
+                )]),
+                Some(7878),
+            ),
+            &DockerOptions {
+                domain: Some("my-domain.com".to_owned()),
+                ..Default::default()
+            },
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        let TraefikedContainerConfig::SinglePort(config) = container.config else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.rule, "Host(`my-app.my-domain.com`)");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(&[("traefik.http.routers.app.rule", "Host(`{{ name }}.local`)")], None)]
+    #[case(&[("traefik.http.routers.app.rule", "Host(`app.{{ domain }}`)")], None)]
+    #[case(&[], Some("Host(`{name}.{{ domain }}`)"))]
+    fn test_from_summary_placeholders_invalid(
+        #[case] pairs: &[(&str, &str)],
+        #[case] default_rule: Option<&str>,
+    ) {
+        let container = TraefikedContainer::from_summary(
+            container_summary(labels(pairs), Some(7878)),
+            &DockerOptions {
+                default_rule: default_rule.map(str::to_owned),
+                ..Default::default()
+            },
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        );
+
+        assert!(container.is_err());
+    }
+
+    #[rstest]
+    #[case(&[], &["proxy"], Ok("172.20.0.5"))]
+    #[case(&[("traefik.docker.network", "backend")], &["proxy", "backend"], Ok("172.21.0.5"))]
+    #[case(&[("traefik.docker.network", "backend")], &["proxy"], Err(()))]
+    #[case(&[], &["proxy", "backend"], Err(()))]
+    #[case(&[], &[], Err(()))]
+    fn test_from_summary_ip_address(
+        #[case] pairs: &[(&str, &str)],
+        #[case] networks: &[&str],
+        #[case] expected: Result<&str, ()>,
+    ) -> anyhow::Result<()> {
+        let ip_addresses = HashMap::from([("proxy", "172.20.0.5"), ("backend", "172.21.0.5")]);
+        let mut summary = container_summary(
+            labels(
+                &[
+                    &[("traefik.http.routers.app.rule", "Host(`app.local`)")],
+                    pairs,
+                ]
+                .concat(),
+            ),
+            Some(7878),
+        );
+        summary.network_settings = Some(ContainerSummaryNetworkSettings {
+            networks: Some(
+                networks
+                    .iter()
+                    .map(|network| {
+                        (
+                            network.to_string(),
+                            EndpointSettings {
+                                ip_address: Some(ip_addresses[network].to_owned()),
+                                ..Default::default()
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+        });
+
+        let container = TraefikedContainer::from_summary(
+            summary,
+            &DockerOptions::default(),
+            &LabelRegexes::default(),
+            &Diagnostics::default(),
+        )?;
+
+        assert_eq!(container.private_ports(), vec![80]);
+        assert_eq!(container.published_port(80), Some(7878));
+        assert_eq!(container.ip_address().map_err(|_| ()), expected);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case(&[], None)]
+    #[case(&[("traefik.tcp.routers.db.rule", "HostSNI(`*`)")], Some(7878))]
+    fn test_default_router_not_generated(
+        #[case] pairs: &[(&str, &str)],
+        #[case] public_port: Option<u16>,
+    ) {
+        let container = container_summary(labels(pairs), public_port);
+
+        assert_eq!(
+            default_router(&container, &LabelRegexes::default(), "Host(`{name}.local`)"),
+            None
+        );
+    }
+
+    #[rstest]
+    #[case(&[("traefik.port", "8080")], Some(8080))]
+    #[case(&[("Traefik.Port", " 8080 ")], Some(8080))]
+    #[case(&[("traefik.port", "http")], None)]
+    #[case(
+        &[
+            ("traefik.port", "8080"),
+            ("traefik.http.services.app.loadbalancer.server.port", "9090"),
+        ],
+        Some(9090)
+    )]
+    fn test_extract_traefik_config_legacy_port(
+        #[case] port_labels: &[(&str, &str)],
+        #[case] expected: Option<u16>,
+    ) {
+        let mut labels = labels(port_labels);
+        labels.insert(
+            "traefik.http.routers.app.rule".to_owned(),
+            "Host(`a.my-domain.com`)".to_owned(),
+        );
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.target_ports, Vec::from_iter(expected));
+    }
+
+    #[test]
+    fn test_extract_traefik_config_single_port_scheme() {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            (
+                "traefik.http.services.app.loadbalancer.server.scheme",
+                "HTTPS",
+            ),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.load_balancer.scheme, Some("https".to_owned()));
+    }
+
+    #[rstest]
+    #[case("https", Some("https"))]
+    #[case("h2c", Some("h2c"))]
+    #[case("ftp", None)]
+    fn test_extract_traefik_config_multi_port_scheme(
+        #[case] scheme: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let labels = labels(&[
+            (
+                "traefik.http.routers.a-router.rule",
+                "Host(`a.my-domain.com`)",
+            ),
+            (
+                "traefik.http.routers.b-router.rule",
+                "Host(`b.my-domain.com`)",
+            ),
+            (
+                "traefik.http.services.alpha.loadbalancer.server.port",
+                "8081",
+            ),
+            (
+                "traefik.http.services.alpha.loadbalancer.server.scheme",
+                scheme,
+            ),
+            (
+                "traefik.http.services.zeta.loadbalancer.server.port",
+                "8082",
+            ),
+        ]);
+
+        let Some(TraefikedContainerConfig::MultiplePorts(configs)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a multi port config");
+        };
+
+        assert_eq!(configs[0].config.load_balancer.scheme.as_deref(), expected);
+        assert_eq!(configs[1].config.load_balancer.scheme, None);
+    }
+
+    #[test]
+    fn test_extract_traefik_config_server_url() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            (
+                "traefik.http.services.app.loadbalancer.server.url",
+                "https://10.0.0.5:9443/internal/",
+            ),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )?
+        else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(
+            config.load_balancer.url,
+            Some(Url::parse("https://10.0.0.5:9443/internal/")?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_traefik_config_invalid_server_url() {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            (
+                "traefik.http.services.app.loadbalancer.server.url",
+                "not a url",
+            ),
+        ]);
+
+        let error = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("Invalid server URL 'not a url'"));
+    }
+
+    #[rstest]
+    #[case("true", Some(true))]
+    #[case("FALSE", Some(false))]
+    #[case("yes", None)]
+    fn test_extract_traefik_config_pass_host_header(
+        #[case] pass_host_header: &str,
+        #[case] expected: Option<bool>,
+    ) {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            (
+                "traefik.http.services.app.loadbalancer.passhostheader",
+                pass_host_header,
+            ),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.load_balancer.pass_host_header, expected);
+    }
+
+    #[test]
+    fn test_extract_traefik_config_health_check() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            (
+                "traefik.http.services.app.loadbalancer.healthcheck.path",
+                "/health",
+            ),
+            (
+                "traefik.http.services.app.loadbalancer.healthcheck.interval",
+                "10s",
+            ),
+            (
+                "traefik.http.services.app.loadbalancer.healthcheck.timeout",
+                "3s",
+            ),
+            (
+                "traefik.http.services.app.loadbalancer.healthcheck.port",
+                "8081",
+            ),
+            (
+                "traefik.http.services.app.loadbalancer.HealthCheck.Scheme",
+                "https",
+            ),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )?
+        else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(
+            config.load_balancer.health_check,
+            Some(TraefikedHealthCheckConfig {
+                path: "/health".to_owned(),
+                interval: Some("10s".to_owned()),
+                timeout: Some("3s".to_owned()),
+                port: Some(8081),
+                scheme: Some("https".to_owned()),
+            })
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("interval", "10s", "requires a 'loadbalancer.healthcheck.path' label")]
+    #[case(
+        "port",
+        "eighty",
+        "Invalid port 'eighty' in label 'traefik.http.services.app.loadbalancer.healthcheck.port'"
+    )]
+    fn test_extract_traefik_config_invalid_health_check(
+        #[case] option: &str,
+        #[case] value: &str,
+        #[case] expected_error: &str,
+    ) {
+        let mut labels = labels(&[("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)")]);
+        labels.insert(
+            format!("traefik.http.services.app.loadbalancer.healthcheck.{option}"),
+            value.to_owned(),
+        );
+        if option != "interval" {
+            labels.insert(
+                "traefik.http.services.app.loadbalancer.healthcheck.path".to_owned(),
+                "/health".to_owned(),
+            );
+        }
+
+        let error = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains(expected_error));
+    }
+
+    #[rstest]
+    #[case(&[("cookie", "true")], Some(TraefikedStickyConfig::default()))]
+    #[case(&[("cookie", "false")], None)]
+    #[case(
+        &[("cookie.name", "session"), ("cookie.secure", "true"), ("cookie.httponly", "TRUE")],
+        Some(TraefikedStickyConfig {
+            cookie: TraefikedStickyCookieConfig {
+                name: Some("session".to_owned()),
+                secure: Some(true),
+                http_only: Some(true),
+                same_site: None,
+            },
+        }),
+    )]
+    fn test_extract_traefik_config_sticky_cookie(
+        #[case] options: &[(&str, &str)],
+        #[case] expected: Option<TraefikedStickyConfig>,
+    ) {
+        let mut labels = labels(&[("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)")]);
+        for (option, value) in options {
+            labels.insert(
+                format!("traefik.http.services.app.loadbalancer.sticky.{option}"),
+                value.to_string(),
+            );
+        }
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.load_balancer.sticky, expected);
+    }
+
+    #[test]
+    fn test_extract_traefik_config_servers_transport() {
+        let labels = labels(&[
+            ("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)"),
+            (
+                "traefik.http.services.app.loadbalancer.serversTransport",
+                "my-transport@file",
+            ),
+        ]);
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(
+            config.load_balancer.servers_transport,
+            Some("my-transport@file".to_owned())
+        );
+    }
+
+    #[rstest]
+    #[case(&[], None)]
+    #[case(
+        &[("traefik.http.services.app.loadbalancer.responseForwarding.flushInterval", "1ms")],
+        Some("1ms")
+    )]
+    fn test_extract_traefik_config_flush_interval(
+        #[case] pairs: &[(&str, &str)],
+        #[case] expected: Option<&str>,
+    ) {
+        let labels = labels(
+            &[
+                &[("traefik.http.routers.app.rule", "Host(`a.my-domain.com`)")],
+                pairs,
+            ]
+            .concat(),
+        );
+
+        let Some(TraefikedContainerConfig::SinglePort(config)) = extract_traefik_config(
+            &labels,
+            &LabelRegexes::default(),
+            true,
+            None,
+            &Diagnostics::default(),
+        )
+        .unwrap() else {
+            panic!("Should be a single port config");
+        };
+
+        assert_eq!(config.load_balancer.flush_interval.as_deref(), expected);
+    }
+
+    /// Labels of a synthetic container: two routers with TLS and middlewares, their services and
+    /// 30 compose and orchestration labels.
+    fn bench_labels(index: usize) -> HashMap<String, String> {
+        let mut labels: HashMap<String, String> = (0..30)
+            .map(|n| {
+                (
+                    format!("com.docker.compose.label-{}", n),
+                    format!("value-{}", index),
+                )
+            })
+            .collect();
+        for router in ["web", "api"] {
+            let name = format!("{}-{}", router, index);
+            labels.extend([
+                (
+                    format!("traefik.http.routers.{}.rule", name),
+                    format!("Host(`{}.my-domain.com`)", name),
+                ),
+                (
+                    format!("traefik.http.routers.{}.entrypoints", name),
+                    "websecure".to_owned(),
+                ),
+                (
+                    format!("traefik.http.routers.{}.tls", name),
+                    "true".to_owned(),
+                ),
+                (
+                    format!("traefik.http.routers.{}.tls.certresolver", name),
+                    "le".to_owned(),
+                ),
+                (
+                    format!("traefik.http.routers.{}.middlewares", name),
+                    "auth@file".to_owned(),
+                ),
+                (
+                    format!("traefik.http.routers.{}.service", name),
+                    name.clone(),
+                ),
+                (
+                    format!("traefik.http.services.{}.loadbalancer.server.port", name),
+                    "80".to_owned(),
+                ),
+                (
+                    format!(
+                        "traefik.http.services.{}.loadbalancer.healthcheck.path",
+                        name
+                    ),
+                    "/health".to_owned(),
+                ),
+            ]);
+        }
+        labels
+    }
+
+    /// Times the conversion of 500 containers and compares sorting their HTTP labels by option in
+    /// a single pass with running the regex of every option on all of them.
+    ///
+    /// Run with `cargo test --release bench_from_summary -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_from_summary_500_containers() {
+        let regexes = LabelRegexes::default();
+        let summaries: Vec<ContainerSummary> = (0..500)
+            .map(|index| container_summary(bench_labels(index), Some(8080)))
+            .collect();
+        let own_labels: Vec<HashMap<String, String>> = (0..500)
+            .map(|index| regexes.own_labels(bench_labels(index)))
+            .collect();
+
+        let start = std::time::Instant::now();
+        for summary in summaries {
+            TraefikedContainer::from_summary(
+                summary,
+                &DockerOptions::default(),
+                &regexes,
+                &Diagnostics::default(),
+            )
+            .unwrap();
+        }
+        println!("from_summary: {:?} for 500 containers", start.elapsed());
+
+        let start = std::time::Instant::now();
+        for labels in &own_labels {
+            let http_labels = HttpLabels::new(labels, &regexes);
+            for option in HttpOption::ALL {
+                std::hint::black_box(http_labels.capture(option));
+            }
+        }
+        let single_pass = start.elapsed();
+
+        let start = std::time::Instant::now();
+        for labels in &own_labels {
+            for option in HttpOption::ALL {
+                std::hint::black_box(capture_labels(labels, regexes.http_option(option)));
+            }
+        }
+        let pass_per_option = start.elapsed();
+
+        println!(
+            "HTTP labels: {:?} in a single pass, {:?} with a pass per option",
+            single_pass, pass_per_option
+        );
+    }
 }
@@ -1,17 +1,28 @@
 use anyhow::Context;
-use axum::http::StatusCode;
+use axum::extract::Query;
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::{Extension, Json, Router};
+use serde::Deserialize;
 use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
 use thiserror::Error;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use url::Url;
 
-use traefik_docker_http_provider_server::docker::get_traefik_labeled_containers;
+use traefik_docker_http_provider_server::docker::{get_traefik_labeled_containers, DockerOptions};
 use traefik_docker_http_provider_server::dynamic_configuration::{
-    DynamicConfiguration, DynamicConfigurationBuilder,
+    CollisionPolicy, ContainerErrorPolicy, DynamicConfiguration, DynamicConfigurationBuilder,
+    EmptyConfigurationPolicy, ExtraConfigurationPolicy, NamePattern, OutputStyle,
+    ServerPathSegment, YamlContentType,
+};
+use traefik_docker_http_provider_server::error::{
+    SerializationError, ValidationError, ValidationErrors,
 };
 
 #[tokio::main]
@@ -41,29 +52,440 @@ fn app() -> anyhow::Result<Router> {
         .route("/dynamic_configuration", get(dynamic_configuration))
         .layer(TraceLayer::new_for_http())
         .layer(Extension(
-            std::env::var("BASE_URL")
+            env_var("BASE_URL")
                 .context("Cannot get base URL")?
                 .parse::<Url>()?,
-        ));
+        ))
+        .layer(Extension(docker_options()?))
+        .layer(Extension(server_options()?))
+        .layer(Extension(LastConfiguration::default()));
 
     Ok(app)
 }
 
+fn docker_options() -> anyhow::Result<DockerOptions> {
+    let mut options = DockerOptions::default();
+
+    options.exposed_by_default =
+        parse_env("EXPOSED_BY_DEFAULT")?.unwrap_or(options.exposed_by_default);
+
+    options.default_rule = env_var("DEFAULT_RULE");
+
+    if let Some(label_prefix) = env_var("LABEL_PREFIX") {
+        options.label_prefix = label_prefix;
+    }
+
+    options.domain = env_var("DOMAIN");
+
+    options.constraint = parse_env("CONSTRAINT")?;
+
+    if let Some(exclude_labels) = parse_env_list("EXCLUDE_LABELS")? {
+        options.exclude_labels = exclude_labels;
+    }
+
+    options.use_compose_service_name =
+        parse_env("USE_COMPOSE_SERVICE_NAME")?.unwrap_or(options.use_compose_service_name);
+
+    options.strict = parse_env("STRICT")?.unwrap_or(options.strict);
+
+    options.resolve_env = parse_env("RESOLVE_ENV")?.unwrap_or(options.resolve_env);
+
+    options.allow_empty_services =
+        parse_env("ALLOW_EMPTY_SERVICES")?.unwrap_or(options.allow_empty_services);
+
+    Ok(options)
+}
+
+/// Options controlling how the dynamic configuration is built from the containers and served.
+#[derive(Clone, Debug)]
+struct ServerOptions {
+    /// Target containers on their IP address and private ports rather than on the base URL
+    /// and public ports, the network being chosen by the `traefik.docker.network` label.
+    use_container_ip: bool,
+    /// Leave out containers whose name matches any of these patterns, e.g. `ops-*`.
+    exclude_containers: Vec<NamePattern>,
+    /// What to do when several containers declare routers or services with the same name.
+    collision_policy: CollisionPolicy,
+    /// Emit the priority Traefik gives HTTP routers without a `priority` label, the rule length.
+    compute_priorities: bool,
+    /// Use the `https` scheme for HTTP services targeting port 443 or 8443, unless their
+    /// `server.scheme` label says otherwise.
+    detect_https: bool,
+    /// Entrypoints of the HTTP and TCP routers without an `entrypoints` label.
+    default_entrypoints: Vec<String>,
+    /// Middlewares appended to every generated HTTP router, unless its `middlewares` label is empty.
+    default_middlewares: Vec<String>,
+    /// Enable TLS on every generated HTTP router without TLS labels, unless labeled `tls=false`.
+    default_tls: bool,
+    /// Certificate resolver of the routers TLS is enabled on by default, which implies `default_tls`.
+    default_tls_cert_resolver: Option<String>,
+    /// Certificate resolver of every generated router enabling TLS without naming one.
+    default_cert_resolver: Option<String>,
+    /// Servers transport of every generated HTTP load balancer without a `serverstransport` label.
+    default_servers_transport: Option<String>,
+    /// Path segment appended to the path of the base URL in server URLs.
+    server_path_segment: ServerPathSegment,
+    /// Prefix of the names of the generated routers and services.
+    name_prefix: String,
+    /// Whether server URLs without path end with a slash.
+    trailing_slash: bool,
+    /// Base URL of the servers using https, required unless https is only detected on port 443 or
+    /// 8443.
+    https_base_url: Option<Url>,
+    /// Other base URLs the containers are reachable through, each adding a server to HTTP services.
+    additional_base_urls: Vec<Url>,
+    /// Weight of the servers reached through each base URL.
+    server_weights: BTreeMap<Url, u32>,
+    /// YAML, JSON or TOML file of hand-written configuration merged with the generated one, read on
+    /// every request.
+    extra_configuration: Option<PathBuf>,
+    /// Which entry is kept when the extra configuration and a container declare the same name.
+    extra_configuration_policy: ExtraConfigurationPolicy,
+    /// What is answered when no container (nor the extra configuration) declares anything.
+    empty_configuration_policy: EmptyConfigurationPolicy,
+    /// Media type the configuration is sent as when served as YAML.
+    yaml_content_type: YamlContentType,
+    /// Layout of the served configuration, unless a request asks for another one.
+    output_style: OutputStyle,
+    /// What happens when a container cannot be added, skipped containers being logged.
+    container_error_policy: ContainerErrorPolicy,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            use_container_ip: false,
+            exclude_containers: vec![],
+            collision_policy: CollisionPolicy::default(),
+            compute_priorities: false,
+            detect_https: false,
+            default_entrypoints: vec![],
+            default_middlewares: vec![],
+            default_tls: false,
+            default_tls_cert_resolver: None,
+            default_cert_resolver: None,
+            default_servers_transport: None,
+            server_path_segment: ServerPathSegment::default(),
+            name_prefix: String::new(),
+            trailing_slash: true,
+            https_base_url: None,
+            additional_base_urls: vec![],
+            server_weights: BTreeMap::new(),
+            extra_configuration: None,
+            extra_configuration_policy: ExtraConfigurationPolicy::default(),
+            empty_configuration_policy: EmptyConfigurationPolicy::default(),
+            yaml_content_type: YamlContentType::default(),
+            output_style: OutputStyle::default(),
+            container_error_policy: ContainerErrorPolicy::SkipAndReport,
+        }
+    }
+}
+
+fn server_options() -> anyhow::Result<ServerOptions> {
+    let mut options = ServerOptions::default();
+
+    options.use_container_ip = parse_env("USE_CONTAINER_IP")?.unwrap_or(options.use_container_ip);
+
+    if let Some(exclude_containers) = parse_env_list("EXCLUDE_CONTAINERS")? {
+        options.exclude_containers = exclude_containers;
+    }
+
+    options.collision_policy = parse_env("COLLISION_POLICY")?.unwrap_or(options.collision_policy);
+
+    options.compute_priorities =
+        parse_env("COMPUTE_PRIORITIES")?.unwrap_or(options.compute_priorities);
+
+    options.detect_https = parse_env("DETECT_HTTPS")?.unwrap_or(options.detect_https);
+
+    if let Some(default_entrypoints) = parse_env_list("DEFAULT_ENTRYPOINTS")? {
+        options.default_entrypoints = default_entrypoints;
+    }
+
+    if let Some(default_middlewares) = parse_env_list("DEFAULT_MIDDLEWARES")? {
+        options.default_middlewares = default_middlewares;
+    }
+
+    options.default_tls = parse_env("DEFAULT_TLS")?.unwrap_or(options.default_tls);
+
+    options.default_tls_cert_resolver = env_var("DEFAULT_TLS_CERT_RESOLVER");
+
+    options.default_cert_resolver = env_var("DEFAULT_CERT_RESOLVER");
+
+    options.default_servers_transport = env_var("DEFAULT_SERVERS_TRANSPORT");
+
+    options.server_path_segment =
+        parse_env("SERVER_PATH_SEGMENT")?.unwrap_or(options.server_path_segment);
+
+    options.https_base_url = parse_env("HTTPS_BASE_URL")?;
+
+    if let Some(additional_base_urls) = parse_env_list("ADDITIONAL_BASE_URLS")? {
+        options.additional_base_urls = additional_base_urls;
+    }
+
+    if let Some(server_weights) = env_var("SERVER_WEIGHTS") {
+        options.server_weights = server_weights
+            .split(',')
+            .map(str::trim)
+            .filter(|server_weight| !server_weight.is_empty())
+            .map(|server_weight| {
+                let (base_url, weight) = server_weight
+                    .rsplit_once('=')
+                    .ok_or_else(|| anyhow::anyhow!("Missing weight in '{}'", server_weight))?;
+                Ok((base_url.trim().parse()?, weight.trim().parse()?))
+            })
+            .collect::<anyhow::Result<_>>()
+            .context("SERVER_WEIGHTS must be comma-separated <base url>=<weight> pairs")?;
+    }
+
+    options.trailing_slash = parse_env("TRAILING_SLASH")?.unwrap_or(options.trailing_slash);
+
+    if let Some(name_prefix) = env_var("NAME_PREFIX") {
+        options.name_prefix = name_prefix;
+    }
+
+    options.extra_configuration = env_var("EXTRA_CONFIGURATION").map(PathBuf::from);
+
+    options.extra_configuration_policy =
+        parse_env("EXTRA_CONFIGURATION_POLICY")?.unwrap_or(options.extra_configuration_policy);
+
+    options.empty_configuration_policy =
+        parse_env("EMPTY_CONFIGURATION_POLICY")?.unwrap_or(options.empty_configuration_policy);
+
+    options.container_error_policy =
+        parse_env("CONTAINER_ERROR_POLICY")?.unwrap_or(options.container_error_policy);
+
+    options.yaml_content_type =
+        parse_env("YAML_CONTENT_TYPE")?.unwrap_or(options.yaml_content_type);
+
+    options.output_style = parse_env("OUTPUT_STYLE")?.unwrap_or(options.output_style);
+
+    Ok(options)
+}
+
+/// Parses the comma-separated items of the environment variable `name`, if set, skipping empty
+/// ones, e.g. `DEFAULT_ENTRYPOINTS=web, websecure`.
+fn parse_env_list<T>(name: &str) -> anyhow::Result<Option<Vec<T>>>
+where
+    T: FromStr,
+    T::Err: Into<anyhow::Error>,
+{
+    let Some(value) = env_var(name) else {
+        return Ok(None);
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.parse()
+                .map_err(|e: T::Err| e.into().context(format!("Invalid {} '{}'", name, item)))
+        })
+        .collect::<anyhow::Result<_>>()
+        .map(Some)
+}
+
+/// Value of the environment variable `name`, trimmed, an empty value counting as unset as in
+/// `HTTPS_BASE_URL=` of an env file.
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+/// Parses the environment variable `name`, if set.
+fn parse_env<T>(name: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Into<anyhow::Error>,
+{
+    let Some(value) = env_var(name) else {
+        return Ok(None);
+    };
+    value
+        .parse()
+        .map(Some)
+        .map_err(|e: T::Err| e.into().context(format!("Invalid {} '{}'", name, value)))
+}
+
 async fn health_check() -> impl IntoResponse {
     Json(json!({"status": "ok"}))
 }
 
 async fn dynamic_configuration(
     Extension(base_url): Extension<Url>,
-) -> Result<DynamicConfiguration, AppError> {
-    let labeled_containers = get_traefik_labeled_containers().await?;
+    Extension(docker_options): Extension<DockerOptions>,
+    Extension(server_options): Extension<ServerOptions>,
+    Extension(last_configuration): Extension<LastConfiguration>,
+    Query(query): Query<DynamicConfigurationQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let output_style = match query.style {
+        Some(style) => style.parse().map_err(AppError::InvalidQuery)?,
+        None => server_options.output_style,
+    };
+    let format = match query.format {
+        Some(format) => format.parse().map_err(AppError::InvalidQuery)?,
+        None => ResponseFormat::accepted(&headers),
+    };
+    let labeled_containers = get_traefik_labeled_containers(&docker_options).await?;
+
+    let mut dynamic_configuration_builder = DynamicConfigurationBuilder::new(base_url)
+        .use_container_ip(server_options.use_container_ip)
+        .collision_policy(server_options.collision_policy)
+        .detect_https(server_options.detect_https)
+        .allow_empty_services(docker_options.allow_empty_services)
+        .default_entrypoints(server_options.default_entrypoints.clone())
+        .with_default_middlewares(server_options.default_middlewares.clone())
+        .exclude_names(server_options.exclude_containers.clone())
+        .strict(docker_options.strict)
+        .compute_priorities(server_options.compute_priorities)
+        .server_path_segment(server_options.server_path_segment)
+        .with_name_prefix(server_options.name_prefix.clone())
+        .trailing_slash(server_options.trailing_slash)
+        .extra_configuration_policy(server_options.extra_configuration_policy)
+        .container_error_policy(server_options.container_error_policy);
+    if server_options.default_tls || server_options.default_tls_cert_resolver.is_some() {
+        dynamic_configuration_builder = dynamic_configuration_builder
+            .with_default_tls(server_options.default_tls_cert_resolver.clone());
+    }
+    if let Some(cert_resolver) = &server_options.default_cert_resolver {
+        dynamic_configuration_builder =
+            dynamic_configuration_builder.with_default_cert_resolver(cert_resolver);
+    }
+    if let Some(servers_transport) = &server_options.default_servers_transport {
+        dynamic_configuration_builder =
+            dynamic_configuration_builder.with_default_servers_transport(servers_transport);
+    }
+    if let Some(https_base_url) = &server_options.https_base_url {
+        dynamic_configuration_builder =
+            dynamic_configuration_builder.with_https_base_url(https_base_url.clone());
+    }
+    for base_url in &server_options.additional_base_urls {
+        dynamic_configuration_builder =
+            dynamic_configuration_builder.with_additional_base_url(base_url.clone());
+    }
+    for (base_url, weight) in &server_options.server_weights {
+        dynamic_configuration_builder =
+            dynamic_configuration_builder.server_weight(base_url.clone(), *weight);
+    }
+    if let Some(path) = &server_options.extra_configuration {
+        dynamic_configuration_builder = dynamic_configuration_builder
+            .extra_configuration(DynamicConfiguration::from_file(path)?);
+    }
+    dynamic_configuration_builder =
+        dynamic_configuration_builder.add_containers(&labeled_containers)?;
+    for skipped_container in dynamic_configuration_builder.skipped_containers() {
+        tracing::warn!("{}", skipped_container);
+    }
+
+    let dynamic_configuration = dynamic_configuration_builder.build()?;
+    tracing::info!("Generated {}", dynamic_configuration.stats());
+    for line in dynamic_configuration.summary().lines() {
+        tracing::debug!("{}", line);
+    }
+    for issue in dynamic_configuration.validate() {
+        // Services without servers are intended when allowed
+        if docker_options.allow_empty_services
+            && matches!(issue.error, ValidationError::NoServers { .. })
+        {
+            continue;
+        }
+        tracing::warn!("{}", issue);
+    }
+
+    // Docker errors were answered above, so this really means nothing to route
+    if dynamic_configuration.is_empty()
+        && server_options.empty_configuration_policy == EmptyConfigurationPolicy::NoContent
+    {
+        tracing::debug!("Empty configuration, Traefik keeps its last one");
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+    last_configuration.log_changes(&dynamic_configuration);
+
+    Ok(match format {
+        ResponseFormat::Yaml => {
+            dynamic_configuration.to_yaml_response(server_options.yaml_content_type, output_style)
+        }
+        ResponseFormat::Json => {
+            let json = dynamic_configuration.to_json_styled(output_style)?;
+            ([(header::CONTENT_TYPE, "application/json")], json).into_response()
+        }
+        ResponseFormat::Toml => {
+            let toml = dynamic_configuration.to_toml()?;
+            ([(header::CONTENT_TYPE, "application/toml")], toml).into_response()
+        }
+    })
+}
+
+/// Query parameters of `/dynamic_configuration`, e.g. `?style=compact&format=json`.
+#[derive(Debug, Default, Deserialize)]
+struct DynamicConfigurationQuery {
+    style: Option<String>,
+    format: Option<String>,
+}
+
+/// Format of the served configuration, from a `format` query parameter or the `Accept` header.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum ResponseFormat {
+    #[default]
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl ResponseFormat {
+    /// The format the client asked for, e.g. with `Accept: application/json`, YAML being served
+    /// otherwise.
+    fn accepted(headers: &HeaderMap) -> ResponseFormat {
+        headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|media_type| media_type.split(';').next().unwrap_or_default().trim())
+            .find_map(
+                |media_type| match media_type.to_ascii_lowercase().as_str() {
+                    "application/json" => Some(ResponseFormat::Json),
+                    "application/toml" | "text/toml" => Some(ResponseFormat::Toml),
+                    _ => None,
+                },
+            )
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for ResponseFormat {
+    type Err = anyhow::Error;
 
-    let mut dynamic_configuration_builder = DynamicConfigurationBuilder::new(base_url);
-    for container in &labeled_containers {
-        dynamic_configuration_builder = dynamic_configuration_builder.add_container(container)?
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "yaml" => Ok(ResponseFormat::Yaml),
+            "json" => Ok(ResponseFormat::Json),
+            "toml" => Ok(ResponseFormat::Toml),
+            _ => Err(anyhow::anyhow!(
+                "Unknown format '{}', expected yaml, json or toml",
+                s
+            )),
+        }
     }
+}
 
-    Ok(dynamic_configuration_builder.build())
+/// Configuration served by the previous request, to log what changed since.
+#[derive(Clone, Default)]
+struct LastConfiguration(Arc<Mutex<Option<DynamicConfiguration>>>);
+
+impl LastConfiguration {
+    fn log_changes(&self, configuration: &DynamicConfiguration) {
+        let mut last = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(last) = last.as_ref() {
+            for change in last.diff(configuration).to_string().lines() {
+                tracing::info!("{}", change);
+            }
+        }
+        *last = Some(configuration.clone());
+    }
 }
 
 #[derive(Debug, Error)]
@@ -71,6 +493,12 @@ pub(crate) enum AppError {
     #[error(transparent)]
     DockerError(#[from] bollard::errors::Error),
     #[error(transparent)]
+    SerializationError(#[from] SerializationError),
+    #[error(transparent)]
+    InvalidConfiguration(#[from] ValidationErrors),
+    #[error(transparent)]
+    InvalidQuery(anyhow::Error),
+    #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
@@ -81,6 +509,15 @@ impl IntoResponse for AppError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Internal Docker error: {}", docker_error),
             ),
+            AppError::SerializationError(e) => {
+                tracing::error!("{}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            }
+            AppError::InvalidConfiguration(e) => {
+                tracing::error!("{}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            }
+            AppError::InvalidQuery(e) => (StatusCode::BAD_REQUEST, e.to_string()),
             AppError::Other(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Something went wrong: {}", e),
@@ -91,3 +528,43 @@ impl IntoResponse for AppError {
         (status, Json(json_payload)).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_empty_env_vars() -> anyhow::Result<()> {
+        // Every variable of the README `.env` block but BASE_URL, left empty
+        let names: Vec<&str> = include_str!("../README.md")
+            .lines()
+            .filter_map(|line| line.split_once('=').map(|(name, _)| name))
+            .filter(|name| {
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c == '_')
+            })
+            .filter(|name| *name != "BASE_URL")
+            .collect();
+        assert!(names.contains(&"HTTPS_BASE_URL"));
+
+        for value in ["", " "] {
+            for name in &names {
+                std::env::set_var(name, value);
+            }
+            let docker_options = docker_options();
+            let server_options = server_options();
+            for name in &names {
+                std::env::remove_var(name);
+            }
+
+            assert_eq!(
+                format!("{:?}", docker_options?),
+                format!("{:?}", DockerOptions::default())
+            );
+            assert_eq!(
+                format!("{:?}", server_options?),
+                format!("{:?}", ServerOptions::default())
+            );
+        }
+        Ok(())
+    }
+}
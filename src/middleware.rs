@@ -0,0 +1,356 @@
+use std::collections::{BTreeMap, HashMap};
+
+use itertools::Itertools;
+use serde::de::Error;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::diagnostics::Diagnostics;
+use crate::{parse_bool, parse_list, LabelRegexes};
+
+type MiddlewareName = String;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MiddlewareConfiguration {
+    #[serde(flatten)]
+    pub middleware_type: MiddlewareType,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MiddlewareType {
+    StripPrefix(StripPrefixMiddleware),
+    RedirectScheme(RedirectSchemeMiddleware),
+    Headers(HeadersMiddleware),
+    BasicAuth(BasicAuthMiddleware),
+    #[serde(untagged)]
+    Other(GenericMiddleware),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StripPrefixMiddleware {
+    pub prefixes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_slash: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedirectSchemeMiddleware {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permanent: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadersMiddleware {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_request_headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_response_headers: BTreeMap<String, String>,
+    /// Any other `headers` option, passed through as-is.
+    #[serde(flatten)]
+    pub options: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BasicAuthMiddleware {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub users_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realm: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remove_header: Option<bool>,
+}
+
+/// A middleware type without dedicated support, serialized as `<type_name>: { <options> }`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenericMiddleware {
+    pub type_name: String,
+    pub options: BTreeMap<String, String>,
+}
+
+impl Serialize for GenericMiddleware {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.type_name, &self.options)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for GenericMiddleware {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let middleware = BTreeMap::<String, BTreeMap<String, String>>::deserialize(deserializer)?;
+
+        match middleware.into_iter().exactly_one() {
+            Ok((type_name, options)) => Ok(Self { type_name, options }),
+            Err(_) => Err(D::Error::custom("expected a single middleware type")),
+        }
+    }
+}
+
+impl From<MiddlewareType> for MiddlewareConfiguration {
+    fn from(middleware_type: MiddlewareType) -> Self {
+        Self { middleware_type }
+    }
+}
+
+impl MiddlewareType {
+    fn from_options(type_name: String, options: BTreeMap<String, String>) -> Self {
+        let option = |key: &str| {
+            options
+                .iter()
+                .find(|(option_key, _)| option_key.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value.clone())
+        };
+
+        match type_name.to_ascii_lowercase().as_str() {
+            "stripprefix" => MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                prefixes: option("prefixes")
+                    .map(|value| parse_list(&value))
+                    .unwrap_or_default(),
+                force_slash: option("forceslash").and_then(|value| parse_bool(&value)),
+            }),
+            "redirectscheme" => MiddlewareType::RedirectScheme(RedirectSchemeMiddleware {
+                scheme: option("scheme"),
+                port: option("port"),
+                permanent: option("permanent").and_then(|value| parse_bool(&value)),
+            }),
+            "headers" => {
+                let mut headers = HeadersMiddleware::default();
+                for (key, value) in &options {
+                    match key.split_once('.') {
+                        Some((prefix, header))
+                            if prefix.eq_ignore_ascii_case("customrequestheaders") =>
+                        {
+                            headers
+                                .custom_request_headers
+                                .insert(header.to_owned(), value.clone());
+                        }
+                        Some((prefix, header))
+                            if prefix.eq_ignore_ascii_case("customresponseheaders") =>
+                        {
+                            headers
+                                .custom_response_headers
+                                .insert(header.to_owned(), value.clone());
+                        }
+                        _ => {
+                            headers.options.insert(key.clone(), value.clone());
+                        }
+                    }
+                }
+                MiddlewareType::Headers(headers)
+            }
+            "basicauth" => MiddlewareType::BasicAuth(BasicAuthMiddleware {
+                users: option("users")
+                    .map(|value| parse_list(&value))
+                    .unwrap_or_default(),
+                users_file: option("usersfile"),
+                realm: option("realm"),
+                header_field: option("headerfield"),
+                remove_header: option("removeheader").and_then(|value| parse_bool(&value)),
+            }),
+            _ => MiddlewareType::Other(GenericMiddleware { type_name, options }),
+        }
+    }
+}
+
+/// Collects the middlewares defined by `traefik.http.middlewares.<name>.<type>.<option>` labels.
+pub(crate) fn extract_middlewares(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> BTreeMap<MiddlewareName, MiddlewareConfiguration> {
+    let mut middlewares: BTreeMap<MiddlewareName, (String, BTreeMap<String, String>)> =
+        BTreeMap::new();
+
+    let mut label_keys: Vec<&String> = labels.keys().collect();
+    label_keys.sort();
+
+    for label_key in label_keys {
+        let Some(captures) = regexes.middlewares.captures(label_key) else {
+            continue;
+        };
+        let (name, middleware_type, option) = (&captures[1], &captures[2], &captures[3]);
+
+        let (existing_type, options) = middlewares
+            .entry(name.to_owned())
+            .or_insert_with(|| (middleware_type.to_owned(), BTreeMap::new()));
+
+        if !existing_type.eq_ignore_ascii_case(middleware_type) {
+            diagnostics.report(
+                label_key,
+                format!(
+                    "middleware '{}' is already a '{}' middleware",
+                    name, existing_type
+                ),
+            );
+            continue;
+        }
+
+        options.insert(option.to_owned(), labels[label_key].clone());
+    }
+
+    middlewares
+        .into_iter()
+        .map(|(name, (type_name, options))| {
+            (
+                name,
+                MiddlewareType::from_options(type_name, options).into(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels;
+
+    #[test]
+    fn test_extract_middlewares() {
+        let labels = labels(&[
+            (
+                "traefik.HTTP.Middlewares.strip-api.StripPrefix.Prefixes",
+                "/api,/v1",
+            ),
+            (
+                "traefik.http.middlewares.to-https.redirectscheme.scheme",
+                "https",
+            ),
+            (
+                "traefik.http.middlewares.to-https.redirectscheme.permanent",
+                "true",
+            ),
+            (
+                "traefik.http.middlewares.myheaders.headers.customrequestheaders.X-Foo",
+                "bar",
+            ),
+            (
+                "traefik.http.middlewares.myheaders.headers.stsSeconds",
+                "31536000",
+            ),
+            (
+                "traefik.http.middlewares.auth.basicauth.users",
+                "test:$apr1$H6uskkkW$IgXLP6ewTrSuBkTrqE8wj/",
+            ),
+            ("traefik.http.middlewares.limit.ratelimit.average", "100"),
+            (
+                "traefik.http.routers.to-my-service.rule",
+                "Host(`my-service.my-domain.com`)",
+            ),
+        ]);
+
+        let middlewares =
+            extract_middlewares(&labels, &LabelRegexes::default(), &Diagnostics::default());
+
+        assert_eq!(
+            middlewares.keys().collect::<Vec<_>>(),
+            vec!["auth", "limit", "myheaders", "strip-api", "to-https"]
+        );
+        assert_eq!(
+            middlewares["strip-api"].middleware_type,
+            MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                prefixes: vec!["/api".to_owned(), "/v1".to_owned()],
+                force_slash: None,
+            })
+        );
+        assert_eq!(
+            middlewares["to-https"].middleware_type,
+            MiddlewareType::RedirectScheme(RedirectSchemeMiddleware {
+                scheme: Some("https".to_owned()),
+                port: None,
+                permanent: Some(true),
+            })
+        );
+        assert_eq!(
+            middlewares["myheaders"].middleware_type,
+            MiddlewareType::Headers(HeadersMiddleware {
+                custom_request_headers: [("X-Foo".to_owned(), "bar".to_owned())].into(),
+                custom_response_headers: BTreeMap::new(),
+                options: [("stsSeconds".to_owned(), "31536000".to_owned())].into(),
+            })
+        );
+        assert_eq!(
+            middlewares["auth"].middleware_type,
+            MiddlewareType::BasicAuth(BasicAuthMiddleware {
+                users: vec!["test:$apr1$H6uskkkW$IgXLP6ewTrSuBkTrqE8wj/".to_owned()],
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            middlewares["limit"].middleware_type,
+            MiddlewareType::Other(GenericMiddleware {
+                type_name: "ratelimit".to_owned(),
+                options: [("average".to_owned(), "100".to_owned())].into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_extract_middlewares_conflicting_types() {
+        let labels = labels(&[
+            (
+                "traefik.http.middlewares.mixed.stripprefix.prefixes",
+                "/api",
+            ),
+            (
+                "traefik.http.middlewares.mixed.redirectscheme.scheme",
+                "https",
+            ),
+        ]);
+
+        let middlewares =
+            extract_middlewares(&labels, &LabelRegexes::default(), &Diagnostics::default());
+
+        assert!(matches!(
+            middlewares["mixed"].middleware_type,
+            MiddlewareType::RedirectScheme(_)
+        ));
+    }
+
+    #[test]
+    fn test_yaml_serialize() -> anyhow::Result<()> {
+        let middlewares: BTreeMap<MiddlewareName, MiddlewareConfiguration> = [
+            (
+                "strip-api".to_owned(),
+                MiddlewareType::StripPrefix(StripPrefixMiddleware {
+                    prefixes: vec!["/api".to_owned()],
+                    force_slash: None,
+                })
+                .into(),
+            ),
+            (
+                "limit".to_owned(),
+                MiddlewareType::Other(GenericMiddleware {
+                    type_name: "rateLimit".to_owned(),
+                    options: [("average".to_owned(), "100".to_owned())].into(),
+                })
+                .into(),
+            ),
+        ]
+        .into();
+
+        let expected = r#"limit:
+  rateLimit:
+    average: '100'
+strip-api:
+  stripPrefix:
+    prefixes:
+    - /api
+"#;
+
+        assert_eq!(serde_yaml::to_string(&middlewares)?, expected);
+        Ok(())
+    }
+}
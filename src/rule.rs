@@ -0,0 +1,280 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Matchers accepted in router rules, compared case-insensitively.
+const MATCHERS: &[&str] = &[
+    "ClientIP",
+    "Header",
+    "HeaderRegexp",
+    "Headers",
+    "HeadersRegexp",
+    "Host",
+    "HostHeader",
+    "HostRegexp",
+    "Method",
+    "Path",
+    "PathPrefix",
+    "PathRegexp",
+    "Query",
+    "QueryRegexp",
+    "HostSNI",
+    "HostSNIRegexp",
+    "ALPN",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Matcher(String),
+    Argument(String),
+    OpenParenthesis,
+    CloseParenthesis,
+    Comma,
+    And,
+    Or,
+    Not,
+}
+
+/// Checks the syntax of a router rule: matchers such as ``Host(`a.com`)`` with quoted arguments,
+/// combined with `&&`, `||`, `!` and parentheses.
+///
+/// This only catches syntax errors, the arguments themselves (e.g. regexes) are not validated.
+pub(crate) fn validate_rule(rule: &str) -> Result<(), String> {
+    let tokens = tokenize(rule)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+    };
+
+    if tokens.is_empty() {
+        return Err("rule is empty".to_owned());
+    }
+
+    parser.expression()?;
+
+    match parser.peek() {
+        None => Ok(()),
+        Some(token) => Err(format!("unexpected {:?} after the end of the rule", token)),
+    }
+}
+
+/// Arguments of the `Host` and `HostRegexp` matchers of a router rule, in order of appearance.
+///
+/// Invalid rules have no hostnames.
+pub(crate) fn rule_hostnames(rule: &str) -> Vec<String> {
+    if validate_rule(rule).is_err() {
+        return vec![];
+    }
+    let Ok(tokens) = tokenize(rule) else {
+        return vec![];
+    };
+
+    let mut hostnames = Vec::new();
+    let mut in_host_matcher = false;
+    for token in tokens {
+        match token {
+            Token::Matcher(name) => {
+                in_host_matcher = ["Host", "HostRegexp"]
+                    .iter()
+                    .any(|matcher| matcher.eq_ignore_ascii_case(&name));
+            }
+            Token::Argument(argument) if in_host_matcher => hostnames.push(argument),
+            Token::CloseParenthesis => in_host_matcher = false,
+            _ => {}
+        }
+    }
+
+    hostnames
+}
+
+fn tokenize(rule: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = rule.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::OpenParenthesis),
+            ')' => tokens.push(Token::CloseParenthesis),
+            ',' => tokens.push(Token::Comma),
+            '!' => tokens.push(Token::Not),
+            '&' | '|' => {
+                if chars.next_if(|(_, next)| *next == c).is_none() {
+                    return Err(format!("expected '{}{}' at position {}", c, c, index));
+                }
+                tokens.push(if c == '&' { Token::And } else { Token::Or });
+            }
+            '`' | '"' => {
+                let mut argument = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, next)) if next == c => break,
+                        Some((_, next)) => argument.push(next),
+                        None => {
+                            return Err(format!("unterminated {} quote at position {}", c, index))
+                        }
+                    }
+                }
+                tokens.push(Token::Argument(argument));
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut name = c.to_string();
+                while let Some((_, next)) = chars.next_if(|(_, next)| next.is_ascii_alphanumeric())
+                {
+                    name.push(next);
+                }
+
+                if !MATCHERS
+                    .iter()
+                    .any(|matcher| matcher.eq_ignore_ascii_case(&name))
+                {
+                    return Err(format!("unknown matcher '{}'", name));
+                }
+                tokens.push(Token::Matcher(name));
+            }
+            c => return Err(format!("unexpected '{}' at position {}", c, index)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if *token == expected => Ok(()),
+            Some(token) => Err(format!("expected {:?}, got {:?}", expected, token)),
+            None => Err(format!(
+                "expected {:?} before the end of the rule",
+                expected
+            )),
+        }
+    }
+
+    /// `term (('&&' | '||') term)*`
+    fn expression(&mut self) -> Result<(), String> {
+        self.term()?;
+
+        while matches!(self.peek(), Some(Token::And | Token::Or)) {
+            self.next();
+            self.term()?;
+        }
+
+        Ok(())
+    }
+
+    /// `'!' term | '(' expression ')' | matcher '(' argument (',' argument)* ')'`
+    fn term(&mut self) -> Result<(), String> {
+        match self.next().cloned() {
+            Some(Token::Not) => self.term(),
+            Some(Token::OpenParenthesis) => {
+                self.expression()?;
+                self.expect(Token::CloseParenthesis)
+            }
+            Some(Token::Matcher(name)) => {
+                self.expect(Token::OpenParenthesis)
+                    .map_err(|e| format!("matcher '{}': {}", name, e))?;
+                loop {
+                    match self.next() {
+                        Some(Token::Argument(_)) => {}
+                        Some(token) => {
+                            return Err(format!(
+                                "matcher '{}': expected an argument, got {:?}",
+                                name, token
+                            ))
+                        }
+                        None => {
+                            return Err(format!(
+                                "matcher '{}': expected an argument before the end of the rule",
+                                name
+                            ))
+                        }
+                    }
+                    match self.next() {
+                        Some(Token::Comma) => continue,
+                        Some(Token::CloseParenthesis) => return Ok(()),
+                        Some(token) => {
+                            return Err(format!(
+                                "matcher '{}': expected ',' or ')', got {:?}",
+                                name, token
+                            ))
+                        }
+                        None => {
+                            return Err(format!(
+                                "matcher '{}': missing ')' before the end of the rule",
+                                name
+                            ))
+                        }
+                    }
+                }
+            }
+            Some(token) => Err(format!("expected a matcher, got {:?}", token)),
+            None => Err("expected a matcher before the end of the rule".to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+
+    #[rstest]
+    #[case("Host(`app.example.com`)")]
+    #[case("host(`app.example.com`)")]
+    #[case("Host(`a.example.com`, `b.example.com`)")]
+    #[case("Host(`app.example.com`) && PathPrefix(`/api`)")]
+    #[case("(Host(`a.com`) || Host(`b.com`)) && !Path(`/admin`)")]
+    #[case("HostRegexp(`{subdomain:[a-z]+}.example.com`)")]
+    #[case("Headers(`X-Forwarded-Proto`, `https`)")]
+    #[case("Query(`mobile=true`)")]
+    #[case("Host(\"app.example.com\")")]
+    #[case("HostSNI(`*`)")]
+    fn test_validate_rule_valid(#[case] rule: &str) {
+        assert_eq!(validate_rule(rule), Ok(()));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("Host(`app.example.com)")]
+    #[case("Host(app.example.com)")]
+    #[case("Host(`app.example.com`")]
+    #[case("Host`app.example.com`")]
+    #[case("Host(`a.com`) & Path(`/`)")]
+    #[case("Host(`a.com`) &&")]
+    #[case("Host(`a.com`) Path(`/`)")]
+    #[case("(Host(`a.com`)")]
+    #[case("Hots(`a.com`)")]
+    #[case("Host()")]
+    #[case("Host(`a.com`,)")]
+    fn test_validate_rule_invalid(#[case] rule: &str) {
+        assert!(validate_rule(rule).is_err(), "{}", rule);
+    }
+
+    #[rstest]
+    #[case("Host(`app.example.com`)", &["app.example.com"])]
+    #[case("host(\"app.example.com\")", &["app.example.com"])]
+    #[case("Host(`a.com`, `b.com`) && PathPrefix(`/api`)", &["a.com", "b.com"])]
+    #[case("(Host(`a.com`) || HostRegexp(`{sub:[a-z]+}.b.com`)) && !Path(`/admin`)", &["a.com", "{sub:[a-z]+}.b.com"])]
+    #[case("PathPrefix(`/api`)", &[])]
+    #[case("HostSNI(`db.example.com`)", &[])]
+    #[case("Host(`a.com`", &[])]
+    fn test_rule_hostnames(#[case] rule: &str, #[case] expected: &[&str]) {
+        assert_eq!(rule_hostnames(rule), expected);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::Diagnostics;
+use crate::{parse_bool, parse_list, LabelRegexes};
+
+type ServersTransportName = String;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServersTransportConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insecure_skip_verify: Option<bool>,
+    #[serde(rename = "rootCAs", default, skip_serializing_if = "Vec::is_empty")]
+    pub root_cas: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_idle_conns_per_host: Option<u32>,
+}
+
+/// Collects the servers transports defined by `traefik.http.serverstransports.<name>.<option>` labels.
+pub(crate) fn extract_servers_transports(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> BTreeMap<ServersTransportName, ServersTransportConfiguration> {
+    let mut servers_transports: BTreeMap<ServersTransportName, ServersTransportConfiguration> =
+        BTreeMap::new();
+
+    for (label_key, label_value) in labels {
+        let Some(captures) = regexes.servers_transports.captures(label_key) else {
+            continue;
+        };
+
+        let servers_transport = servers_transports
+            .entry(captures[1].to_owned())
+            .or_default();
+
+        match captures[2].to_ascii_lowercase().as_str() {
+            "servername" => servers_transport.server_name = Some(label_value.clone()),
+            "insecureskipverify" => {
+                servers_transport.insecure_skip_verify = parse_bool(label_value.trim());
+                if servers_transport.insecure_skip_verify.is_none() {
+                    diagnostics.report(
+                        label_key,
+                        format!("expected true or false, got '{}'", label_value),
+                    );
+                }
+            }
+            "rootcas" => servers_transport.root_cas = parse_list(label_value),
+            "maxidleconnsperhost" => match label_value.trim().parse() {
+                Ok(max_idle_conns_per_host) => {
+                    servers_transport.max_idle_conns_per_host = Some(max_idle_conns_per_host)
+                }
+                Err(e) => diagnostics.report(label_key, e),
+            },
+            option => diagnostics.report(
+                label_key,
+                format!("unsupported servers transport option '{}'", option),
+            ),
+        }
+    }
+
+    servers_transports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels;
+
+    #[test]
+    fn test_extract_servers_transports() {
+        let labels = labels(&[
+            (
+                "traefik.http.serverstransports.mytransport.insecureskipverify",
+                "true",
+            ),
+            (
+                "traefik.http.serverstransports.mytransport.serverName",
+                "backend.local",
+            ),
+            (
+                "traefik.http.serverstransports.mytransport.maxidleconnsperhost",
+                "7",
+            ),
+            (
+                "traefik.http.serverstransports.other.rootcas",
+                "/certs/ca.crt,/certs/other-ca.crt",
+            ),
+            (
+                "traefik.http.serverstransports.other.maxidleconnsperhost",
+                "many",
+            ),
+        ]);
+
+        let servers_transports =
+            extract_servers_transports(&labels, &LabelRegexes::default(), &Diagnostics::default());
+
+        assert_eq!(
+            servers_transports["mytransport"],
+            ServersTransportConfiguration {
+                server_name: Some("backend.local".to_owned()),
+                insecure_skip_verify: Some(true),
+                root_cas: vec![],
+                max_idle_conns_per_host: Some(7),
+            }
+        );
+        assert_eq!(
+            servers_transports["other"],
+            ServersTransportConfiguration {
+                root_cas: vec!["/certs/ca.crt".to_owned(), "/certs/other-ca.crt".to_owned()],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_yaml_serialize() -> anyhow::Result<()> {
+        let servers_transport = ServersTransportConfiguration {
+            server_name: Some("backend.local".to_owned()),
+            insecure_skip_verify: Some(true),
+            root_cas: vec!["/certs/ca.crt".to_owned()],
+            max_idle_conns_per_host: Some(7),
+        };
+
+        let expected = r#"serverName: backend.local
+insecureSkipVerify: true
+rootCAs:
+- /certs/ca.crt
+maxIdleConnsPerHost: 7
+"#;
+
+        assert_eq!(serde_yaml::to_string(&servers_transport)?, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::Diagnostics;
+use crate::rule::validate_rule;
+use crate::{
+    capture_labels, label_regex, pair_routers_with_services, parse_bool, parse_list, parse_port,
+    LabelRegexes,
+};
+
+/// TCP label regexes compiled for a label prefix.
+pub(crate) struct TcpLabelRegexes {
+    pub(crate) routers: Regex,
+    router_entrypoints: Regex,
+    router_service: Regex,
+    router_tls: Regex,
+    router_tls_passthrough: Regex,
+    router_tls_cert_resolver: Regex,
+    service_port: Regex,
+}
+
+impl TcpLabelRegexes {
+    pub(crate) fn new(prefix: &str) -> Self {
+        Self {
+            routers: label_regex(prefix, r"tcp\.routers\.([^.]+)\.rule$"),
+            router_entrypoints: label_regex(prefix, r"tcp\.routers\.([^.]+)\.entrypoints$"),
+            router_service: label_regex(prefix, r"tcp\.routers\.([^.]+)\.service$"),
+            router_tls: label_regex(prefix, r"tcp\.routers\.([^.]+)\.tls$"),
+            router_tls_passthrough: label_regex(
+                prefix,
+                r"tcp\.routers\.([^.]+)\.tls\.passthrough$",
+            ),
+            router_tls_cert_resolver: label_regex(
+                prefix,
+                r"tcp\.routers\.([^.]+)\.tls\.certresolver$",
+            ),
+            service_port: label_regex(
+                prefix,
+                r"tcp\.services\.([^.]+)\.loadbalancer\.server\.port$",
+            ),
+        }
+    }
+
+    /// Whether `label_key` is a supported TCP router or service label.
+    pub(crate) fn is_supported(&self, label_key: &str) -> bool {
+        [
+            &self.routers,
+            &self.router_entrypoints,
+            &self.router_service,
+            &self.router_tls,
+            &self.router_tls_passthrough,
+            &self.router_tls_cert_resolver,
+            &self.service_port,
+        ]
+        .iter()
+        .any(|regex| regex.is_match(label_key))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedContainerTcpConfig {
+    pub router_name: String,
+    pub rule: String,
+    pub entrypoints: Vec<String>,
+    pub tls: Option<TraefikedTcpRouterTlsConfig>,
+    /// Service targeted by the router. The container name is used when not declared.
+    pub service_name: Option<String>,
+    /// Container-side port of the service, targeted through the host port it is published on.
+    /// The first public port of the container is used when not declared.
+    pub target_port: Option<u16>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedTcpRouterTlsConfig {
+    pub passthrough: Option<bool>,
+    pub cert_resolver: Option<String>,
+}
+
+/// Whether the labels declare the rule of at least one TCP router.
+pub(crate) fn has_tcp_routers(labels: &HashMap<String, String>, regexes: &LabelRegexes) -> bool {
+    labels
+        .keys()
+        .any(|label_key| regexes.tcp.routers.is_match(label_key))
+}
+
+/// Collects the TCP routers defined by `traefik.tcp.routers.<name>.<option>` labels, sorted by name,
+/// paired with the services defined by `traefik.tcp.services.<name>.loadbalancer.server.port` labels.
+pub(crate) fn extract_tcp_config(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> anyhow::Result<Vec<TraefikedContainerTcpConfig>> {
+    let mut routers: Vec<(String, &String)> = capture_labels(labels, &regexes.tcp.routers)
+        .into_iter()
+        .sorted_by(|a, b| Ord::cmp(&a.0, &b.0))
+        .collect();
+
+    if routers.is_empty() {
+        return Ok(vec![]);
+    }
+
+    routers.retain(|(router_name, rule)| match validate_rule(rule) {
+        Ok(()) => true,
+        Err(e) => {
+            diagnostics.report(
+                regexes.label(&format!("tcp.routers.{}.rule", router_name)),
+                format!("invalid rule '{}': {}", rule, e),
+            );
+            false
+        }
+    });
+
+    if routers.is_empty() {
+        return Err(anyhow!("No TCP router has a valid rule"));
+    }
+
+    let entrypoints = capture_labels(labels, &regexes.tcp.router_entrypoints);
+    let explicit_services = capture_labels(labels, &regexes.tcp.router_service);
+    let tls = capture_labels(labels, &regexes.tcp.router_tls);
+    let passthroughs = capture_labels(labels, &regexes.tcp.router_tls_passthrough);
+    let cert_resolvers = capture_labels(labels, &regexes.tcp.router_tls_cert_resolver);
+    let services: BTreeMap<String, u16> = capture_labels(labels, &regexes.tcp.service_port)
+        .into_iter()
+        .filter_map(|(service_name, value)| {
+            let label = regexes.label(&format!(
+                "tcp.services.{}.loadbalancer.server.port",
+                service_name
+            ));
+            match parse_port(&label, value) {
+                Ok(port) => Some((service_name, port)),
+                Err(error) => {
+                    diagnostics.report(label, error);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    // Without service labels, routers target the container itself on its first public port.
+    let paired_services: Vec<Option<String>> =
+        if services.is_empty() && explicit_services.is_empty() {
+            vec![None; routers.len()]
+        } else if routers.len() == 1 && services.len() == 1 {
+            let (router_name, _) = &routers[0];
+            let service_name = match explicit_services.get(router_name) {
+                Some(service_name) => service_name.to_string(),
+                None => services
+                    .keys()
+                    .next()
+                    .cloned()
+                    .expect("Should have a service"),
+            };
+            vec![Some(service_name)]
+        } else {
+            let router_names: Vec<String> = routers.iter().map(|(name, _)| name.clone()).collect();
+            let service_names: Vec<String> = services.keys().cloned().collect();
+            pair_routers_with_services(&router_names, &explicit_services, &service_names)?
+                .into_iter()
+                .map(Some)
+                .collect()
+        };
+
+    Ok(routers
+        .into_iter()
+        .zip(paired_services)
+        .map(
+            |((router_name, rule), service_name)| TraefikedContainerTcpConfig {
+                entrypoints: entrypoints
+                    .get(&router_name)
+                    .map(|value| parse_list(value))
+                    .unwrap_or_default(),
+                tls: tcp_router_tls_config(
+                    tls.get(&router_name).and_then(|value| parse_bool(value)),
+                    passthroughs
+                        .get(&router_name)
+                        .and_then(|value| parse_bool(value)),
+                    cert_resolvers
+                        .get(&router_name)
+                        .map(|value| value.to_string()),
+                ),
+                target_port: service_name
+                    .as_ref()
+                    .and_then(|service_name| services.get(service_name).copied()),
+                service_name,
+                router_name,
+                rule: rule.clone(),
+            },
+        )
+        .collect())
+}
+
+/// TLS is enabled by `tls=true` or implied by any TLS option, unless explicitly set to `tls=false`.
+fn tcp_router_tls_config(
+    tls: Option<bool>,
+    passthrough: Option<bool>,
+    cert_resolver: Option<String>,
+) -> Option<TraefikedTcpRouterTlsConfig> {
+    let enabled = tls.unwrap_or(passthrough.is_some() || cert_resolver.is_some());
+
+    enabled.then_some(TraefikedTcpRouterTlsConfig {
+        passthrough,
+        cert_resolver,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels;
+
+    #[test]
+    fn test_extract_tcp_config() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.tcp.routers.db.rule", "HostSNI(`*`)"),
+            ("traefik.tcp.routers.db.entrypoints", "postgres"),
+            ("traefik.tcp.services.db.loadbalancer.server.port", "5432"),
+        ]);
+
+        let config =
+            extract_tcp_config(&labels, &LabelRegexes::default(), &Diagnostics::default())?;
+
+        assert_eq!(
+            config,
+            vec![TraefikedContainerTcpConfig {
+                router_name: "db".to_owned(),
+                rule: "HostSNI(`*`)".to_owned(),
+                entrypoints: vec!["postgres".to_owned()],
+                tls: None,
+                service_name: Some("db".to_owned()),
+                target_port: Some(5432),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_tcp_config_without_service() -> anyhow::Result<()> {
+        let labels = labels(&[("traefik.tcp.routers.db.rule", "HostSNI(`*`)")]);
+
+        let config =
+            extract_tcp_config(&labels, &LabelRegexes::default(), &Diagnostics::default())?;
+
+        assert_eq!(config.len(), 1);
+        assert_eq!(config[0].service_name, None);
+        assert_eq!(config[0].target_port, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_tcp_config_multiple_routers() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.tcp.routers.db.rule", "HostSNI(`db.local`)"),
+            ("traefik.tcp.routers.db.service", "postgres"),
+            ("traefik.tcp.routers.cache.rule", "HostSNI(`cache.local`)"),
+            ("traefik.tcp.routers.cache.tls.passthrough", "true"),
+            (
+                "traefik.tcp.services.postgres.loadbalancer.server.port",
+                "5432",
+            ),
+            (
+                "traefik.tcp.services.redis.loadbalancer.server.port",
+                "6379",
+            ),
+        ]);
+
+        let config =
+            extract_tcp_config(&labels, &LabelRegexes::default(), &Diagnostics::default())?;
+
+        assert_eq!(
+            config
+                .iter()
+                .map(|c| (
+                    c.router_name.as_str(),
+                    c.service_name.as_deref(),
+                    c.target_port
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("cache", Some("redis"), Some(6379)),
+                ("db", Some("postgres"), Some(5432)),
+            ]
+        );
+        assert_eq!(
+            config[0].tls,
+            Some(TraefikedTcpRouterTlsConfig {
+                passthrough: Some(true),
+                cert_resolver: None,
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_tcp_config_unpaired_routers() {
+        let labels = labels(&[
+            ("traefik.tcp.routers.db.rule", "HostSNI(`db.local`)"),
+            ("traefik.tcp.routers.cache.rule", "HostSNI(`cache.local`)"),
+            ("traefik.tcp.routers.queue.rule", "HostSNI(`queue.local`)"),
+            (
+                "traefik.tcp.services.postgres.loadbalancer.server.port",
+                "5432",
+            ),
+            (
+                "traefik.tcp.services.redis.loadbalancer.server.port",
+                "6379",
+            ),
+        ]);
+
+        assert!(
+            extract_tcp_config(&labels, &LabelRegexes::default(), &Diagnostics::default()).is_err()
+        );
+    }
+}
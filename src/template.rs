@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use crate::error::ContainerConversionError;
+use crate::LabelRegexes;
+
+/// Values of the `{{ placeholder }}`s that can be used in rule and middleware labels.
+pub(crate) struct TemplateVariables<'a> {
+    pub container_name: &'a str,
+    /// Domain suffix, from the `DOMAIN` setting
+    pub domain: Option<&'a str>,
+}
+
+impl TemplateVariables<'_> {
+    fn get(&self, placeholder: &str) -> Result<&str, String> {
+        match placeholder {
+            "container_name" => Ok(self.container_name),
+            "domain" => self
+                .domain
+                .ok_or_else(|| "placeholder 'domain' requires the DOMAIN setting".to_owned()),
+            _ => Err(format!("unknown placeholder '{}'", placeholder)),
+        }
+    }
+}
+
+/// Replaces every `{{ placeholder }}` in `value`.
+///
+/// Unknown placeholders are errors rather than being left as-is, so that a half-substituted
+/// value never ends up in the configuration.
+pub(crate) fn substitute(value: &str, variables: &TemplateVariables) -> Result<String, String> {
+    let mut substituted = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .map(|end| start + end)
+            .ok_or_else(|| format!("unterminated placeholder in '{}'", value))?;
+
+        substituted.push_str(&rest[..start]);
+        substituted.push_str(variables.get(rest[start + 2..end].trim())?);
+        rest = &rest[end + 2..];
+    }
+
+    substituted.push_str(rest);
+    Ok(substituted)
+}
+
+/// Substitutes the placeholders in the HTTP/TCP router rules and middleware options.
+///
+/// Fails on the first label using an unknown placeholder, so that the container is skipped
+/// rather than exposed with a partially rendered rule.
+pub(crate) fn substitute_labels(
+    labels: HashMap<String, String>,
+    regexes: &LabelRegexes,
+    variables: &TemplateVariables,
+) -> Result<HashMap<String, String>, ContainerConversionError> {
+    labels
+        .into_iter()
+        .map(|(key, value)| {
+            if !(regexes.routers.is_match(&key)
+                || regexes.tcp.routers.is_match(&key)
+                || regexes.middlewares.is_match(&key))
+            {
+                return Ok((key, value));
+            }
+
+            match substitute(&value, variables) {
+                Ok(value) => Ok((key, value)),
+                Err(reason) => {
+                    Err(ContainerConversionError::InvalidPlaceholder { label: key, reason })
+                }
+            }
+        })
+        .collect()
+}
+
+/// Whether `name` can be the name of an environment variable, unlike e.g. the `${1}` capture
+/// group references of `redirectregex` replacements.
+fn is_variable_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replaces every `${VARIABLE}` in `value` by its value in `env`.
+///
+/// Sequences that cannot name a variable are left as-is.
+pub(crate) fn resolve_env_variables(
+    value: &str,
+    env: &HashMap<String, String>,
+) -> Result<String, String> {
+    let mut resolved = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        let name = &rest[start + 2..end];
+
+        resolved.push_str(&rest[..start]);
+        if is_variable_name(name) {
+            let variable = env
+                .get(name)
+                .ok_or_else(|| format!("unknown environment variable '{}'", name))?;
+            resolved.push_str(variable);
+        } else {
+            resolved.push_str(&rest[start..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+/// Whether any label value references an environment variable with `${VARIABLE}`.
+pub(crate) fn has_env_variables(labels: &HashMap<String, String>) -> bool {
+    labels
+        .values()
+        .any(|value| resolve_env_variables(value, &HashMap::new()).is_err())
+}
+
+/// Resolves the `${VARIABLE}`s of every label value from the container environment, given as
+/// `NAME=value` entries.
+///
+/// Fails on the first unknown variable rather than exposing the literal reference.
+pub(crate) fn resolve_env_labels(
+    labels: HashMap<String, String>,
+    env: &[String],
+) -> Result<HashMap<String, String>, ContainerConversionError> {
+    let env: HashMap<String, String> = env
+        .iter()
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect();
+
+    labels
+        .into_iter()
+        .map(|(key, value)| match resolve_env_variables(&value, &env) {
+            Ok(value) => Ok((key, value)),
+            Err(reason) => Err(ContainerConversionError::InvalidPlaceholder { label: key, reason }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+
+    use super::*;
+
+    const VARIABLES: TemplateVariables = TemplateVariables {
+        container_name: "my-app",
+        domain: Some("example.com"),
+    };
+
+    #[rstest]
+    #[case("Host(`app.local`)", "Host(`app.local`)")]
+    #[case(
+        "Host(`{{ container_name }}.example.com`)",
+        "Host(`my-app.example.com`)"
+    )]
+    #[case(
+        "Host(`{{container_name}}.{{ domain }}`)",
+        "Host(`my-app.example.com`)"
+    )]
+    #[case("HostRegexp(`{sub:[a-z]+}.local`)", "HostRegexp(`{sub:[a-z]+}.local`)")]
+    fn test_substitute(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(substitute(value, &VARIABLES), Ok(expected.to_owned()));
+    }
+
+    #[rstest]
+    #[case("Host(`{{ name }}.example.com`)")]
+    #[case("Host(`{{ container_name .example.com`)")]
+    fn test_substitute_invalid(#[case] value: &str) {
+        assert!(substitute(value, &VARIABLES).is_err());
+    }
+
+    #[test]
+    fn test_substitute_labels() -> Result<(), ContainerConversionError> {
+        let labels = HashMap::from([
+            (
+                "traefik.http.routers.app.rule".to_owned(),
+                "Host(`{{ container_name }}.{{ domain }}`)".to_owned(),
+            ),
+            (
+                "traefik.tcp.routers.db.rule".to_owned(),
+                "HostSNI(`db.{{ domain }}`)".to_owned(),
+            ),
+            (
+                "traefik.http.middlewares.redirect.redirectregex.replacement".to_owned(),
+                "https://{{ domain }}/${1}".to_owned(),
+            ),
+            (
+                "traefik.http.routers.app.entrypoints".to_owned(),
+                "{{ domain }}".to_owned(),
+            ),
+        ]);
+
+        let labels = substitute_labels(labels, &LabelRegexes::default(), &VARIABLES)?;
+
+        assert_eq!(
+            labels["traefik.http.routers.app.rule"],
+            "Host(`my-app.example.com`)"
+        );
+        assert_eq!(
+            labels["traefik.tcp.routers.db.rule"],
+            "HostSNI(`db.example.com`)"
+        );
+        assert_eq!(
+            labels["traefik.http.middlewares.redirect.redirectregex.replacement"],
+            "https://example.com/${1}"
+        );
+        assert_eq!(
+            labels["traefik.http.routers.app.entrypoints"],
+            "{{ domain }}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_substitute_labels_unknown_placeholder() {
+        let labels = HashMap::from([(
+            "traefik.http.routers.app.rule".to_owned(),
+            "Host(`{{ container }}.local`)".to_owned(),
+        )]);
+
+        assert!(substitute_labels(labels, &LabelRegexes::default(), &VARIABLES).is_err());
+    }
+
+    #[rstest]
+    #[case("${APP_PORT}", Ok("8080"))]
+    #[case("http://app:${APP_PORT}/${1}", Ok("http://app:8080/${1}"))]
+    #[case(
+        "HostRegexp(`{sub:[a-z]+}.local`)",
+        Ok("HostRegexp(`{sub:[a-z]+}.local`)")
+    )]
+    #[case("${APP_PORT", Ok("${APP_PORT"))]
+    #[case("${OTHER_PORT}", Err("unknown environment variable 'OTHER_PORT'"))]
+    fn test_resolve_env_variables(#[case] value: &str, #[case] expected: Result<&str, &str>) {
+        let env = HashMap::from([("APP_PORT".to_owned(), "8080".to_owned())]);
+
+        assert_eq!(
+            resolve_env_variables(value, &env),
+            expected.map(str::to_owned).map_err(str::to_owned)
+        );
+    }
+
+    #[test]
+    fn test_resolve_env_labels() -> Result<(), ContainerConversionError> {
+        let labels = HashMap::from([(
+            "traefik.http.services.app.loadbalancer.server.port".to_owned(),
+            "${APP_PORT}".to_owned(),
+        )]);
+        assert!(has_env_variables(&labels));
+
+        let labels = resolve_env_labels(labels, &["APP_PORT=8080".to_owned()])?;
+
+        assert_eq!(
+            labels["traefik.http.services.app.loadbalancer.server.port"],
+            "8080"
+        );
+        assert!(resolve_env_labels(labels, &[]).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_substitute_missing_domain() {
+        let variables = TemplateVariables {
+            container_name: "my-app",
+            domain: None,
+        };
+
+        assert!(substitute("Host(`{{ domain }}`)", &variables).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// Certificate served by Traefik, e.g. one signed by an internal CA.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsCertificate {
+    pub cert_file: String,
+    pub key_file: String,
+    /// TLS stores the certificate is added to, the default store when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stores: Vec<String>,
+}
+
+/// Named TLS options, referenced by routers through their `tls.options` label.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsOptions {
+    /// e.g. `VersionTLS12`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cipher_suites: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub curve_preferences: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_auth: Option<TlsClientAuth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sni_strict: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alpn_protocols: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsClientAuth {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ca_files: Vec<String>,
+    /// e.g. `RequireAndVerifyClientCert`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_auth_type: Option<String>,
+}
@@ -0,0 +1,680 @@
+use std::fmt::{Display, Write};
+
+use anyhow::{anyhow, bail};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+/// Serializes `value` to a TOML document, as read by Traefik's file provider.
+///
+/// The value goes through `serde_json::Value` first so flattened enums (e.g. `loadBalancer`) are
+/// already resolved to plain tables. Maps become `[tables]`, sequences of maps `[[arrays of tables]]`
+/// and `None` fields are left out, TOML having no null.
+pub(crate) fn to_string<T: Serialize>(value: &T) -> anyhow::Result<String> {
+    let Value::Object(table) = serde_json::to_value(value)? else {
+        bail!("A TOML document must be a table");
+    };
+
+    let mut output = String::new();
+    write_table(&mut output, &[], &table, false)?;
+    Ok(output)
+}
+
+fn write_table(
+    output: &mut String,
+    path: &[&str],
+    table: &Map<String, Value>,
+    array_item: bool,
+) -> anyhow::Result<()> {
+    // A header applies to every following key/value, so they must all come before nested tables.
+    let (key_values, tables): (Vec<_>, Vec<_>) = table
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .partition(|(_, value)| !is_table(value) && !is_array_of_tables(value));
+
+    // Intermediate tables having only nested tables need no header of their own.
+    let header = if array_item {
+        Some(format!("[[{}]]", dotted_key(path)))
+    } else if !path.is_empty() && (!key_values.is_empty() || tables.is_empty()) {
+        Some(format!("[{}]", dotted_key(path)))
+    } else {
+        None
+    };
+    if let Some(header) = header {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        writeln!(output, "{}", header)?;
+    }
+
+    for (key, value) in key_values {
+        writeln!(output, "{} = {}", format_key(key), inline_value(value)?)?;
+    }
+
+    for (key, value) in tables {
+        let path = [path, &[key.as_str()]].concat();
+        match value {
+            Value::Object(table) => write_table(output, &path, table, false)?,
+            Value::Array(items) => {
+                for item in items {
+                    let Value::Object(table) = item else {
+                        unreachable!("Arrays of tables only contain tables");
+                    };
+                    write_table(output, &path, table, true)?;
+                }
+            }
+            _ => unreachable!("Only tables and arrays of tables are nested"),
+        }
+    }
+
+    Ok(())
+}
+
+fn is_table(value: &Value) -> bool {
+    matches!(value, Value::Object(_))
+}
+
+fn is_array_of_tables(value: &Value) -> bool {
+    matches!(value, Value::Array(items) if !items.is_empty() && items.iter().all(is_table))
+}
+
+fn inline_value(value: &Value) -> anyhow::Result<String> {
+    Ok(match value {
+        Value::Null => return Err(anyhow!("TOML cannot represent null values")),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(value) => value.to_string(),
+        Value::String(value) => format_string(value),
+        Value::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(inline_value)
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ")
+        ),
+        Value::Object(table) => format!(
+            "{{ {} }}",
+            table
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| Ok(format!("{} = {}", format_key(key), inline_value(value)?)))
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ")
+        ),
+    })
+}
+
+fn dotted_key(path: &[&str]) -> String {
+    path.iter()
+        .map(|key| format_key(key))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Keys are left bare when possible, e.g. `to-my-service`, and quoted otherwise, e.g. `"api.v2"`.
+fn format_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if bare {
+        key.to_owned()
+    } else {
+        format_string(key)
+    }
+}
+
+fn format_string(value: &str) -> String {
+    let mut formatted = String::with_capacity(value.len() + 2);
+    formatted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => formatted.push_str("\\\""),
+            '\\' => formatted.push_str("\\\\"),
+            '\n' => formatted.push_str("\\n"),
+            '\r' => formatted.push_str("\\r"),
+            '\t' => formatted.push_str("\\t"),
+            c if c.is_control() => formatted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => formatted.push(c),
+        }
+    }
+    formatted.push('"');
+    formatted
+}
+
+/// Deserializes a TOML document, e.g. a configuration of Traefik's file provider.
+///
+/// The document is parsed into a `serde_json::Value` first, so it goes through the same
+/// `Deserialize` impls as YAML or JSON ones. Tables, arrays of tables, dotted and quoted keys,
+/// strings (multi-line and literal ones included), integers, floats, booleans, arrays and inline
+/// tables are supported. Dates are rejected, JSON having no such type.
+pub(crate) fn from_str<T: DeserializeOwned>(input: &str) -> anyhow::Result<T> {
+    let value = Parser { input, position: 0 }.document()?;
+    Ok(serde_json::from_value(value)?)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    /// Byte offset of the next character to read.
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn document(&mut self) -> anyhow::Result<Value> {
+        let mut root = Map::new();
+        // Table the key/values are added to, going through the last item of arrays of tables
+        let mut current: Vec<String> = vec![];
+
+        loop {
+            self.skip_blank_lines();
+            match self.peek() {
+                None => break,
+                Some('[') => {
+                    self.next();
+                    let array_item = self.eat('[');
+                    let path = self.key()?;
+                    self.expect(']')?;
+                    if array_item {
+                        self.expect(']')?;
+                    }
+                    self.end_of_line()?;
+
+                    let (parent, key) = path.split_at(path.len() - 1);
+                    let parent = self.table_at(&mut root, parent)?;
+                    if array_item {
+                        let items = parent
+                            .entry(key[0].clone())
+                            .or_insert_with(|| Value::Array(vec![]));
+                        let Value::Array(items) = items else {
+                            return Err(self.error(format!("'{}' is not an array", key[0])));
+                        };
+                        items.push(Value::Object(Map::new()));
+                    } else {
+                        self.table_at(parent, key)?;
+                    }
+                    current = path;
+                }
+                Some(_) => {
+                    let path = self.key()?;
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.value()?;
+                    self.end_of_line()?;
+
+                    let table = self.table_at(&mut root, &current)?;
+                    self.insert(table, &path, value)?;
+                }
+            }
+        }
+
+        Ok(Value::Object(root))
+    }
+
+    /// Table at `path` from `table`, created when missing.
+    fn table_at<'m>(
+        &self,
+        table: &'m mut Map<String, Value>,
+        path: &[String],
+    ) -> anyhow::Result<&'m mut Map<String, Value>> {
+        let Some((key, rest)) = path.split_first() else {
+            return Ok(table);
+        };
+        let value = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+        let table = match value {
+            Value::Object(table) => table,
+            Value::Array(items) => match items.last_mut() {
+                Some(Value::Object(table)) => table,
+                _ => return Err(self.error(format!("'{}' is not an array of tables", key))),
+            },
+            _ => return Err(self.error(format!("'{}' is not a table", key))),
+        };
+        self.table_at(table, rest)
+    }
+
+    /// Inserts `value` at the dotted key `path` of `table`, which must not be defined yet.
+    fn insert(
+        &self,
+        table: &mut Map<String, Value>,
+        path: &[String],
+        value: Value,
+    ) -> anyhow::Result<()> {
+        let (parent, key) = path.split_at(path.len() - 1);
+        let table = self.table_at(table, parent)?;
+        if table.contains_key(&key[0]) {
+            return Err(self.error(format!("'{}' is defined twice", key[0])));
+        }
+        table.insert(key[0].clone(), value);
+        Ok(())
+    }
+
+    /// Dotted key, e.g. `http.routers."api.v2"`, and the spaces following it.
+    fn key(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut path = vec![];
+        loop {
+            self.skip_spaces();
+            let key = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let key =
+                        self.take_while(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                    if key.is_empty() {
+                        return Err(self.error("Expected a key"));
+                    }
+                    key.to_owned()
+                }
+            };
+            path.push(key);
+            self.skip_spaces();
+            if !self.eat('.') {
+                return Ok(path);
+            }
+        }
+    }
+
+    fn value(&mut self) -> anyhow::Result<Value> {
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.basic_string()?)),
+            Some('\'') => Ok(Value::String(self.literal_string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) => self.scalar(),
+            None => Err(self.error("Expected a value")),
+        }
+    }
+
+    fn array(&mut self) -> anyhow::Result<Value> {
+        self.expect('[')?;
+        let mut items = vec![];
+        loop {
+            self.skip_blank_lines();
+            if self.eat(']') {
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank_lines();
+            if !self.eat(',') {
+                self.expect(']')?;
+                return Ok(Value::Array(items));
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> anyhow::Result<Value> {
+        self.expect('{')?;
+        let mut table = Map::new();
+        self.skip_spaces();
+        if self.eat('}') {
+            return Ok(Value::Object(table));
+        }
+        loop {
+            let path = self.key()?;
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.value()?;
+            self.insert(&mut table, &path, value)?;
+            self.skip_spaces();
+            if !self.eat(',') {
+                self.expect('}')?;
+                return Ok(Value::Object(table));
+            }
+        }
+    }
+
+    /// Boolean or number, e.g. `true`, `8080`, `0x1F`, `1_000` or `0.5`.
+    fn scalar(&mut self) -> anyhow::Result<Value> {
+        let token = self
+            .take_while(|c| !c.is_whitespace() && !",]}#".contains(c))
+            .to_owned();
+        let number = token.replace('_', "");
+        let (digits, radix) = match number.get(..2) {
+            Some("0x") => (&number[2..], 16),
+            Some("0o") => (&number[2..], 8),
+            Some("0b") => (&number[2..], 2),
+            _ => (number.as_str(), 10),
+        };
+
+        if token == "true" || token == "false" {
+            Ok(Value::Bool(token == "true"))
+        } else if let Ok(integer) = i64::from_str_radix(digits, radix) {
+            Ok(Value::from(integer))
+        } else if token.contains(':') || token.matches('-').count() >= 2 {
+            Err(self.error(format!("Unsupported date '{}'", token)))
+        } else {
+            number
+                .parse::<f64>()
+                .ok()
+                .filter(|float| float.is_finite())
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| self.error(format!("Invalid value '{}'", token)))
+        }
+    }
+
+    fn basic_string(&mut self) -> anyhow::Result<String> {
+        let multi_line = self.eat_str("\"\"\"");
+        if multi_line {
+            // A newline right after the delimiter is not part of the string
+            self.eat('\r');
+            self.eat('\n');
+        } else {
+            self.expect('"')?;
+        }
+
+        let mut string = String::new();
+        loop {
+            if multi_line && self.eat_str("\"\"\"") {
+                return Ok(string);
+            }
+            match self.next() {
+                None => return Err(self.error("Unterminated string")),
+                Some('"') if !multi_line => return Ok(string),
+                Some('\n') if !multi_line => return Err(self.error("Unterminated string")),
+                Some('\\') => match self.next() {
+                    Some('b') => string.push('\u{8}'),
+                    Some('t') => string.push('\t'),
+                    Some('n') => string.push('\n'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('r') => string.push('\r'),
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('u') => string.push(self.unicode_escape(4)?),
+                    Some('U') => string.push(self.unicode_escape(8)?),
+                    // A line ending backslash trims the whitespace up to the next character
+                    Some(c) if multi_line && c.is_whitespace() => {
+                        self.take_while(char::is_whitespace);
+                    }
+                    _ => return Err(self.error("Invalid escape sequence")),
+                },
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, length: usize) -> anyhow::Result<char> {
+        let c = self
+            .input
+            .get(self.position..self.position + length)
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("Invalid unicode escape"))?;
+        self.position += length;
+        Ok(c)
+    }
+
+    fn literal_string(&mut self) -> anyhow::Result<String> {
+        let delimiter = if self.eat_str("'''") {
+            self.eat('\r');
+            self.eat('\n');
+            "'''"
+        } else {
+            self.expect('\'')?;
+            "'"
+        };
+
+        let rest = &self.input[self.position..];
+        let end = rest
+            .find(delimiter)
+            .filter(|end| delimiter == "'''" || !rest[..*end].contains('\n'))
+            .ok_or_else(|| self.error("Unterminated string"))?;
+        self.position += end + delimiter.len();
+        Ok(rest[..end].to_owned())
+    }
+
+    /// Skips the rest of a line, which must be blank or a comment.
+    fn end_of_line(&mut self) -> anyhow::Result<()> {
+        self.skip_spaces();
+        if self.eat('#') {
+            self.take_while(|c| c != '\n');
+        }
+        self.eat('\r');
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            Some(c) => Err(self.error(format!("Unexpected '{}'", c))),
+        }
+    }
+
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.take_while(char::is_whitespace);
+            if !self.eat('#') {
+                return;
+            }
+            self.take_while(|c| c != '\n');
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        self.take_while(|c| c == ' ' || c == '\t');
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &str {
+        let start = self.position;
+        while self.peek().is_some_and(&predicate) {
+            self.next();
+        }
+        &self.input[start..self.position]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.next();
+        }
+        found
+    }
+
+    fn eat_str(&mut self, expected: &str) -> bool {
+        let found = self.input[self.position..].starts_with(expected);
+        if found {
+            self.position += expected.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: char) -> anyhow::Result<()> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(format!("Expected '{}'", expected)))
+        }
+    }
+
+    /// e.g. `Line 3: Expected '='`
+    fn error(&self, message: impl Display) -> anyhow::Error {
+        let line = self.input[..self.position].matches('\n').count() + 1;
+        anyhow!("Line {}: {}", line, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::*;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_to_string() -> anyhow::Result<()> {
+        let value = json!({
+            "http": {
+                "routers": {
+                    "api.v2": {
+                        "entryPoints": ["web", "websecure"],
+                        "rule": "PathPrefix(`/api`) && Header(`X-Name`, `\"a\\b\"`)",
+                        "priority": 10,
+                        "tls": { "certResolver": "letsencrypt" },
+                        "observability": null
+                    }
+                },
+                "services": {
+                    "api": {
+                        "loadBalancer": {
+                            "servers": [
+                                { "url": "http://192.168.1.100:8080/" },
+                                { "url": "http://192.168.1.101:8080/" }
+                            ],
+                            "passHostHeader": false
+                        }
+                    }
+                },
+                "middlewares": {}
+            }
+        });
+
+        let expected = r#"[http.middlewares]
+
+[http.routers."api.v2"]
+entryPoints = ["web", "websecure"]
+priority = 10
+rule = "PathPrefix(`/api`) && Header(`X-Name`, `\"a\\b\"`)"
+
+[http.routers."api.v2".tls]
+certResolver = "letsencrypt"
+
+[http.services.api.loadBalancer]
+passHostHeader = false
+
+[[http.services.api.loadBalancer.servers]]
+url = "http://192.168.1.100:8080/"
+
+[[http.services.api.loadBalancer.servers]]
+url = "http://192.168.1.101:8080/"
+"#;
+
+        assert_eq!(to_string(&value)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_string_array_of_tables_with_nested_tables() -> anyhow::Result<()> {
+        let value = json!({
+            "domains": [
+                { "main": "my-domain.com", "options": { "sans": ["*.my-domain.com"] } }
+            ]
+        });
+
+        let expected = r#"[[domains]]
+main = "my-domain.com"
+
+[domains.options]
+sans = ["*.my-domain.com"]
+"#;
+
+        assert_eq!(to_string(&value)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_string_rejects_non_tables() {
+        assert!(to_string(&json!(["not", "a", "table"])).is_err());
+        assert!(to_string(&json!({ "values": [1, null] })).is_err());
+    }
+
+    #[test]
+    fn test_from_str() -> anyhow::Result<()> {
+        let document = r#"
+# Hand-written configuration
+[http.routers."api.v2"]
+entryPoints = [
+    "web",
+    'websecure', # trailing comma
+]
+priority = 1_0
+rule = "PathPrefix(`/api`) && Header(`X-Name`, `\"a\\b\"`)"
+tls = { certResolver = "letsencrypt", domains = [{ main = "my-domain.com" }] }
+
+[http.services.api.loadBalancer]
+passHostHeader = false
+healthCheck.interval = "10s"
+sticky.cookie.maxAge = 0x10
+
+[[http.services.api.loadBalancer.servers]]
+url = "http://192.168.1.100:8080/"
+weight = 0.5
+
+[[http.services.api.loadBalancer.servers]]
+url = 'http://192.168.1.101:8080/'
+
+[http.middlewares.strip.stripPrefix]
+prefixes = ["""
+/api\
+  /v2""", '''/raw\n''']
+"#;
+
+        let expected = json!({
+            "http": {
+                "routers": {
+                    "api.v2": {
+                        "entryPoints": ["web", "websecure"],
+                        "priority": 10,
+                        "rule": "PathPrefix(`/api`) && Header(`X-Name`, `\"a\\b\"`)",
+                        "tls": {
+                            "certResolver": "letsencrypt",
+                            "domains": [{ "main": "my-domain.com" }]
+                        }
+                    }
+                },
+                "services": {
+                    "api": {
+                        "loadBalancer": {
+                            "passHostHeader": false,
+                            "healthCheck": { "interval": "10s" },
+                            "sticky": { "cookie": { "maxAge": 16 } },
+                            "servers": [
+                                { "url": "http://192.168.1.100:8080/", "weight": 0.5 },
+                                { "url": "http://192.168.1.101:8080/" }
+                            ]
+                        }
+                    }
+                },
+                "middlewares": {
+                    "strip": { "stripPrefix": { "prefixes": ["/api/v2", "/raw\\n"] } }
+                }
+            }
+        });
+
+        assert_eq!(from_str::<Value>(document)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_str_reads_to_string_output() -> anyhow::Result<()> {
+        let value = json!({
+            "http": {
+                "routers": { "api.v2": { "rule": "Host(`a\tb`)", "tls": {} } },
+                "services": {
+                    "api": { "loadBalancer": { "servers": [{ "url": "http://api/" }] } }
+                }
+            },
+            "domains": [{ "main": "my-domain.com", "options": { "sans": ["*.my-domain.com"] } }]
+        });
+
+        assert_eq!(from_str::<Value>(&to_string(&value)?)?, value);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::date("created = 1979-05-27T07:32:00Z")]
+    #[case::duplicate_key("a = 1\na = 2")]
+    #[case::table_redefined_as_value("[a]\nb = 1\n[a.b]")]
+    #[case::unterminated_string("a = \"b")]
+    #[case::missing_value("a =")]
+    #[case::trailing_characters("a = 1 2")]
+    #[case::invalid_escape(r#"a = "\q""#)]
+    fn test_from_str_rejects_invalid_documents(#[case] document: &str) {
+        assert!(from_str::<Value>(document).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::Diagnostics;
+use crate::{
+    capture_labels, label_regex, pair_routers_with_services, parse_list, parse_port, LabelRegexes,
+};
+
+/// UDP label regexes compiled for a label prefix.
+pub(crate) struct UdpLabelRegexes {
+    routers: Regex,
+    router_entrypoints: Regex,
+    router_service: Regex,
+    service_port: Regex,
+}
+
+impl UdpLabelRegexes {
+    pub(crate) fn new(prefix: &str) -> Self {
+        Self {
+            routers: label_regex(prefix, r"udp\.routers\.([^.]+)\.(?:entrypoints|service)$"),
+            router_entrypoints: label_regex(prefix, r"udp\.routers\.([^.]+)\.entrypoints$"),
+            router_service: label_regex(prefix, r"udp\.routers\.([^.]+)\.service$"),
+            service_port: label_regex(
+                prefix,
+                r"udp\.services\.([^.]+)\.loadbalancer\.server\.port$",
+            ),
+        }
+    }
+
+    /// Whether `label_key` is a supported UDP router or service label.
+    pub(crate) fn is_supported(&self, label_key: &str) -> bool {
+        [&self.routers, &self.service_port]
+            .iter()
+            .any(|regex| regex.is_match(label_key))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedContainerUdpConfig {
+    pub router_name: String,
+    pub entrypoints: Vec<String>,
+    /// Service targeted by the router. The container name is used when not declared.
+    pub service_name: Option<String>,
+    /// Container-side port of the service, targeted through the host port it is published on.
+    /// The first public port of the container is used when not declared.
+    pub target_port: Option<u16>,
+}
+
+/// Whether the labels declare at least one UDP router.
+///
+/// UDP routers have no rule, so any `entrypoints` or `service` router label declares one.
+pub(crate) fn has_udp_routers(labels: &HashMap<String, String>, regexes: &LabelRegexes) -> bool {
+    labels
+        .keys()
+        .any(|label_key| regexes.udp.routers.is_match(label_key))
+}
+
+/// Collects the UDP routers defined by `traefik.udp.routers.<name>.<option>` labels, sorted by name,
+/// paired with the services defined by `traefik.udp.services.<name>.loadbalancer.server.port` labels.
+pub(crate) fn extract_udp_config(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> anyhow::Result<Vec<TraefikedContainerUdpConfig>> {
+    let mut router_names: Vec<String> = capture_labels(labels, &regexes.udp.routers)
+        .into_keys()
+        .collect();
+    router_names.sort();
+
+    if router_names.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let entrypoints = capture_labels(labels, &regexes.udp.router_entrypoints);
+    let explicit_services = capture_labels(labels, &regexes.udp.router_service);
+    let services: BTreeMap<String, u16> = capture_labels(labels, &regexes.udp.service_port)
+        .into_iter()
+        .filter_map(|(service_name, value)| {
+            let label = regexes.label(&format!(
+                "udp.services.{}.loadbalancer.server.port",
+                service_name
+            ));
+            match parse_port(&label, value) {
+                Ok(port) => Some((service_name, port)),
+                Err(error) => {
+                    diagnostics.report(label, error);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    // Without service labels, routers target the container itself on its first public port.
+    let paired_services: Vec<Option<String>> =
+        if services.is_empty() && explicit_services.is_empty() {
+            vec![None; router_names.len()]
+        } else if router_names.len() == 1 && services.len() == 1 {
+            let service_name = match explicit_services.get(&router_names[0]) {
+                Some(service_name) => service_name.to_string(),
+                None => services
+                    .keys()
+                    .next()
+                    .cloned()
+                    .expect("Should have a service"),
+            };
+            vec![Some(service_name)]
+        } else {
+            let service_names: Vec<String> = services.keys().cloned().collect();
+            pair_routers_with_services(&router_names, &explicit_services, &service_names)?
+                .into_iter()
+                .map(Some)
+                .collect()
+        };
+
+    Ok(router_names
+        .into_iter()
+        .zip(paired_services)
+        .map(|(router_name, service_name)| TraefikedContainerUdpConfig {
+            entrypoints: entrypoints
+                .get(&router_name)
+                .map(|value| parse_list(value))
+                .unwrap_or_default(),
+            target_port: service_name
+                .as_ref()
+                .and_then(|service_name| services.get(service_name).copied()),
+            service_name,
+            router_name,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels;
+
+    #[test]
+    fn test_extract_udp_config() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.udp.routers.vpn.entrypoints", "wg"),
+            ("traefik.udp.services.vpn.loadbalancer.server.port", "51820"),
+        ]);
+
+        let config =
+            extract_udp_config(&labels, &LabelRegexes::default(), &Diagnostics::default())?;
+
+        assert_eq!(
+            config,
+            vec![TraefikedContainerUdpConfig {
+                router_name: "vpn".to_owned(),
+                entrypoints: vec!["wg".to_owned()],
+                service_name: Some("vpn".to_owned()),
+                target_port: Some(51820),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_udp_config_multiple_routers() -> anyhow::Result<()> {
+        let labels = labels(&[
+            ("traefik.udp.routers.vpn.entrypoints", "wg"),
+            ("traefik.udp.routers.dns.entrypoints", "dns"),
+            ("traefik.udp.routers.dns.service", "resolver"),
+            (
+                "traefik.udp.services.resolver.loadbalancer.server.port",
+                "53",
+            ),
+            (
+                "traefik.udp.services.wireguard.loadbalancer.server.port",
+                "51820",
+            ),
+        ]);
+
+        let config =
+            extract_udp_config(&labels, &LabelRegexes::default(), &Diagnostics::default())?;
+
+        assert_eq!(
+            config
+                .iter()
+                .map(|c| (
+                    c.router_name.as_str(),
+                    c.service_name.as_deref(),
+                    c.target_port
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("dns", Some("resolver"), Some(53)),
+                ("vpn", Some("wireguard"), Some(51820)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_udp_config_ignores_tcp_labels() -> anyhow::Result<()> {
+        let labels = labels(&[("traefik.tcp.routers.db.entrypoints", "postgres")]);
+
+        assert!(!has_udp_routers(&labels, &LabelRegexes::default()));
+        assert_eq!(
+            extract_udp_config(&labels, &LabelRegexes::default(), &Diagnostics::default())?,
+            vec![]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::Diagnostics;
+use crate::LabelRegexes;
+
+type WeightedServiceName = String;
+
+/// Name and weight of each balanced service, by label index.
+type IndexedServices = BTreeMap<usize, (Option<String>, Option<u32>)>;
+
+/// Service balanced by a weighted service, possibly provider-qualified (e.g. `app-v2@file`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraefikedWeightedService {
+    pub name: String,
+    pub weight: Option<u32>,
+}
+
+/// Collects the weighted services defined by
+/// `traefik.http.services.<name>.weighted.services[<index>].<name|weight>` labels,
+/// their services being ordered by index.
+pub(crate) fn extract_weighted_services(
+    labels: &HashMap<String, String>,
+    regexes: &LabelRegexes,
+    diagnostics: &Diagnostics,
+) -> BTreeMap<WeightedServiceName, Vec<TraefikedWeightedService>> {
+    let mut weighted_services: BTreeMap<WeightedServiceName, IndexedServices> = BTreeMap::new();
+
+    for (label_key, label_value) in labels {
+        let Some(captures) = regexes.weighted_services.captures(label_key) else {
+            continue;
+        };
+
+        let Ok(index) = captures[2].parse::<usize>() else {
+            diagnostics.report(
+                label_key,
+                format!("'{}' is not a valid service index", &captures[2]),
+            );
+            continue;
+        };
+
+        let (name, weight) = weighted_services
+            .entry(captures[1].to_owned())
+            .or_default()
+            .entry(index)
+            .or_default();
+
+        match captures[3].to_ascii_lowercase().as_str() {
+            "name" => *name = Some(label_value.trim().to_owned()),
+            "weight" => match label_value.trim().parse::<u32>() {
+                Ok(value) => *weight = Some(value),
+                Err(e) => diagnostics.report(
+                    label_key,
+                    format!("invalid weight '{}': {}", label_value, e),
+                ),
+            },
+            option => diagnostics.report(
+                label_key,
+                format!("unsupported weighted service option '{}'", option),
+            ),
+        }
+    }
+
+    weighted_services
+        .into_iter()
+        .filter_map(|(weighted_service_name, services)| {
+            let services: Vec<TraefikedWeightedService> = services
+                .into_iter()
+                .filter_map(|(index, (name, weight))| match name {
+                    Some(name) if !name.is_empty() => {
+                        Some(TraefikedWeightedService { name, weight })
+                    }
+                    _ => {
+                        diagnostics.report(
+                            regexes.label(&format!(
+                                "http.services.{}.weighted.services[{}].name",
+                                weighted_service_name, index
+                            )),
+                            "missing service name",
+                        );
+                        None
+                    }
+                })
+                .collect();
+
+            (!services.is_empty()).then_some((weighted_service_name, services))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels;
+
+    #[test]
+    fn test_extract_weighted_services() {
+        let labels = labels(&[
+            (
+                "traefik.http.services.canary.weighted.services[1].name",
+                "app-v2",
+            ),
+            (
+                "traefik.http.services.canary.weighted.services[1].weight",
+                "10",
+            ),
+            (
+                "traefik.http.services.canary.weighted.services[0].name",
+                "app-v1@file",
+            ),
+            (
+                "traefik.http.services.canary.weighted.services[0].Weight",
+                "90",
+            ),
+            ("traefik.http.services.app.loadbalancer.server.port", "80"),
+        ]);
+        let diagnostics = Diagnostics::default();
+
+        let weighted_services =
+            extract_weighted_services(&labels, &LabelRegexes::default(), &diagnostics);
+
+        assert_eq!(
+            weighted_services,
+            BTreeMap::from([(
+                "canary".to_owned(),
+                vec![
+                    TraefikedWeightedService {
+                        name: "app-v1@file".to_owned(),
+                        weight: Some(90),
+                    },
+                    TraefikedWeightedService {
+                        name: "app-v2".to_owned(),
+                        weight: Some(10),
+                    },
+                ]
+            )])
+        );
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_extract_weighted_services_reports_invalid_labels() {
+        let labels = labels(&[
+            (
+                "traefik.http.services.canary.weighted.services[0].name",
+                "app-v1",
+            ),
+            (
+                "traefik.http.services.canary.weighted.services[0].weight",
+                "heavy",
+            ),
+            (
+                "traefik.http.services.canary.weighted.services[1].weight",
+                "10",
+            ),
+            (
+                "traefik.http.services.canary.weighted.services[x].name",
+                "app-v3",
+            ),
+        ]);
+        let diagnostics = Diagnostics::default();
+
+        let weighted_services =
+            extract_weighted_services(&labels, &LabelRegexes::default(), &diagnostics);
+
+        assert_eq!(
+            weighted_services["canary"],
+            vec![TraefikedWeightedService {
+                name: "app-v1".to_owned(),
+                weight: None,
+            }]
+        );
+        assert_eq!(diagnostics.into_errors().len(), 3);
+    }
+}